@@ -0,0 +1,4 @@
+ALTER TABLE groups_users
+DROP COLUMN role;
+
+DROP TYPE group_role;
@@ -0,0 +1,9 @@
+CREATE TYPE group_role AS ENUM ('owner', 'admin', 'member');
+
+ALTER TABLE groups_users
+ADD COLUMN role group_role NOT NULL DEFAULT 'member';
+
+UPDATE groups_users gu
+SET role = 'owner'
+FROM groups g
+WHERE g.id = gu.group_id AND g.owner = gu.user_id;
@@ -0,0 +1 @@
+ALTER TABLE talks DROP COLUMN pinned_message_id;
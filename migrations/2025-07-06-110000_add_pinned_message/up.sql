@@ -0,0 +1,2 @@
+-- one message per talk can be pinned above the message list
+ALTER TABLE talks ADD COLUMN pinned_message_id UUID REFERENCES messages (id) ON DELETE SET NULL;
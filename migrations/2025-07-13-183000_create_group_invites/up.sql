@@ -0,0 +1,14 @@
+CREATE TABLE group_invites (
+    token TEXT PRIMARY KEY,
+    group_id UUID NOT NULL,
+    created_by UUID NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ,
+    max_uses INTEGER,
+    uses INTEGER NOT NULL DEFAULT 0,
+    FOREIGN KEY (group_id) REFERENCES groups (id) ON DELETE CASCADE,
+    FOREIGN KEY (created_by) REFERENCES users (id) ON DELETE CASCADE,
+    CHECK (max_uses IS NULL OR max_uses > 0)
+);
+
+CREATE INDEX idx_group_invites_group_id ON group_invites (group_id);
@@ -0,0 +1 @@
+DROP TABLE talk_preferences;
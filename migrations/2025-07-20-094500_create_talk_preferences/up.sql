@@ -0,0 +1,12 @@
+CREATE TABLE talk_preferences (
+    talk_id UUID,
+    user_id UUID,
+    archived BOOLEAN NOT NULL DEFAULT FALSE,
+    pinned BOOLEAN NOT NULL DEFAULT FALSE,
+    muted_until TIMESTAMPTZ,
+    FOREIGN KEY (talk_id) REFERENCES talks (id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE,
+    PRIMARY KEY (talk_id, user_id)
+);
+
+CREATE INDEX idx_talk_preferences_user_id ON talk_preferences (user_id);
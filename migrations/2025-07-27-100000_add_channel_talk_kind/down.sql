@@ -0,0 +1,6 @@
+DELETE FROM talks WHERE kind = 'channel';
+
+ALTER TYPE talk_kind RENAME TO talk_kind_old;
+CREATE TYPE talk_kind AS ENUM ('group', 'chat');
+ALTER TABLE talks ALTER COLUMN kind TYPE talk_kind USING kind::text::talk_kind;
+DROP TYPE talk_kind_old;
@@ -0,0 +1 @@
+ALTER TYPE talk_kind ADD VALUE 'channel';
@@ -0,0 +1,3 @@
+DELETE FROM messages WHERE system;
+
+ALTER TABLE messages DROP COLUMN system;
@@ -0,0 +1,2 @@
+ALTER TABLE contacts DROP COLUMN created_at;
+ALTER TABLE contacts DROP COLUMN note;
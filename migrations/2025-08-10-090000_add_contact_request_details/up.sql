@@ -0,0 +1,2 @@
+ALTER TABLE contacts ADD COLUMN note TEXT;
+ALTER TABLE contacts ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT now();
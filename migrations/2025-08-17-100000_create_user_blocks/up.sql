@@ -0,0 +1,11 @@
+CREATE TABLE user_blocks (
+    blocker UUID NOT NULL,
+    blocked UUID NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (blocker, blocked),
+    FOREIGN KEY (blocker) REFERENCES users (id) ON DELETE CASCADE,
+    FOREIGN KEY (blocked) REFERENCES users (id) ON DELETE CASCADE,
+    CHECK (blocker != blocked)
+);
+
+CREATE INDEX user_blocks_blocked_idx ON user_blocks (blocked);
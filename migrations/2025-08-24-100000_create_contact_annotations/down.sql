@@ -0,0 +1,2 @@
+DROP TABLE contact_labels;
+DROP TABLE contact_aliases;
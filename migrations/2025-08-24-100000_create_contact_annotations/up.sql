@@ -0,0 +1,17 @@
+CREATE TABLE contact_aliases (
+    owner UUID NOT NULL,
+    user_id UUID NOT NULL,
+    alias TEXT NOT NULL,
+    PRIMARY KEY (owner, user_id),
+    FOREIGN KEY (owner) REFERENCES users (id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+);
+
+CREATE TABLE contact_labels (
+    owner UUID NOT NULL,
+    user_id UUID NOT NULL,
+    label TEXT NOT NULL,
+    PRIMARY KEY (owner, user_id, label),
+    FOREIGN KEY (owner) REFERENCES users (id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+);
@@ -0,0 +1,3 @@
+ALTER TABLE users
+    DROP COLUMN name_overridden,
+    DROP COLUMN nickname_overridden;
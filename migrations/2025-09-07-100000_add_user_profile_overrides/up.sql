@@ -0,0 +1,4 @@
+-- fields edited in the profile are no longer synced from the IdP
+ALTER TABLE users
+    ADD COLUMN name_overridden BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN nickname_overridden BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,5 @@
+ALTER TABLE users
+    DROP COLUMN presence,
+    DROP COLUMN last_seen;
+
+DROP TYPE user_presence;
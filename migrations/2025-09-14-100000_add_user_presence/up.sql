@@ -0,0 +1,5 @@
+CREATE TYPE user_presence AS ENUM ('available', 'away', 'dnd', 'invisible');
+
+ALTER TABLE users
+    ADD COLUMN presence user_presence NOT NULL DEFAULT 'available',
+    ADD COLUMN last_seen TIMESTAMPTZ;
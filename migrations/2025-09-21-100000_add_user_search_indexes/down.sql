@@ -0,0 +1,3 @@
+DROP INDEX users_email_lower_idx;
+DROP INDEX users_name_trgm_idx;
+DROP INDEX users_nickname_trgm_idx;
@@ -0,0 +1,10 @@
+-- Placeholder that takes over the messages and chats of deleted accounts
+INSERT INTO users (id, sub, nickname, name, picture, email)
+VALUES (
+    '00000000-0000-0000-0000-000000000000',
+    'deleted|user',
+    'deleted',
+    'Deleted user',
+    '/static/deleted-user.svg',
+    'deleted@localhost'
+);
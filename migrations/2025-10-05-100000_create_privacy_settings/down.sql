@@ -0,0 +1,3 @@
+DROP TABLE privacy_settings;
+
+DROP TYPE audience;
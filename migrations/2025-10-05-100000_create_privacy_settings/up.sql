@@ -0,0 +1,11 @@
+CREATE TYPE audience AS ENUM ('everyone', 'contacts_of_contacts', 'contacts', 'nobody');
+
+CREATE TABLE privacy_settings (
+    user_id UUID PRIMARY KEY,
+    discoverable_by audience NOT NULL DEFAULT 'everyone',
+    contact_requests_from audience NOT NULL DEFAULT 'everyone',
+    group_adds_from audience NOT NULL DEFAULT 'everyone',
+    share_presence BOOLEAN NOT NULL DEFAULT true,
+    share_read_receipts BOOLEAN NOT NULL DEFAULT true,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+);
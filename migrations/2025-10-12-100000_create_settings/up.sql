@@ -0,0 +1,13 @@
+CREATE TYPE theme AS ENUM ('light', 'dark', 'system');
+
+CREATE TABLE settings (
+    user_id UUID PRIMARY KEY,
+    theme theme NOT NULL DEFAULT 'system',
+    time_zone TEXT NOT NULL DEFAULT 'UTC',
+    locale TEXT NOT NULL DEFAULT 'en',
+    enter_to_send BOOLEAN NOT NULL DEFAULT true,
+    notify_messages BOOLEAN NOT NULL DEFAULT true,
+    notify_talks BOOLEAN NOT NULL DEFAULT true,
+    notify_contact_requests BOOLEAN NOT NULL DEFAULT true,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+);
@@ -0,0 +1 @@
+DROP INDEX users_nickname_lower_idx;
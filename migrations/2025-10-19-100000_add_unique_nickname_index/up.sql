@@ -0,0 +1,10 @@
+-- of nicknames clashing case-insensitively one is kept, the others get an id suffix
+UPDATE users u
+SET nickname = u.nickname || '-' || left(u.id::text, 8)
+FROM (
+    SELECT id, row_number() OVER (PARTITION BY lower(nickname) ORDER BY id) AS n
+    FROM users
+) d
+WHERE d.id = u.id AND d.n > 1;
+
+CREATE UNIQUE INDEX users_nickname_lower_idx ON users (lower(nickname));
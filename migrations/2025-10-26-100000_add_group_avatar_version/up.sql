@@ -0,0 +1,2 @@
+-- bumped on every avatar change, so that urls of the old one stop matching
+ALTER TABLE groups ADD COLUMN avatar_version BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1,16 @@
+-- one saved messages chat per user, so that concurrent requests cannot create two
+ALTER TABLE chats ADD COLUMN saved_by UUID UNIQUE REFERENCES users (id) ON DELETE CASCADE;
+
+-- duplicates left by earlier races stay detached, the one with messages is preferred
+UPDATE chats c
+SET saved_by = s.user_id
+FROM (
+    SELECT DISTINCT ON (cu.user_id) cu.chat_id, cu.user_id
+    FROM chats_users cu
+    JOIN talks t ON t.id = cu.chat_id
+    WHERE NOT EXISTS (
+        SELECT 1 FROM chats_users o WHERE o.chat_id = cu.chat_id AND o.user_id != cu.user_id
+    )
+    ORDER BY cu.user_id, t.last_message_id IS NULL, cu.chat_id
+) s
+WHERE c.id = s.chat_id;
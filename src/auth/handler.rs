@@ -26,14 +26,18 @@ pub(super) mod pages {
 }
 
 pub(super) mod api {
-    use crate::auth::{self, Code, Csrf, Session};
+    use crate::{
+        auth::{self, Code, Csrf, ReturnTo, Session},
+        user,
+    };
     use axum::{
         extract::State,
         response::{IntoResponse, Redirect},
     };
     use axum_extra::extract::cookie::{self, Cookie};
     use axum_extra::extract::{CookieJar, Query};
-    use log::debug;
+    use log::{debug, error};
+    use messenger_service::AsStr;
     use serde::Deserialize;
 
     pub async fn sso_login(auth_service: State<auth::Service>) -> impl IntoResponse {
@@ -64,12 +68,23 @@ pub(super) mod api {
     pub async fn callback(
         Query(params): Query<Params>,
         auth_service: State<auth::Service>,
+        user_service: State<user::Service>,
         jar: CookieJar,
     ) -> crate::Result<impl IntoResponse> {
         let (token, ttl) = auth_service
             .exchange_code(params.code, params.state)
             .await?;
 
+        // a stale profile is no reason to refuse the login
+        match auth_service.get_user_info(token.secret()).await {
+            Ok(ui) => {
+                if let Err(e) = user_service.sync(&ui).await {
+                    error!("Failed to sync {:?} with IdP: {e:?}", ui.sub());
+                }
+            }
+            Err(e) => error!("Failed to fetch user info: {e:?}"),
+        }
+
         let sid = Session::new(uuid::Uuid::new_v4().to_string());
         debug!("Initializing session {sid:?}");
         auth_service.cache_token(&sid, token.secret(), &ttl).await;
@@ -82,6 +97,12 @@ pub(super) mod api {
             sid
         };
 
-        Ok((jar.add(sid), Redirect::to("/")))
+        let return_to = jar
+            .get(ReturnTo::ID)
+            .and_then(|c| ReturnTo::new(c.value()))
+            .map_or_else(|| "/".to_string(), |r| r.as_str().to_string());
+        let jar = jar.remove(Cookie::build(ReturnTo::ID).path("/"));
+
+        Ok((jar.add(sid), Redirect::to(&return_to)))
     }
 }
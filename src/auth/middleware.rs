@@ -1,5 +1,6 @@
 use axum::{
     extract::{Request, State},
+    http::{Method, header},
     middleware::Next,
     response::{IntoResponse, Redirect, Response},
 };
@@ -7,9 +8,10 @@ use axum_extra::extract::CookieJar;
 use log::debug;
 use oauth2::AccessToken;
 
+use crate::settings;
 use crate::user::{self, model::UserDto};
 use crate::{
-    auth::{self, Session},
+    auth::{self, ReturnTo, Session},
     user::Sub,
 };
 
@@ -39,12 +41,19 @@ pub async fn validate_sid(
 
 pub async fn authorize(
     user_service: State<user::Service>,
+    settings_service: State<settings::Service>,
     auth_service: State<auth::Service>,
     jar: CookieJar,
     mut req: Request,
     next: Next,
 ) -> crate::Result<Response> {
     if jar.get(super::Session::ID).is_none() {
+        if is_page_request(&req)
+            && let Some(rt) = ReturnTo::new(req.uri().to_string())
+        {
+            debug!("Remembering {rt:?} until login");
+            return Ok((jar.add(rt), Redirect::to("/login")).into_response());
+        }
         return Ok(Redirect::to("/login").into_response());
     }
 
@@ -63,8 +72,20 @@ pub async fn authorize(
         Err(e) => return Err(e.into()),
     };
 
-    let auth_user = auth::User::from(user_dto);
+    let settings = settings_service.find(user_dto.id()).await?;
+    let auth_user = auth::User::from(user_dto).with_settings(settings);
     req.extensions_mut().insert(auth_user);
 
     Ok(next.run(req).await)
 }
+
+// full page navigations only, htmx requests and event streams are not worth returning to
+fn is_page_request(req: &Request) -> bool {
+    let headers = req.headers();
+    req.method() == Method::GET
+        && !headers.contains_key("HX-Request")
+        && headers
+            .get(header::ACCEPT)
+            .and_then(|a| a.to_str().ok())
+            .is_some_and(|a| a.contains("text/html"))
+}
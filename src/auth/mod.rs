@@ -1,12 +1,13 @@
 use std::fmt;
 use std::sync::Arc;
 
+use crate::settings::model::Settings;
 use crate::user::model::UserDto;
 use crate::user::{self, Email, Picture, Sub};
 use crate::{state::AppServices, user::Nickname};
 use axum::Router;
 use axum::routing::get;
-use axum_extra::extract::cookie::Cookie;
+use axum_extra::extract::cookie::{self, Cookie};
 use log::error;
 use messenger_service::{AsStr, Redact};
 use serde::Deserialize;
@@ -45,6 +46,7 @@ pub struct User {
     nickname: Nickname,
     name: String,
     picture: Picture,
+    settings: Settings,
 }
 
 impl User {
@@ -55,15 +57,22 @@ impl User {
         name: impl Into<String>,
         picture: Picture,
     ) -> Self {
+        let settings = Settings::default_for(&id);
         Self {
             id,
             sub,
             nickname,
             name: name.into(),
             picture,
+            settings,
         }
     }
 
+    pub fn with_settings(mut self, settings: Settings) -> Self {
+        self.settings = settings;
+        self
+    }
+
     pub const fn id(&self) -> &user::Id {
         &self.id
     }
@@ -83,6 +92,10 @@ impl User {
     pub const fn picture(&self) -> &Picture {
         &self.picture
     }
+
+    pub const fn settings(&self) -> &Settings {
+        &self.settings
+    }
 }
 
 impl From<UserDto> for User {
@@ -93,6 +106,7 @@ impl From<UserDto> for User {
             nickname: u.nickname().clone(),
             name: u.name().to_string(),
             picture: u.picture().clone(),
+            settings: Settings::default_for(u.id()),
         }
     }
 }
@@ -211,6 +225,36 @@ impl From<Session> for Cookie<'_> {
     }
 }
 
+/// Page requested before login, restored once the callback succeeds.
+#[derive(Debug)]
+pub struct ReturnTo(String);
+
+impl ReturnTo {
+    const ID: &str = "return_to";
+
+    /// Only local paths are accepted to avoid open redirects.
+    pub fn new(path: impl Into<String>) -> Option<Self> {
+        let path = path.into();
+        (path.starts_with('/') && !path.starts_with("//")).then_some(Self(path))
+    }
+}
+
+impl AsStr for ReturnTo {
+    fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<ReturnTo> for Cookie<'_> {
+    fn from(r: ReturnTo) -> Self {
+        Cookie::build((ReturnTo::ID, r.0))
+            .path("/")
+            .http_only(true)
+            .same_site(cookie::SameSite::Lax)
+            .build()
+    }
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error("unauthorized to access the resource")]
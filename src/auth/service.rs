@@ -37,6 +37,9 @@ pub trait AuthService {
     async fn invalidate_token(&self, sid: &Session) -> super::Result<()>;
 
     async fn find_token(&self, sid: &Session) -> Option<String>;
+
+    /// Ends every session of the subject, e.g. on other devices.
+    async fn revoke_sessions(&self, sub: &Sub);
 }
 
 #[derive(Clone)]
@@ -185,6 +188,15 @@ impl AuthService for AuthServiceImpl {
         self.redis
             .set_ex_explicit(cache::Key::Session(sid), token, ttl)
             .await;
+
+        match self.validate(token).await {
+            Ok(sub) => {
+                let key = cache::Key::Sessions(&sub);
+                self.redis.sadd(key.clone(), sid.as_str()).await;
+                self.redis.expire(key).await;
+            }
+            Err(e) => warn!("Failed to index {sid:?} by subject: {e:?}"),
+        }
     }
 
     async fn invalidate_token(&self, sid: &Session) -> super::Result<()> {
@@ -204,6 +216,24 @@ impl AuthService for AuthServiceImpl {
     async fn find_token(&self, sid: &Session) -> Option<String> {
         self.redis.get::<String>(cache::Key::Session(sid)).await
     }
+
+    async fn revoke_sessions(&self, sub: &Sub) {
+        let key = cache::Key::Sessions(sub);
+        let sids = self
+            .redis
+            .smembers::<Vec<String>>(key.clone())
+            .await
+            .unwrap_or_default();
+
+        for sid in sids {
+            let sid = Session::new(sid);
+            if let Err(e) = self.invalidate_token(&sid).await {
+                error!("Failed to revoke {sid:?}: {e:?}");
+            }
+        }
+
+        self.redis.del(key).await;
+    }
 }
 
 impl AuthServiceImpl {
@@ -1,22 +1,27 @@
 pub(super) mod api {
 
+    use std::collections::HashSet;
+
     use axum::{
         Extension, Form,
-        extract::{Path, Query, State},
+        extract::{Multipart, Path, Query, State},
         http::StatusCode,
+        response::IntoResponse,
     };
     use maud::{Markup, Render};
+    use messenger_service::AsStr;
     use serde::Deserialize;
 
     use crate::{
         auth,
-        contact::{self, StatusTransition, Transition},
+        contact::{self, IMPORT_MAX_ADDRESSES, StatusTransition, Transition},
         user,
     };
 
     #[derive(Deserialize)]
     pub struct CreateParams {
         user_id: user::Id,
+        note: Option<String>,
     }
 
     pub async fn create(
@@ -24,10 +29,151 @@ pub(super) mod api {
         contact_service: State<contact::Service>,
         params: Form<CreateParams>,
     ) -> crate::Result<Markup> {
-        let s = contact_service.add(auth_user.id(), &params.user_id).await?;
+        let s = contact_service
+            .add(&auth_user, &params.user_id, params.note.as_deref())
+            .await?;
         Ok(s.render())
     }
 
+    /// Accepts pasted addresses (`emails`) and/or a CSV upload (`file`).
+    pub async fn import(
+        auth_user: Extension<auth::User>,
+        user_service: State<user::Service>,
+        contact_service: State<contact::Service>,
+        mut multipart: Multipart,
+    ) -> crate::Result<Markup> {
+        let mut input = String::new();
+        while let Some(field) = multipart
+            .next_field()
+            .await
+            .map_err(|e| contact::Error::InvalidImport(e.body_text()))?
+        {
+            if matches!(field.name(), Some("emails" | "file")) {
+                let text = field
+                    .text()
+                    .await
+                    .map_err(|e| contact::Error::InvalidImport(e.body_text()))?;
+                input.push('\n');
+                input.push_str(&text);
+            }
+        }
+
+        let addresses = split_addresses(&input);
+        if addresses.len() > IMPORT_MAX_ADDRESSES {
+            return Err(contact::Error::TooManyAddresses(addresses.len()).into());
+        }
+
+        let mut invalid = Vec::new();
+        let mut emails = Vec::with_capacity(addresses.len());
+        for a in addresses {
+            match user::Email::try_from(a) {
+                Ok(e) => emails.push(e),
+                Err(_) => invalid.push(a.to_string()),
+            }
+        }
+
+        // users hiding from the importer are reported as unknown
+        let users = user_service.find_by_emails(&emails, auth_user.id())?;
+        let matches = emails
+            .iter()
+            .map(|e| {
+                let u = users
+                    .iter()
+                    .find(|u| u.email().as_str().eq_ignore_ascii_case(e.as_str()));
+                (e.as_str().to_string(), u.map(|u| u.id().clone()))
+            })
+            .collect::<Vec<_>>();
+
+        let mut report = contact_service.import(&auth_user, &matches).await?;
+        report.invalid = invalid;
+
+        Ok(report.render())
+    }
+
+    /// Splits pasted text or CSV into unique address candidates,
+    /// cells without an `@` (headers, names) are skipped.
+    fn split_addresses(input: &str) -> Vec<&str> {
+        let mut seen = HashSet::new();
+
+        input
+            .split(|c: char| c.is_whitespace() || matches!(c, ',' | ';'))
+            .map(|a| a.trim_matches(|c| matches!(c, '"' | '\'' | '<' | '>')))
+            .filter(|a| a.contains('@'))
+            .filter(|a| seen.insert(a.to_lowercase()))
+            .collect()
+    }
+
+    #[derive(Deserialize)]
+    pub struct BlockParams {
+        user_id: user::Id,
+    }
+
+    pub async fn block(
+        auth_user: Extension<auth::User>,
+        contact_service: State<contact::Service>,
+        params: Form<BlockParams>,
+    ) -> crate::Result<impl IntoResponse> {
+        contact_service
+            .block_user(auth_user.id(), &params.user_id)
+            .await?;
+
+        Ok((
+            [("HX-Trigger", contact::markup::REQUESTS_CHANGED_EVENT)],
+            contact::markup::Blocked.render(),
+        ))
+    }
+
+    pub async fn unblock(
+        auth_user: Extension<auth::User>,
+        contact_service: State<contact::Service>,
+        Path(user_id): Path<user::Id>,
+    ) -> crate::Result<()> {
+        contact_service.unblock_user(auth_user.id(), &user_id)?;
+        Ok(())
+    }
+
+    #[derive(Deserialize)]
+    pub struct AnnotateParams {
+        alias: String,
+        labels: String,
+    }
+
+    pub async fn annotate(
+        auth_user: Extension<auth::User>,
+        contact_service: State<contact::Service>,
+        Path(user_id): Path<user::Id>,
+        params: Form<AnnotateParams>,
+    ) -> crate::Result<impl IntoResponse> {
+        let mut seen = HashSet::new();
+        let labels = params
+            .labels
+            .split(',')
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .filter(|l| seen.insert(*l))
+            .collect::<Vec<_>>();
+
+        contact_service
+            .annotate(auth_user.id(), &user_id, &params.alias, &labels)
+            .await?;
+
+        Ok([("HX-Trigger", contact::markup::ANNOTATED_EVENT)])
+    }
+
+    pub async fn count_requests(
+        auth_user: Extension<auth::User>,
+        contact_service: State<contact::Service>,
+    ) -> crate::Result<Markup> {
+        let incoming = contact_service
+            .find_by_user_id(auth_user.id())
+            .await?
+            .iter()
+            .filter(|c| c.is_incoming())
+            .count();
+
+        Ok(contact::markup::RequestsCount(incoming).render())
+    }
+
     pub async fn delete(
         auth_user: Extension<auth::User>,
         user_id: Query<user::Id>,
@@ -41,7 +187,7 @@ pub(super) mod api {
         Extension(auth_user): Extension<auth::User>,
         Path((id, transition)): Path<(contact::Id, Transition)>,
         contact_service: State<contact::Service>,
-    ) -> crate::Result<Markup> {
+    ) -> crate::Result<impl IntoResponse> {
         let auth_id = auth_user.id();
         let st = match transition {
             Transition::Accept => StatusTransition::Accept { responder: auth_id },
@@ -60,7 +206,10 @@ pub(super) mod api {
             .transition_status(auth_user.id(), &id, st)
             .await?;
 
-        Ok(contact::markup::Icons::new(&id, &new_status, &auth_user).render())
+        Ok((
+            [("HX-Trigger", contact::markup::REQUESTS_CHANGED_EVENT)],
+            contact::markup::Icons::new(&id, &new_status, auth_user.id()).render(),
+        ))
     }
 
     impl From<contact::Error> for StatusCode {
@@ -68,13 +217,97 @@ pub(super) mod api {
             match e {
                 contact::Error::NotFound(_) => Self::NOT_FOUND,
                 contact::Error::AlreadyExists => Self::CONFLICT,
-                contact::Error::SameUsers(_) | contact::Error::StatusTransitionFailed => {
-                    Self::BAD_REQUEST
-                }
+                contact::Error::SameUsers(_)
+                | contact::Error::StatusTransitionFailed
+                | contact::Error::NoteTooLong(_)
+                | contact::Error::InvalidImport(_)
+                | contact::Error::TooManyAddresses(_)
+                | contact::Error::AliasTooLong(_)
+                | contact::Error::InvalidLabel(_) => Self::BAD_REQUEST,
+                contact::Error::Blocked(_)
+                | contact::Error::NotContact(_)
+                | contact::Error::RequestsRestricted(_) => Self::FORBIDDEN,
                 contact::Error::_R2d2(_) | contact::Error::_Diesel(_) => {
                     Self::INTERNAL_SERVER_ERROR
                 }
             }
         }
     }
+
+    #[cfg(test)]
+    mod test {
+        use super::split_addresses;
+
+        #[test]
+        fn should_skip_csv_header_and_names() {
+            let input = "name,email\nIon Popescu,ion@mail.md\nAna,\"ana@mail.md\"\n";
+
+            assert_eq!(split_addresses(input), ["ion@mail.md", "ana@mail.md"]);
+        }
+
+        #[test]
+        fn should_strip_display_names_and_brackets() {
+            let input = r#""Ion Popescu" <ion@mail.md>, 'ana@mail.md'"#;
+
+            assert_eq!(split_addresses(input), ["ion@mail.md", "ana@mail.md"]);
+        }
+
+        #[test]
+        fn should_split_on_every_separator() {
+            let input = "ion@mail.md;ana@mail.md,dan@mail.md\neva@mail.md\r\n\tmax@mail.md ";
+
+            assert_eq!(
+                split_addresses(input),
+                [
+                    "ion@mail.md",
+                    "ana@mail.md",
+                    "dan@mail.md",
+                    "eva@mail.md",
+                    "max@mail.md"
+                ]
+            );
+        }
+
+        #[test]
+        fn should_keep_first_of_case_insensitive_duplicates() {
+            let input = "Ion@Mail.md; ion@mail.md\nION@MAIL.MD, ana@mail.md";
+
+            assert_eq!(split_addresses(input), ["Ion@Mail.md", "ana@mail.md"]);
+        }
+    }
+}
+
+pub(super) mod templates {
+    use axum::{
+        Extension,
+        extract::{Path, State},
+    };
+    use maud::{Markup, Render};
+
+    use crate::{auth, contact, user};
+
+    pub async fn blocked(
+        auth_user: Extension<auth::User>,
+        contact_service: State<contact::Service>,
+    ) -> crate::Result<Markup> {
+        let blocked = contact_service.find_blocked_users(auth_user.id())?;
+
+        Ok(contact::markup::BlockedUsers(&blocked).render())
+    }
+
+    pub async fn annotations(
+        auth_user: Extension<auth::User>,
+        contact_service: State<contact::Service>,
+        Path(user_id): Path<user::Id>,
+    ) -> crate::Result<Markup> {
+        let annotations = contact_service.find_annotations(auth_user.id())?;
+        let labels = annotations.labels_of(&user_id);
+
+        Ok(contact::markup::AnnotationForm {
+            user_id: &user_id,
+            alias: annotations.alias(&user_id),
+            labels: &labels,
+        }
+        .render())
+    }
 }
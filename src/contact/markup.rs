@@ -6,10 +6,19 @@ use maud::{Markup, Render, html};
 use crate::{
     auth,
     contact::{self, Status, Transition},
-    user::model::UserDto,
+    markup::IdExt,
+    user::{self, model::UserDto},
 };
 
-use super::model::ContactDto;
+use super::model::{
+    Annotations, BlockedUser, ContactDto, ContactRequest, ImportReport, Suggestion,
+};
+
+/// Fired by responses that answer a contact request, refreshes the requests badge.
+pub const REQUESTS_CHANGED_EVENT: &str = "contactRequestsChanged";
+
+/// Fired once an alias or labels are saved, reloads the contacts tab.
+pub const ANNOTATED_EVENT: &str = "contactAnnotated";
 
 impl Display for super::Id {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -17,19 +26,38 @@ impl Display for super::Id {
     }
 }
 
+impl crate::markup::IdExt for super::Id {
+    fn attr(&self) -> String {
+        format!("c-{}", self.0)
+    }
+
+    fn target(&self) -> String {
+        format!("#c-{}", self.0)
+    }
+}
+
 pub struct ContactInfos<'a> {
     pub auth_user: &'a auth::User,
     pub contact_infos: &'a [(ContactDto, UserDto)],
+    pub suggestions: &'a [Suggestion],
+    pub annotations: &'a Annotations,
+    pub label: Option<&'a str>,
 }
 
 impl<'a> ContactInfos<'a> {
     pub const fn new(
         auth_user: &'a auth::User,
         contact_infos: &'a [(ContactDto, UserDto)],
+        suggestions: &'a [Suggestion],
+        annotations: &'a Annotations,
+        label: Option<&'a str>,
     ) -> Self {
         Self {
             auth_user,
             contact_infos,
+            suggestions,
+            annotations,
+            label,
         }
     }
 }
@@ -39,19 +67,118 @@ const CONTACT_ITEM_CLASS: &str =
 
 impl Render for ContactInfos<'_> {
     fn render(&self) -> maud::Markup {
+        let incoming = self.contact_infos.iter().filter(|(c, _)| c.is_incoming());
+        let outgoing = self.contact_infos.iter().filter(|(c, _)| c.is_outgoing());
+        let settled = self
+            .contact_infos
+            .iter()
+            .filter(|(c, _)| !c.status().is_pending())
+            .filter(|(c, _)| {
+                self.label
+                    .is_none_or(|l| self.annotations.labels_of(c.recipient()).contains(&l))
+            });
+        let labels = self.annotations.all_labels();
+
         html! {
             header ."text-center mb-4"{
                 h2.text-2xl { "Contacts" }
             }
+
+            (ImportForm)
+
+            div #contact-requests ."mb-4" {
+                details #incoming-requests ."mb-2" open {
+                    summary ."cursor-pointer text-gray-500 mb-2" {
+                        "Incoming requests ("
+                        // kept in step with requests pushed into the list over SSE
+                        span #incoming-requests-count
+                            _="on htmx:sseMessage from #incoming-requests-list put #incoming-requests-list.children.length into me"
+                        { (incoming.clone().count()) }
+                        ")"
+                    }
+                    ul #incoming-requests-list ."flex flex-col space-y-2"
+                        sse-swap="newContactRequest"
+                        hx-swap="afterbegin"
+                    {
+                        @for (c, u) in incoming {
+                            (request_item(c, u.name(), u.picture(), Icons::new(c.id(), c.status(), self.auth_user.id())))
+                        }
+                    }
+                }
+                details #outgoing-requests {
+                    summary ."cursor-pointer text-gray-500 mb-2" {
+                        "Outgoing requests (" (outgoing.clone().count()) ")"
+                    }
+                    ul ."flex flex-col space-y-2" {
+                        @for (c, u) in outgoing {
+                            (request_item(c, u.name(), u.picture(), Icons::new(c.id(), c.status(), self.auth_user.id())))
+                        }
+                    }
+                }
+            }
+
+            div .hidden
+                sse-swap="contactUpdated,contactRemoved"
+                hx-swap="none" {}
+
+            div .hidden
+                hx-get="/tabs/contacts"
+                hx-vals=[self.label.map(label_vals)]
+                hx-trigger={(ANNOTATED_EVENT) " from:body"} {}
+
+            div #contact-annotation ."mb-2" {}
+
+            @if !labels.is_empty() {
+                div #contact-labels ."flex flex-wrap gap-2 mb-2" {
+                    (LabelChip { label: None, active: self.label.is_none() })
+                    @for l in labels {
+                        (LabelChip { label: Some(l), active: self.label.is_some_and(|sl| sl.eq(l)) })
+                    }
+                }
+            }
+
             ul ."flex flex-col space-y-2" {
-                @for (c, u) in self.contact_infos {
-                    li .(CONTACT_ITEM_CLASS) {
+                @for (c, u) in settled {
+                    li #(c.id().attr()) .(CONTACT_ITEM_CLASS) {
                         img ."w-9 h-9 rounded-full float-left mr-2"
                             src=(u.picture())
                             alt="User avatar" {}
-                        (u.name())
+                        div ."flex flex-col" {
+                            @if let Some(alias) = self.annotations.alias(c.recipient()) {
+                                (alias)
+                                span ."text-xs text-gray-500" { (u.name()) }
+                            } @else {
+                                (u.name())
+                            }
+                            @let contact_labels = self.annotations.labels_of(c.recipient());
+                            @if !contact_labels.is_empty() {
+                                div ."flex flex-wrap gap-1 mt-1" {
+                                    @for l in contact_labels {
+                                        span ."px-2 text-xs rounded-full bg-blue-100 text-blue-800" { (l) }
+                                    }
+                                }
+                            }
+                        }
 
-                        (Icons::new(c.id(), c.status(), self.auth_user))
+                        @if !c.status().is_blocked() {
+                            i ."fa-solid fa-pen ml-3 text-gray-500 cursor-pointer"
+                                title="Edit alias and labels"
+                                hx-get={"/templates/contacts/annotations/" (c.recipient())}
+                                hx-target="#contact-annotation" {}
+                        }
+
+                        (Icons::new(c.id(), c.status(), self.auth_user.id()))
+                    }
+                }
+            }
+
+            @if !self.suggestions.is_empty() {
+                div #contact-suggestions ."mt-4" {
+                    h3 ."text-gray-500 mb-2" { "People you may know" }
+                    ul ."flex flex-col space-y-2" {
+                        @for s in self.suggestions {
+                            (s)
+                        }
                     }
                 }
             }
@@ -59,34 +186,336 @@ impl Render for ContactInfos<'_> {
     }
 }
 
+/// Query of the contacts tab narrowed down to a label.
+fn label_vals(label: &str) -> String {
+    serde_json::json!({ "label": label }).to_string()
+}
+
+/// Filters the contacts tab by label, `None` shows every contact.
+struct LabelChip<'a> {
+    label: Option<&'a str>,
+    active: bool,
+}
+
+impl Render for LabelChip<'_> {
+    fn render(&self) -> Markup {
+        html! {
+            button ."px-3 py-1 text-sm rounded-full cursor-pointer"
+                .bg-gray-100[!self.active]
+                ."bg-blue-600 text-white"[self.active]
+                hx-get="/tabs/contacts"
+                hx-vals=[self.label.map(label_vals)] {
+                (self.label.unwrap_or("All"))
+            }
+        }
+    }
+}
+
+/// Form editing the private alias and labels of a contact.
+pub struct AnnotationForm<'a> {
+    pub user_id: &'a user::Id,
+    pub alias: Option<&'a str>,
+    pub labels: &'a [&'a str],
+}
+
+impl Render for AnnotationForm<'_> {
+    fn render(&self) -> Markup {
+        html! {
+            form ."flex flex-col space-y-2 p-3 rounded-md border border-gray-300"
+                hx-put={"/api/contacts/annotations/" (self.user_id)}
+                hx-target="#contact-annotation"
+            {
+                input ."w-full px-3 py-2 border border-gray-300 rounded-md focus:outline-none"
+                    type="text"
+                    name="alias"
+                    maxlength=(contact::ALIAS_MAX_LEN)
+                    placeholder="Alias, only visible to you"
+                    value=[self.alias] {}
+                input ."w-full px-3 py-2 border border-gray-300 rounded-md focus:outline-none"
+                    type="text"
+                    name="labels"
+                    placeholder="Labels, comma separated"
+                    value=(self.labels.join(", ")) {}
+                div ."flex space-x-2" {
+                    input ."px-4 py-2 text-white bg-green-700 hover:bg-green-800 rounded-md cursor-pointer"
+                        type="submit"
+                        value="Save" {}
+                    button ."px-4 py-2 bg-gray-200 hover:bg-gray-300 rounded-md cursor-pointer"
+                        type="button"
+                        _="on click set #contact-annotation's innerHTML to ''" { "Cancel" }
+                }
+            }
+        }
+    }
+}
+
+impl Render for Suggestion {
+    fn render(&self) -> Markup {
+        let mut reasons = Vec::with_capacity(2);
+        match self.mutual_contacts() {
+            0 => {}
+            1 => reasons.push("1 mutual contact".to_string()),
+            n => reasons.push(format!("{n} mutual contacts")),
+        }
+        match self.shared_groups() {
+            0 => {}
+            1 => reasons.push("1 shared group".to_string()),
+            n => reasons.push(format!("{n} shared groups")),
+        }
+
+        html! {
+            li .(CONTACT_ITEM_CLASS) {
+                img ."w-9 h-9 rounded-full float-left mr-2"
+                    src=(self.picture())
+                    alt="User avatar" {}
+                div ."flex flex-col grow" {
+                    (self.name())
+                    span ."text-sm text-gray-500" { (reasons.join(" · ")) }
+                }
+                form hx-post="/api/contacts"
+                    hx-target="this"
+                    hx-swap="outerHTML"
+                {
+                    input type="hidden" name="user_id" value=(self.id()) {}
+                    input ."px-2 py-1 text-white bg-green-700 hover:bg-green-800 font-medium rounded-lg text-xs focus:outline-none cursor-pointer"
+                        type="submit"
+                        value="Add" {}
+                }
+            }
+        }
+    }
+}
+
+fn request_item(c: &ContactDto, name: &str, picture: &user::Picture, icons: impl Render) -> Markup {
+    html! {
+        li #(c.id().attr()) .(CONTACT_ITEM_CLASS) {
+            img ."w-9 h-9 rounded-full float-left mr-2"
+                src=(picture)
+                alt="User avatar" {}
+            div ."flex flex-col" {
+                (name)
+                @if let Some(note) = c.note() {
+                    span ."text-sm text-gray-500 italic" { (note) }
+                }
+            }
+
+            (icons)
+        }
+    }
+}
+
+/// A contact request pushed to the responder as it arrives.
+pub struct IncomingRequest<'a>(pub &'a ContactRequest);
+
+impl Render for IncomingRequest<'_> {
+    fn render(&self) -> Markup {
+        let c = self.0.contact();
+
+        html! {
+            (request_item(c, self.0.name(), self.0.picture(), html! {
+                div #{"ci-status-" (c.id())} ."grow text-right" {
+                    (Icon::Accept(c.id()))
+                    (Icon::Reject(c.id()))
+                    (BlockUser(c.recipient()))
+                }
+            }))
+        }
+    }
+}
+
+struct ImportForm;
+
+impl Render for ImportForm {
+    fn render(&self) -> Markup {
+        html! {
+            details #contact-import ."mb-4" {
+                summary ."cursor-pointer text-gray-500 mb-2" { "Import by email" }
+                form ."flex flex-col space-y-2"
+                    hx-post="/api/contacts/import"
+                    hx-encoding="multipart/form-data"
+                    hx-target="#import-report"
+                    hx-disabled-elt="find input[type='submit']"
+                {
+                    textarea ."w-full px-3 py-2 border border-gray-300 rounded-md focus:outline-none"
+                        name="emails"
+                        rows="3"
+                        placeholder="Paste email addresses, one per line or comma separated" {}
+                    input ."text-sm"
+                        type="file"
+                        name="file"
+                        accept=".csv,.txt,text/csv,text/plain" {}
+                    input ."px-4 py-2 text-white bg-green-700 hover:bg-green-800 rounded-md cursor-pointer"
+                        type="submit"
+                        value="Send requests" {}
+                }
+                div #import-report ."mt-2" {}
+            }
+        }
+    }
+}
+
+impl Render for ImportReport {
+    fn render(&self) -> Markup {
+        let section = |title: &str, class: &str, addresses: &[String]| {
+            html! {
+                @if !addresses.is_empty() {
+                    div ."mb-2" {
+                        span .{"font-bold " (class)} { (title) " (" (addresses.len()) ")" }
+                        ul ."text-sm text-gray-600" {
+                            @for a in addresses {
+                                li { (a) }
+                            }
+                        }
+                    }
+                }
+            }
+        };
+
+        html! {
+            (section("Requests sent", "text-green-700", &self.requested))
+            (section("Already contacts", "text-gray-500", &self.existing))
+            (section("Skipped", "text-gray-500", &self.skipped))
+            (section("Unknown", "text-gray-500", &self.unknown))
+            (section("Invalid", "text-red-500", &self.invalid))
+            @if self.requested.is_empty()
+                && self.existing.is_empty()
+                && self.skipped.is_empty()
+                && self.unknown.is_empty()
+                && self.invalid.is_empty()
+            {
+                span ."text-gray-500" { "No email addresses found" }
+            }
+        }
+    }
+}
+
+/// Status change made by the other side, swapped out-of-band into `ContactInfos`.
+pub fn contact_updated(c: &ContactDto) -> Markup {
+    Icons::new(c.id(), c.status(), c.sender()).oob().render()
+}
+
+/// Removal made by the other side, swapped out-of-band into `ContactInfos`.
+pub fn contact_removed(id: &contact::Id) -> Markup {
+    html! {
+        li #(id.attr()) hx-swap-oob="delete" {}
+    }
+}
+
+/// Button putting a user on the block list, shown next to strangers.
+pub struct BlockUser<'a>(pub &'a user::Id);
+
+impl Render for BlockUser<'_> {
+    fn render(&self) -> Markup {
+        html! {
+            i ."fa-solid fa-user-slash ml-3 text-xl text-gray-500 cursor-pointer"
+                title="Block user"
+                hx-post="/api/contacts/blocks"
+                hx-vals=(format!(r#"{{"user_id": "{}"}}"#, self.0))
+                hx-target="this"
+                hx-swap="outerHTML" {}
+        }
+    }
+}
+
+pub struct Blocked;
+
+impl Render for Blocked {
+    fn render(&self) -> Markup {
+        html! {
+            span ."ml-3 text-red-700" { "Blocked" }
+        }
+    }
+}
+
+pub struct BlockedUsers<'a>(pub &'a [BlockedUser]);
+
+impl Render for BlockedUsers<'_> {
+    fn render(&self) -> Markup {
+        html! {
+            h3 ."text-gray-500 mt-4 mb-2" { "Blocked users" }
+            ul ."flex flex-col space-y-2" {
+                @for u in self.0 {
+                    li .(CONTACT_ITEM_CLASS) {
+                        img ."w-9 h-9 rounded-full float-left mr-2"
+                            src=(u.picture())
+                            alt="User avatar" {}
+                        span .grow { (u.name()) }
+                        button ."px-2 py-1 text-white bg-green-700 hover:bg-green-800 rounded-lg text-xs"
+                            hx-delete={"/api/contacts/blocks/" (u.id())}
+                            hx-target="closest li"
+                            hx-swap="delete" { "Unblock" }
+                    }
+                }
+                @if self.0.is_empty() {
+                    li ."text-center text-gray-500" { "No blocked users" }
+                }
+            }
+        }
+    }
+}
+
+/// Number of incoming requests shown on the contacts tab control.
+pub struct RequestsBadge;
+
+impl Render for RequestsBadge {
+    fn render(&self) -> Markup {
+        html! {
+            span #contact-requests-badge
+                hx-get="/api/contacts/requests/count"
+                hx-trigger={"load, sse:newContactRequest, sse:contactRemoved, " (REQUESTS_CHANGED_EVENT) " from:body"}
+                hx-swap="innerHTML" {}
+        }
+    }
+}
+
+pub struct RequestsCount(pub usize);
+
+impl Render for RequestsCount {
+    fn render(&self) -> Markup {
+        html! {
+            @if self.0 > 0 {
+                span ."ml-1 px-2 text-xs text-white bg-red-500 rounded-full align-top" { (self.0) }
+            }
+        }
+    }
+}
+
 pub struct Icons<'a> {
     contact_id: &'a contact::Id,
     status: &'a Status,
-    auth_user: &'a auth::User,
+    auth_id: &'a user::Id,
+    oob: bool,
 }
 
 impl<'a> Icons<'a> {
     pub const fn new(
         contact_id: &'a contact::Id,
         status: &'a Status,
-        auth_user: &'a auth::User,
+        auth_id: &'a user::Id,
     ) -> Self {
         Self {
             contact_id,
             status,
-            auth_user,
+            auth_id,
+            oob: false,
         }
     }
+
+    const fn oob(mut self) -> Self {
+        self.oob = true;
+        self
+    }
 }
 
 impl Render for Icons<'_> {
     fn render(&self) -> Markup {
         let c_id = self.contact_id;
-        let auth_id = self.auth_user.id();
+        let auth_id = self.auth_id;
 
         html! {
             div #{"ci-status-" (c_id)}
                 ."grow text-right"
+                hx-swap-oob=[self.oob.then_some("true")]
             {
                 @match self.status {
                     Status::Pending { initiator } => {
@@ -95,6 +524,7 @@ impl Render for Icons<'_> {
                         } @else {
                             (Icon::Accept(c_id))
                             (Icon::Reject(c_id))
+                            (BlockUser(initiator))
                         }
                     },
                     Status::Accepted => (Icon::Block(c_id)),
@@ -173,3 +603,105 @@ impl fmt::Display for Transition {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use maud::Render;
+    use uuid::Uuid;
+
+    use super::{BlockedUsers, IncomingRequest};
+    use crate::contact::model::{ContactDto, ContactRequest, ImportReport};
+    use crate::contact::{Id, Status};
+    use crate::user;
+
+    #[test]
+    fn should_render_incoming_request() {
+        let initiator = user::Id::from(Uuid::from_u128(2));
+        // seen from the responder's side
+        let request = ContactRequest::new(
+            ContactDto::new(
+                Id::from(Uuid::from_u128(3)),
+                user::Id::from(Uuid::from_u128(1)),
+                initiator.clone(),
+                Status::Pending { initiator },
+                None,
+            ),
+            String::from("Ion Popescu"),
+            user::Picture::from(String::from("https://idp.example/ion.png")),
+        );
+        let expected = concat!(
+            r#"<li class="px-3 py-2 rounded-md bg-gray-100 hover:bg-gray-200 cursor-pointer flex items-center" id="c-00000000-0000-0000-0000-000000000003">"#,
+            r#"<img class="w-9 h-9 rounded-full float-left mr-2" src="https://idp.example/ion.png" alt="User avatar"></img>"#,
+            r#"<div class="flex flex-col">Ion Popescu</div>"#,
+            r#"<div class="grow text-right" id="ci-status-00000000-0000-0000-0000-000000000003">"#,
+            r##"<i class="fa-solid fa-check text-2xl text-green-600 cursor-pointer" hx-target="#ci-status-00000000-0000-0000-0000-000000000003" hx-put="/api/contacts/00000000-0000-0000-0000-000000000003/accept"></i>"##,
+            r##"<i class="fa-solid fa-xmark ml-3 text-2xl text-red-500 cursor-pointer" hx-target="#ci-status-00000000-0000-0000-0000-000000000003" hx-put="/api/contacts/00000000-0000-0000-0000-000000000003/reject"></i>"##,
+            r#"<i class="fa-solid fa-user-slash ml-3 text-xl text-gray-500 cursor-pointer" title="Block user" hx-post="/api/contacts/blocks" hx-vals="{&quot;user_id&quot;: &quot;00000000-0000-0000-0000-000000000002&quot;}" hx-target="this" hx-swap="outerHTML"></i>"#,
+            "</div>",
+            "</li>",
+        );
+
+        let actual = IncomingRequest(&request).render().into_string();
+
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn should_render_import_report() {
+        let report = ImportReport {
+            requested: vec![String::from("ion@mail.md"), String::from("ana@mail.md")],
+            existing: vec![],
+            skipped: vec![String::from("vlad@mail.md")],
+            unknown: vec![],
+            invalid: vec![String::from("not-an-email")],
+        };
+        let expected = concat!(
+            r#"<div class="mb-2">"#,
+            r#"<span class="font-bold text-green-700">Requests sent (2)</span>"#,
+            r#"<ul class="text-sm text-gray-600">"#,
+            "<li>ion@mail.md</li>",
+            "<li>ana@mail.md</li>",
+            "</ul>",
+            "</div>",
+            r#"<div class="mb-2">"#,
+            r#"<span class="font-bold text-gray-500">Skipped (1)</span>"#,
+            r#"<ul class="text-sm text-gray-600">"#,
+            "<li>vlad@mail.md</li>",
+            "</ul>",
+            "</div>",
+            r#"<div class="mb-2">"#,
+            r#"<span class="font-bold text-red-500">Invalid (1)</span>"#,
+            r#"<ul class="text-sm text-gray-600">"#,
+            "<li>not-an-email</li>",
+            "</ul>",
+            "</div>",
+        );
+
+        let actual = report.render().into_string();
+
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn should_render_empty_import_report() {
+        let expected = r#"<span class="text-gray-500">No email addresses found</span>"#;
+
+        let actual = ImportReport::default().render().into_string();
+
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn should_render_empty_block_list() {
+        let expected = concat!(
+            r#"<h3 class="text-gray-500 mt-4 mb-2">Blocked users</h3>"#,
+            r#"<ul class="flex flex-col space-y-2">"#,
+            r#"<li class="text-center text-gray-500">No blocked users</li>"#,
+            "</ul>",
+        );
+
+        let actual = BlockedUsers(&[]).render().into_string();
+
+        assert_eq!(expected, actual);
+    }
+}
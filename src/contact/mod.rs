@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use axum::{
     Router,
-    routing::{delete, post, put},
+    routing::{delete, get, post, put},
 };
 use diesel::{deserialize::FromSqlRow, expression::AsExpression, sql_types};
 use messenger_service::AsStr;
@@ -22,6 +22,19 @@ pub mod repository;
 pub mod service;
 
 type Result<T> = std::result::Result<T, Error>;
+
+/// Maximum length of the note attached to a contact request, in characters.
+pub const NOTE_MAX_LEN: usize = 280;
+
+/// Maximum number of addresses accepted by a single bulk import.
+pub const IMPORT_MAX_ADDRESSES: usize = 200;
+
+/// Maximum length of a contact alias, in characters.
+pub const ALIAS_MAX_LEN: usize = 64;
+
+/// Maximum length of a single contact label, in characters.
+pub const LABEL_MAX_LEN: usize = 32;
+
 pub type Repository = Arc<dyn ContactRepository + Send + Sync>;
 pub type Service = Arc<dyn ContactService + Send + Sync>;
 
@@ -44,11 +57,32 @@ impl From<Uuid> for Id {
 pub fn api<S>(s: AppServices) -> Router<S> {
     Router::new()
         .route("/contacts", post(handler::api::create))
+        .route("/contacts/import", post(handler::api::import))
+        .route(
+            "/contacts/requests/count",
+            get(handler::api::count_requests),
+        )
+        .route("/contacts/blocks", post(handler::api::block))
+        .route("/contacts/blocks/{user_id}", delete(handler::api::unblock))
+        .route(
+            "/contacts/annotations/{user_id}",
+            put(handler::api::annotate),
+        )
         .route("/contacts/{id}", delete(handler::api::delete))
         .route("/contacts/{id}/{transition}", put(handler::api::transition))
         .with_state(s)
 }
 
+pub fn templates<S>(s: AppServices) -> Router<S> {
+    Router::new()
+        .route("/contacts/blocked", get(handler::templates::blocked))
+        .route(
+            "/contacts/annotations/{user_id}",
+            get(handler::templates::annotations),
+        )
+        .with_state(s)
+}
+
 #[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug)]
 #[serde(tag = "indicator", rename_all = "snake_case")]
 pub enum Status {
@@ -84,6 +118,10 @@ impl Status {
     pub const fn is_rejected(&self) -> bool {
         matches!(self, Self::Rejected)
     }
+
+    pub const fn is_blocked(&self) -> bool {
+        matches!(self, Self::Blocked { .. })
+    }
 }
 
 impl From<&Contact> for Status {
@@ -115,6 +153,14 @@ impl From<&Contact> for Status {
     }
 }
 
+/// How close two users are through accepted contacts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Relation {
+    Contact,
+    ContactOfContact,
+    Stranger,
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum Transition {
@@ -141,6 +187,22 @@ pub enum Error {
     SameUsers(user::Id),
     #[error("could not transition contact status")]
     StatusTransitionFailed,
+    #[error("contact request note is too long: {0} characters")]
+    NoteTooLong(usize),
+    #[error("invalid import: {0}")]
+    InvalidImport(String),
+    #[error("too many addresses to import: {0}")]
+    TooManyAddresses(usize),
+    #[error("user is blocked: {0:?}")]
+    Blocked(user::Id),
+    #[error("user is not a contact: {0:?}")]
+    NotContact(user::Id),
+    #[error("contact alias is too long: {0} characters")]
+    AliasTooLong(usize),
+    #[error("invalid contact label: {0}")]
+    InvalidLabel(String),
+    #[error("user does not accept contact requests: {0:?}")]
+    RequestsRestricted(user::Id),
 
     #[error(transparent)]
     _R2d2(#[from] r2d2::Error),
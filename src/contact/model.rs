@@ -1,9 +1,15 @@
-use diesel::prelude::{Insertable, Queryable, Selectable};
+use std::collections::{BTreeSet, HashMap};
+
+use chrono::{DateTime, Utc};
+use diesel::{
+    prelude::{Insertable, Queryable, QueryableByName, Selectable},
+    sql_types,
+};
 use serde::{Deserialize, Serialize};
 
 use crate::user;
 
-use super::{Id, Status, StatusTransition};
+use super::{Id, Relation, Status, StatusTransition};
 
 #[derive(Queryable, Selectable)]
 #[diesel(table_name = crate::schema::contacts)]
@@ -14,6 +20,8 @@ pub struct Contact {
     user_id_2: user::Id,
     status: String,
     initiator: Option<user::Id>,
+    note: Option<String>,
+    created_at: DateTime<Utc>,
 }
 
 impl Contact {
@@ -36,6 +44,14 @@ impl Contact {
     pub const fn initiator(&self) -> Option<&user::Id> {
         self.initiator.as_ref()
     }
+
+    pub fn note(&self) -> Option<&str> {
+        self.note.as_deref()
+    }
+
+    pub const fn created_at(&self) -> &DateTime<Utc> {
+        &self.created_at
+    }
 }
 
 #[derive(Insertable)]
@@ -45,15 +61,21 @@ pub struct NewContact<'a> {
     user_id_2: &'a user::Id,
     status: &'a str,
     initiator: &'a user::Id,
+    note: Option<&'a str>,
 }
 
 impl<'a> NewContact<'a> {
-    pub const fn new(initiator: &'a user::Id, responder: &'a user::Id) -> Self {
+    pub const fn new(
+        initiator: &'a user::Id,
+        responder: &'a user::Id,
+        note: Option<&'a str>,
+    ) -> Self {
         Self {
             user_id_1: initiator,
             user_id_2: responder,
             status: "pending",
             initiator,
+            note,
         }
     }
 
@@ -72,15 +94,24 @@ pub struct ContactDto {
     sender: user::Id,
     recipient: user::Id,
     status: Status,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    note: Option<String>,
 }
 
 impl ContactDto {
-    pub const fn new(id: Id, sender: user::Id, recipient: user::Id, status: Status) -> Self {
+    pub const fn new(
+        id: Id,
+        sender: user::Id,
+        recipient: user::Id,
+        status: Status,
+        note: Option<String>,
+    ) -> Self {
         Self {
             id,
             sender,
             recipient,
             status,
+            note,
         }
     }
 
@@ -88,6 +119,10 @@ impl ContactDto {
         &self.id
     }
 
+    pub const fn sender(&self) -> &user::Id {
+        &self.sender
+    }
+
     pub const fn recipient(&self) -> &user::Id {
         &self.recipient
     }
@@ -96,9 +131,23 @@ impl ContactDto {
         &self.status
     }
 
+    pub fn note(&self) -> Option<&str> {
+        self.note.as_deref()
+    }
+
     pub const fn is_accepted(&self) -> bool {
         matches!(self.status, Status::Accepted)
     }
+
+    /// A pending request sent by the other side, waiting for the user's answer.
+    pub fn is_incoming(&self) -> bool {
+        matches!(&self.status, Status::Pending { initiator } if initiator.eq(&self.recipient))
+    }
+
+    /// A pending request sent by the user.
+    pub fn is_outgoing(&self) -> bool {
+        matches!(&self.status, Status::Pending { initiator } if initiator.eq(&self.sender))
+    }
 }
 
 impl ContactDto {
@@ -168,6 +217,209 @@ impl ContactDto {
     }
 }
 
+/// Incoming contact request as delivered to the responder.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ContactRequest {
+    contact: ContactDto,
+    name: String,
+    picture: user::Picture,
+}
+
+impl ContactRequest {
+    pub const fn new(contact: ContactDto, name: String, picture: user::Picture) -> Self {
+        Self {
+            contact,
+            name,
+            picture,
+        }
+    }
+
+    pub const fn contact(&self) -> &ContactDto {
+        &self.contact
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub const fn picture(&self) -> &user::Picture {
+        &self.picture
+    }
+}
+
+/// A user reachable through accepted contacts or shared groups.
+#[derive(QueryableByName)]
+pub struct Suggestion {
+    #[diesel(sql_type = sql_types::Uuid)]
+    id: user::Id,
+    #[diesel(sql_type = sql_types::Text)]
+    name: String,
+    #[diesel(sql_type = sql_types::Text)]
+    picture: String,
+    #[diesel(sql_type = sql_types::BigInt)]
+    mutual_contacts: i64,
+    #[diesel(sql_type = sql_types::BigInt)]
+    shared_groups: i64,
+}
+
+impl Suggestion {
+    pub const fn id(&self) -> &user::Id {
+        &self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn picture(&self) -> &str {
+        &self.picture
+    }
+
+    pub const fn mutual_contacts(&self) -> i64 {
+        self.mutual_contacts
+    }
+
+    pub const fn shared_groups(&self) -> i64 {
+        self.shared_groups
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::user_blocks)]
+pub struct NewBlock<'a> {
+    blocker: &'a user::Id,
+    blocked: &'a user::Id,
+}
+
+impl<'a> NewBlock<'a> {
+    pub const fn new(blocker: &'a user::Id, blocked: &'a user::Id) -> Self {
+        Self { blocker, blocked }
+    }
+}
+
+/// Whether two users are contacts, or share an accepted contact.
+#[derive(QueryableByName)]
+pub struct Closeness {
+    #[diesel(sql_type = sql_types::Bool)]
+    contact: bool,
+    #[diesel(sql_type = sql_types::Bool)]
+    mutual: bool,
+}
+
+impl From<Closeness> for Relation {
+    fn from(c: Closeness) -> Self {
+        if c.contact {
+            Self::Contact
+        } else if c.mutual {
+            Self::ContactOfContact
+        } else {
+            Self::Stranger
+        }
+    }
+}
+
+/// A user on the block list, as shown in settings.
+#[derive(QueryableByName)]
+pub struct BlockedUser {
+    #[diesel(sql_type = sql_types::Uuid)]
+    id: user::Id,
+    #[diesel(sql_type = sql_types::Text)]
+    name: String,
+    #[diesel(sql_type = sql_types::Text)]
+    picture: String,
+}
+
+impl BlockedUser {
+    pub const fn id(&self) -> &user::Id {
+        &self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn picture(&self) -> &str {
+        &self.picture
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::contact_aliases)]
+pub struct NewAlias<'a> {
+    owner: &'a user::Id,
+    user_id: &'a user::Id,
+    alias: &'a str,
+}
+
+impl<'a> NewAlias<'a> {
+    pub const fn new(owner: &'a user::Id, user_id: &'a user::Id, alias: &'a str) -> Self {
+        Self {
+            owner,
+            user_id,
+            alias,
+        }
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::contact_labels)]
+pub struct NewLabel<'a> {
+    owner: &'a user::Id,
+    user_id: &'a user::Id,
+    label: &'a str,
+}
+
+impl<'a> NewLabel<'a> {
+    pub const fn new(owner: &'a user::Id, user_id: &'a user::Id, label: &'a str) -> Self {
+        Self {
+            owner,
+            user_id,
+            label,
+        }
+    }
+}
+
+/// Private aliases and labels a user has given to their contacts.
+#[derive(Default)]
+pub struct Annotations {
+    aliases: HashMap<user::Id, String>,
+    labels: Vec<(user::Id, String)>,
+}
+
+impl Annotations {
+    pub const fn new(aliases: HashMap<user::Id, String>, labels: Vec<(user::Id, String)>) -> Self {
+        Self { aliases, labels }
+    }
+
+    pub fn alias(&self, id: &user::Id) -> Option<&str> {
+        self.aliases.get(id).map(String::as_str)
+    }
+
+    pub fn labels_of(&self, id: &user::Id) -> Vec<&str> {
+        self.labels
+            .iter()
+            .filter(|(u, _)| u.eq(id))
+            .map(|(_, l)| l.as_str())
+            .collect()
+    }
+
+    /// Distinct labels in use, sorted.
+    pub fn all_labels(&self) -> BTreeSet<&str> {
+        self.labels.iter().map(|(_, l)| l.as_str()).collect()
+    }
+}
+
+/// Outcome of a bulk import, addresses grouped as entered.
+#[derive(Default)]
+pub struct ImportReport {
+    pub requested: Vec<String>,
+    pub existing: Vec<String>,
+    /// Blocked or not accepting requests from the importer.
+    pub skipped: Vec<String>,
+    pub unknown: Vec<String>,
+    pub invalid: Vec<String>,
+}
+
 #[derive(Deserialize, Clone)]
 pub struct Contacts(Vec<ContactDto>);
 
@@ -176,3 +428,145 @@ impl Contacts {
         &self.0
     }
 }
+
+#[cfg(test)]
+mod test {
+    use uuid::Uuid;
+
+    use super::ContactDto;
+    use crate::contact::{Error, Id, Status, StatusTransition};
+    use crate::user;
+
+    fn user_id() -> user::Id {
+        user::Id::from(Uuid::new_v4())
+    }
+
+    fn contact(sender: &user::Id, recipient: &user::Id, status: Status) -> ContactDto {
+        ContactDto::new(
+            Id::from(Uuid::new_v4()),
+            sender.clone(),
+            recipient.clone(),
+            status,
+            None,
+        )
+    }
+
+    fn is_rejected(res: crate::contact::Result<Status>) -> bool {
+        matches!(res, Err(Error::StatusTransitionFailed))
+    }
+
+    #[test]
+    fn should_let_only_the_other_side_answer_a_request() {
+        let (a, b, stranger) = (user_id(), user_id(), user_id());
+        let c = contact(
+            &a,
+            &b,
+            Status::Pending {
+                initiator: a.clone(),
+            },
+        );
+
+        assert_eq!(
+            c.transition(StatusTransition::Accept { responder: &b })
+                .ok(),
+            Some(Status::Accepted)
+        );
+        assert_eq!(
+            c.transition(StatusTransition::Reject { responder: &b })
+                .ok(),
+            Some(Status::Rejected)
+        );
+
+        for responder in [&a, &stranger] {
+            assert!(is_rejected(
+                c.transition(StatusTransition::Accept { responder })
+            ));
+            assert!(is_rejected(
+                c.transition(StatusTransition::Reject { responder })
+            ));
+        }
+    }
+
+    #[test]
+    fn should_let_either_side_block_a_contact() {
+        let (a, b, stranger) = (user_id(), user_id(), user_id());
+        let c = contact(&a, &b, Status::Accepted);
+
+        for initiator in [&a, &b] {
+            assert_eq!(
+                c.transition(StatusTransition::Block { initiator }).ok(),
+                Some(Status::Blocked {
+                    initiator: initiator.clone()
+                })
+            );
+        }
+
+        assert!(is_rejected(c.transition(StatusTransition::Block {
+            initiator: &stranger
+        })));
+    }
+
+    #[test]
+    fn should_unblock_only_the_blocked_side() {
+        let (a, b, stranger) = (user_id(), user_id(), user_id());
+        let c = contact(
+            &a,
+            &b,
+            Status::Blocked {
+                initiator: a.clone(),
+            },
+        );
+
+        assert_eq!(
+            c.transition(StatusTransition::Unblock { target: &b }).ok(),
+            Some(Status::Accepted)
+        );
+
+        for target in [&a, &stranger] {
+            assert!(is_rejected(
+                c.transition(StatusTransition::Unblock { target })
+            ));
+        }
+    }
+
+    #[test]
+    fn should_reject_transitions_not_in_the_table() {
+        let (a, b) = (user_id(), user_id());
+
+        let cases = [
+            (
+                Status::Pending {
+                    initiator: a.clone(),
+                },
+                StatusTransition::Block { initiator: &b },
+            ),
+            (
+                Status::Pending {
+                    initiator: a.clone(),
+                },
+                StatusTransition::Unblock { target: &b },
+            ),
+            (Status::Accepted, StatusTransition::Accept { responder: &b }),
+            (Status::Accepted, StatusTransition::Unblock { target: &b }),
+            (Status::Rejected, StatusTransition::Accept { responder: &b }),
+            (Status::Rejected, StatusTransition::Block { initiator: &b }),
+            (
+                Status::Blocked {
+                    initiator: a.clone(),
+                },
+                StatusTransition::Block { initiator: &b },
+            ),
+            (
+                Status::Blocked {
+                    initiator: a.clone(),
+                },
+                StatusTransition::Accept { responder: &b },
+            ),
+        ];
+
+        for (status, t) in cases {
+            let c = contact(&a, &b, status.clone());
+            assert!(is_rejected(c.transition(t)), "{status:?}");
+        }
+    }
+}
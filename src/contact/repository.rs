@@ -1,17 +1,23 @@
+use chrono::{DateTime, Utc};
 use diesel::{
-    BoolExpressionMethods, ExpressionMethods, OptionalExtension, PgConnection, QueryDsl,
-    RunQueryDsl, SelectableHelper, delete, insert_into, r2d2::ConnectionManager, update,
+    BoolExpressionMethods, Connection, ExpressionMethods, OptionalExtension, PgConnection,
+    QueryDsl, RunQueryDsl, SelectableHelper, delete, insert_into, r2d2::ConnectionManager,
+    sql_query, sql_types, update, upsert::excluded,
 };
 use messenger_service::AsStr;
 
-use crate::schema::contacts::dsl::{contacts, id, initiator, status, user_id_1, user_id_2};
+use crate::schema::contacts::dsl::{
+    contacts, created_at, id, initiator, status, user_id_1, user_id_2,
+};
 
 use crate::contact::{self};
 use crate::user;
 
 use super::{
-    Status,
-    model::{Contact, NewContact},
+    Relation, Status,
+    model::{
+        BlockedUser, Closeness, Contact, NewAlias, NewBlock, NewContact, NewLabel, Suggestion,
+    },
 };
 
 pub trait ContactRepository {
@@ -21,13 +27,55 @@ pub trait ContactRepository {
 
     fn find_by_user_id(&self, user_id: &user::Id) -> super::Result<Vec<Contact>>;
 
-    fn add(&self, c: &NewContact) -> super::Result<()>;
+    fn add(&self, c: &NewContact) -> super::Result<contact::Id>;
 
     fn update_status(&self, c_id: &contact::Id, status: &Status) -> super::Result<bool>;
 
     fn delete(&self, me: &user::Id, you: &user::Id) -> super::Result<bool>;
 
     fn exists(&self, me: &user::Id, you: &user::Id) -> super::Result<bool>;
+
+    /// Deletes requests left pending since before `created_before`, returning them.
+    fn delete_expired(&self, created_before: &DateTime<Utc>) -> super::Result<Vec<Contact>>;
+
+    /// Contacts of accepted contacts and members of shared groups the user has
+    /// no contact with in any status, ranked by mutual count.
+    fn find_suggestions(&self, user_id: &user::Id, limit: i64) -> super::Result<Vec<Suggestion>>;
+
+    fn find_relation(&self, me: &user::Id, you: &user::Id) -> super::Result<Relation>;
+
+    /// Who the user accepts contact requests from, per their privacy settings.
+    fn find_requests_audience(&self, user_id: &user::Id) -> super::Result<user::Audience>;
+
+    fn block(&self, b: &NewBlock) -> super::Result<()>;
+
+    fn unblock(&self, blocker: &user::Id, blocked: &user::Id) -> super::Result<bool>;
+
+    /// Whether either of the users is on the block list of the other.
+    fn is_blocked(&self, me: &user::Id, you: &user::Id) -> super::Result<bool>;
+
+    fn find_blocked(&self, blocker: &user::Id) -> super::Result<Vec<BlockedUser>>;
+
+    /// Users the given one has blocked or is blocked by.
+    fn find_block_relations(&self, user_id: &user::Id) -> super::Result<Vec<user::Id>>;
+
+    fn upsert_alias(&self, a: &NewAlias) -> super::Result<()>;
+
+    fn delete_alias(&self, owner: &user::Id, user_id: &user::Id) -> super::Result<bool>;
+
+    fn find_alias(&self, owner: &user::Id, user_id: &user::Id) -> super::Result<Option<String>>;
+
+    fn find_aliases(&self, owner: &user::Id) -> super::Result<Vec<(user::Id, String)>>;
+
+    /// Replaces all labels the owner has put on the user.
+    fn replace_labels(
+        &self,
+        owner: &user::Id,
+        user_id: &user::Id,
+        labels: &[NewLabel],
+    ) -> super::Result<()>;
+
+    fn find_labels(&self, owner: &user::Id) -> super::Result<Vec<(user::Id, String)>>;
 }
 
 pub struct PgContactRepository {
@@ -74,14 +122,16 @@ impl ContactRepository for PgContactRepository {
             .map_err(super::Error::from)
     }
 
-    fn add(&self, c: &NewContact) -> super::Result<()> {
+    fn add(&self, c: &NewContact) -> super::Result<contact::Id> {
         assert_ne!(c.user_id_1(), c.user_id_2());
 
         let mut conn = self.pool.get()?;
 
-        insert_into(contacts).values(c).execute(&mut conn)?;
-
-        Ok(())
+        insert_into(contacts)
+            .values(c)
+            .returning(id)
+            .get_result(&mut conn)
+            .map_err(super::Error::from)
     }
 
     fn update_status(&self, c_id: &contact::Id, s: &Status) -> super::Result<bool> {
@@ -125,4 +175,287 @@ impl ContactRepository for PgContactRepository {
 
         Ok(count > 0)
     }
+
+    fn delete_expired(&self, created_before: &DateTime<Utc>) -> super::Result<Vec<Contact>> {
+        let mut conn = self.pool.get()?;
+
+        delete(contacts)
+            .filter(status.eq("pending").and(created_at.lt(created_before)))
+            .returning(Contact::as_returning())
+            .get_results(&mut conn)
+            .map_err(super::Error::from)
+    }
+
+    fn find_suggestions(&self, user_id: &user::Id, limit: i64) -> super::Result<Vec<Suggestion>> {
+        let mut conn = self.pool.get()?;
+
+        sql_query(
+            r"
+            WITH mine AS (
+                SELECT CASE WHEN c.user_id_1 = $1 THEN c.user_id_2 ELSE c.user_id_1 END AS friend
+                FROM contacts c
+                WHERE (c.user_id_1 = $1 OR c.user_id_2 = $1) AND c.status = 'accepted'
+            ),
+            fof AS (
+                SELECT
+                    CASE WHEN c.user_id_1 = m.friend THEN c.user_id_2 ELSE c.user_id_1 END AS candidate,
+                    COUNT(*) AS mutual
+                FROM contacts c
+                JOIN mine m ON c.user_id_1 = m.friend OR c.user_id_2 = m.friend
+                WHERE c.status = 'accepted'
+                GROUP BY candidate
+            ),
+            groupmates AS (
+                SELECT other.user_id AS candidate, COUNT(DISTINCT other.group_id) AS shared
+                FROM groups_users gu
+                JOIN talks t ON t.id = gu.group_id AND t.kind = 'group'
+                JOIN groups_users other ON other.group_id = gu.group_id AND other.user_id != $1
+                WHERE gu.user_id = $1
+                GROUP BY other.user_id
+            )
+            SELECT
+                u.id,
+                u.name,
+                u.picture,
+                COALESCE(f.mutual, 0) AS mutual_contacts,
+                COALESCE(g.shared, 0) AS shared_groups
+            FROM users u
+            LEFT JOIN fof f ON f.candidate = u.id
+            LEFT JOIN groupmates g ON g.candidate = u.id
+            LEFT JOIN privacy_settings ps ON ps.user_id = u.id
+            WHERE (f.candidate IS NOT NULL OR g.candidate IS NOT NULL)
+            AND u.id != $1
+            -- discoverable_by as in user search, candidates are never contacts
+            -- so only contacts of contacts can be admitted past 'everyone'
+            AND CASE COALESCE(ps.discoverable_by, 'everyone')
+                WHEN 'everyone' THEN true
+                WHEN 'nobody' THEN false
+                WHEN 'contacts' THEN false
+                ELSE f.candidate IS NOT NULL
+            END
+            AND NOT EXISTS (
+                SELECT 1 FROM contacts x
+                WHERE (x.user_id_1 = $1 AND x.user_id_2 = u.id)
+                OR (x.user_id_1 = u.id AND x.user_id_2 = $1)
+            )
+            AND NOT EXISTS (
+                SELECT 1 FROM user_blocks b
+                WHERE (b.blocker = $1 AND b.blocked = u.id)
+                OR (b.blocker = u.id AND b.blocked = $1)
+            )
+            ORDER BY COALESCE(f.mutual, 0) + COALESCE(g.shared, 0) DESC, u.name
+            LIMIT $2
+            ",
+        )
+        .bind::<sql_types::Uuid, _>(user_id)
+        .bind::<sql_types::BigInt, _>(limit)
+        .load::<Suggestion>(&mut conn)
+        .map_err(super::Error::from)
+    }
+
+    fn find_relation(&self, me: &user::Id, you: &user::Id) -> super::Result<Relation> {
+        let mut conn = self.pool.get()?;
+
+        sql_query(
+            r"
+            WITH mine AS (
+                SELECT CASE WHEN c.user_id_1 = $1 THEN c.user_id_2 ELSE c.user_id_1 END AS friend
+                FROM contacts c
+                WHERE (c.user_id_1 = $1 OR c.user_id_2 = $1) AND c.status = 'accepted'
+            ),
+            yours AS (
+                SELECT CASE WHEN c.user_id_1 = $2 THEN c.user_id_2 ELSE c.user_id_1 END AS friend
+                FROM contacts c
+                WHERE (c.user_id_1 = $2 OR c.user_id_2 = $2) AND c.status = 'accepted'
+            )
+            SELECT
+                EXISTS (SELECT 1 FROM mine WHERE friend = $2) AS contact,
+                EXISTS (SELECT 1 FROM mine JOIN yours USING (friend)) AS mutual
+            ",
+        )
+        .bind::<sql_types::Uuid, _>(me)
+        .bind::<sql_types::Uuid, _>(you)
+        .get_result::<Closeness>(&mut conn)
+        .map(Relation::from)
+        .map_err(super::Error::from)
+    }
+
+    fn find_requests_audience(&self, user_id: &user::Id) -> super::Result<user::Audience> {
+        use crate::schema::privacy_settings::dsl as p;
+
+        let mut conn = self.pool.get()?;
+
+        let audience = p::privacy_settings
+            .find(user_id)
+            .select(p::contact_requests_from)
+            .first::<user::Audience>(&mut conn)
+            .optional()?;
+
+        Ok(audience.unwrap_or_default())
+    }
+
+    fn block(&self, b: &NewBlock) -> super::Result<()> {
+        use crate::schema::user_blocks::dsl::user_blocks;
+
+        let mut conn = self.pool.get()?;
+
+        insert_into(user_blocks)
+            .values(b)
+            .on_conflict_do_nothing()
+            .execute(&mut conn)?;
+
+        Ok(())
+    }
+
+    fn unblock(&self, blocker: &user::Id, blocked: &user::Id) -> super::Result<bool> {
+        use crate::schema::user_blocks::dsl as ub;
+
+        let mut conn = self.pool.get()?;
+
+        let deleted_count = delete(ub::user_blocks)
+            .filter(ub::blocker.eq(blocker).and(ub::blocked.eq(blocked)))
+            .execute(&mut conn)?;
+
+        Ok(deleted_count > 0)
+    }
+
+    fn is_blocked(&self, me: &user::Id, you: &user::Id) -> super::Result<bool> {
+        use crate::schema::user_blocks::dsl as ub;
+
+        let mut conn = self.pool.get()?;
+
+        let count: i64 = ub::user_blocks
+            .filter(
+                (ub::blocker.eq(me).and(ub::blocked.eq(you)))
+                    .or(ub::blocker.eq(you).and(ub::blocked.eq(me))),
+            )
+            .count()
+            .get_result(&mut conn)?;
+
+        Ok(count > 0)
+    }
+
+    fn find_blocked(&self, blocker: &user::Id) -> super::Result<Vec<BlockedUser>> {
+        let mut conn = self.pool.get()?;
+
+        sql_query(
+            r"
+            SELECT u.id, u.name, u.picture
+            FROM user_blocks ub
+            JOIN users u ON u.id = ub.blocked
+            WHERE ub.blocker = $1
+            ORDER BY ub.created_at DESC
+            ",
+        )
+        .bind::<sql_types::Uuid, _>(blocker)
+        .load::<BlockedUser>(&mut conn)
+        .map_err(super::Error::from)
+    }
+
+    fn find_block_relations(&self, user_id: &user::Id) -> super::Result<Vec<user::Id>> {
+        use crate::schema::user_blocks::dsl as ub;
+
+        let mut conn = self.pool.get()?;
+
+        let blocked: Vec<user::Id> = ub::user_blocks
+            .filter(ub::blocker.eq(user_id))
+            .select(ub::blocked)
+            .load(&mut conn)?;
+
+        let blockers: Vec<user::Id> = ub::user_blocks
+            .filter(ub::blocked.eq(user_id))
+            .select(ub::blocker)
+            .load(&mut conn)?;
+
+        Ok(blocked.into_iter().chain(blockers).collect())
+    }
+
+    fn upsert_alias(&self, a: &NewAlias) -> super::Result<()> {
+        use crate::schema::contact_aliases::dsl as ca;
+
+        let mut conn = self.pool.get()?;
+
+        insert_into(ca::contact_aliases)
+            .values(a)
+            .on_conflict((ca::owner, ca::user_id))
+            .do_update()
+            .set(ca::alias.eq(excluded(ca::alias)))
+            .execute(&mut conn)?;
+
+        Ok(())
+    }
+
+    fn delete_alias(&self, owner: &user::Id, user_id: &user::Id) -> super::Result<bool> {
+        use crate::schema::contact_aliases::dsl as ca;
+
+        let mut conn = self.pool.get()?;
+
+        let deleted_count = delete(ca::contact_aliases)
+            .filter(ca::owner.eq(owner).and(ca::user_id.eq(user_id)))
+            .execute(&mut conn)?;
+
+        Ok(deleted_count > 0)
+    }
+
+    fn find_alias(&self, owner: &user::Id, user_id: &user::Id) -> super::Result<Option<String>> {
+        use crate::schema::contact_aliases::dsl as ca;
+
+        let mut conn = self.pool.get()?;
+
+        ca::contact_aliases
+            .find((owner, user_id))
+            .select(ca::alias)
+            .first(&mut conn)
+            .optional()
+            .map_err(super::Error::from)
+    }
+
+    fn find_aliases(&self, owner: &user::Id) -> super::Result<Vec<(user::Id, String)>> {
+        use crate::schema::contact_aliases::dsl as ca;
+
+        let mut conn = self.pool.get()?;
+
+        ca::contact_aliases
+            .filter(ca::owner.eq(owner))
+            .select((ca::user_id, ca::alias))
+            .load(&mut conn)
+            .map_err(super::Error::from)
+    }
+
+    fn replace_labels(
+        &self,
+        owner: &user::Id,
+        user_id: &user::Id,
+        labels: &[NewLabel],
+    ) -> super::Result<()> {
+        use crate::schema::contact_labels::dsl as cl;
+
+        let mut conn = self.pool.get()?;
+
+        conn.transaction(|conn| {
+            delete(cl::contact_labels)
+                .filter(cl::owner.eq(owner).and(cl::user_id.eq(user_id)))
+                .execute(conn)?;
+
+            insert_into(cl::contact_labels)
+                .values(labels)
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+
+            Ok(())
+        })
+    }
+
+    fn find_labels(&self, owner: &user::Id) -> super::Result<Vec<(user::Id, String)>> {
+        use crate::schema::contact_labels::dsl as cl;
+
+        let mut conn = self.pool.get()?;
+
+        cl::contact_labels
+            .filter(cl::owner.eq(owner))
+            .select((cl::user_id, cl::label))
+            .order(cl::label)
+            .load(&mut conn)
+            .map_err(super::Error::from)
+    }
 }
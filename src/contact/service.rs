@@ -1,10 +1,18 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::Utc;
+use log::{debug, error};
 use messenger_service::AsStr;
 
-use crate::{integration::cache, user};
+use crate::{auth, event, integration::cache, user};
 
 use super::{
-    Id, Repository, Status, StatusTransition,
-    model::{Contact, ContactDto, Contacts, NewContact},
+    ALIAS_MAX_LEN, Id, LABEL_MAX_LEN, NOTE_MAX_LEN, Relation, Repository, Status, StatusTransition,
+    model::{
+        Annotations, BlockedUser, Contact, ContactDto, ContactRequest, Contacts, ImportReport,
+        NewAlias, NewBlock, NewContact, NewLabel, Suggestion,
+    },
 };
 
 #[async_trait::async_trait]
@@ -17,6 +25,10 @@ pub trait ContactService {
 
     async fn find_by_id(&self, auth_id: &user::Id, id: &Id) -> super::Result<ContactDto>;
 
+    /// Whether either of the users has blocked the other,
+    /// through the contact status or the block list.
+    async fn is_blocked(&self, auth_id: &user::Id, recipient: &user::Id) -> super::Result<bool>;
+
     async fn find_by_user_id(&self, user_id: &user::Id) -> super::Result<Vec<ContactDto>>;
 
     async fn find_by_user_id_and_status(
@@ -25,7 +37,21 @@ pub trait ContactService {
         s: &Status,
     ) -> super::Result<Vec<ContactDto>>;
 
-    async fn add(&self, me: &user::Id, you: &user::Id) -> super::Result<Status>;
+    /// Sends a contact request with an optional note and notifies the responder.
+    async fn add(
+        &self,
+        auth_user: &auth::User,
+        you: &user::Id,
+        note: Option<&str>,
+    ) -> super::Result<Status>;
+
+    /// Sends a request to each matched address. Addresses that cannot be
+    /// requested are reported, only failures of the store abort the import.
+    async fn import(
+        &self,
+        auth_user: &auth::User,
+        matches: &[(String, Option<user::Id>)],
+    ) -> super::Result<ImportReport>;
 
     async fn transition_status(
         &self,
@@ -35,17 +61,67 @@ pub trait ContactService {
     ) -> super::Result<Status>;
 
     async fn delete(&self, auth_id: &user::Id, contact: &user::Id) -> super::Result<()>;
+
+    /// "People you may know", the most connected first.
+    fn find_suggestions(&self, auth_id: &user::Id) -> super::Result<Vec<Suggestion>>;
+
+    fn find_relation(&self, auth_id: &user::Id, user_id: &user::Id) -> super::Result<Relation>;
+
+    /// Puts the user on the block list, whether or not they are a contact.
+    /// A pending request between the two is dropped.
+    async fn block_user(&self, auth_id: &user::Id, user_id: &user::Id) -> super::Result<()>;
+
+    fn unblock_user(&self, auth_id: &user::Id, user_id: &user::Id) -> super::Result<()>;
+
+    fn find_blocked_users(&self, auth_id: &user::Id) -> super::Result<Vec<BlockedUser>>;
+
+    /// Users on the block list of the given one, or having the given one on theirs.
+    fn find_block_relations(&self, auth_id: &user::Id) -> super::Result<Vec<user::Id>>;
+
+    /// Sets the private alias and labels the user gives to one of their contacts.
+    /// An empty alias removes it, labels replace the previous ones.
+    async fn annotate(
+        &self,
+        auth_id: &user::Id,
+        user_id: &user::Id,
+        alias: &str,
+        labels: &[&str],
+    ) -> super::Result<()>;
+
+    fn find_alias(&self, auth_id: &user::Id, user_id: &user::Id) -> super::Result<Option<String>>;
+
+    /// All aliases the user gave, by the user they stand for.
+    fn find_aliases(&self, auth_id: &user::Id) -> super::Result<HashMap<user::Id, String>>;
+
+    fn find_annotations(&self, auth_id: &user::Id) -> super::Result<Annotations>;
 }
 
 #[derive(Clone)]
 pub struct ContactServiceImpl {
     repo: Repository,
+    event_service: event::Service,
     redis: cache::Redis,
 }
 
 impl ContactServiceImpl {
-    pub fn new(repo: Repository, redis: cache::Redis) -> Self {
-        Self { repo, redis }
+    pub fn new(repo: Repository, event_service: event::Service, redis: cache::Redis) -> Self {
+        Self {
+            repo,
+            event_service,
+            redis,
+        }
+    }
+
+    /// Starts dropping pending requests past their time in the background.
+    pub fn spawn_expiry(&self) {
+        let s = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(EXPIRY_CHECK_INTERVAL);
+            loop {
+                interval.tick().await;
+                s.expire_pending().await;
+            }
+        });
     }
 }
 
@@ -95,6 +171,19 @@ impl ContactService for ContactServiceImpl {
         }
     }
 
+    async fn is_blocked(&self, auth_id: &user::Id, recipient: &user::Id) -> super::Result<bool> {
+        if auth_id.eq(recipient) {
+            return Ok(false);
+        }
+
+        if self.repo.is_blocked(auth_id, recipient)? {
+            return Ok(true);
+        }
+
+        let c = self.find(auth_id, recipient).await?;
+        Ok(c.is_some_and(|c| c.status().is_blocked()))
+    }
+
     async fn find_by_user_id(&self, user_id: &user::Id) -> super::Result<Vec<ContactDto>> {
         let contacts = self
             .redis
@@ -131,22 +220,97 @@ impl ContactService for ContactServiceImpl {
         }
     }
 
-    async fn add(&self, me: &user::Id, you: &user::Id) -> super::Result<Status> {
+    async fn add(
+        &self,
+        auth_user: &auth::User,
+        you: &user::Id,
+        note: Option<&str>,
+    ) -> super::Result<Status> {
+        let me = auth_user.id();
         if me.eq(you) {
             return Err(super::Error::SameUsers(me.clone()));
         }
 
+        let note = note.map(str::trim).filter(|n| !n.is_empty());
+        if let Some(n) = note
+            && n.chars().count() > NOTE_MAX_LEN
+        {
+            return Err(super::Error::NoteTooLong(n.chars().count()));
+        }
+
+        if self.repo.is_blocked(me, you)? {
+            return Err(super::Error::Blocked(you.clone()));
+        }
+
         let exists = self.repo.exists(me, you)?;
         if exists {
             return Err(super::Error::AlreadyExists);
         }
 
-        self.repo.add(&NewContact::new(me, you))?;
+        let audience = self.repo.find_requests_audience(you)?;
+        if audience.ne(&user::Audience::Everyone)
+            && !audience.admits(self.repo.find_relation(me, you)?)
+        {
+            return Err(super::Error::RequestsRestricted(you.clone()));
+        }
+
+        let id = self.repo.add(&NewContact::new(me, you, note))?;
         self.invalidate([me.clone(), you.clone()]).await;
 
-        Ok(Status::Pending {
+        let status = Status::Pending {
             initiator: me.clone(),
-        })
+        };
+
+        // seen from the responder's side
+        let request = ContactRequest::new(
+            ContactDto::new(
+                id,
+                you.clone(),
+                me.clone(),
+                status.clone(),
+                note.map(String::from),
+            ),
+            auth_user.name().to_string(),
+            auth_user.picture().clone(),
+        );
+        self.event_service
+            .publish(
+                &event::Subject::Notifications(you),
+                event::Notification::NewContactRequest(request).into(),
+            )
+            .await;
+
+        Ok(status)
+    }
+
+    async fn import(
+        &self,
+        auth_user: &auth::User,
+        matches: &[(String, Option<user::Id>)],
+    ) -> super::Result<ImportReport> {
+        let mut report = ImportReport::default();
+        for (address, user_id) in matches {
+            let Some(user_id) = user_id else {
+                report.unknown.push(address.clone());
+                continue;
+            };
+
+            if user_id.eq(auth_user.id()) {
+                continue;
+            }
+
+            match self.add(auth_user, user_id, None).await {
+                Ok(_) => report.requested.push(address.clone()),
+                Err(super::Error::AlreadyExists) => report.existing.push(address.clone()),
+                Err(e @ (super::Error::_R2d2(_) | super::Error::_Diesel(_))) => return Err(e),
+                Err(e) => {
+                    debug!("Skipping import address: {e}");
+                    report.skipped.push(address.clone());
+                }
+            }
+        }
+
+        Ok(report)
     }
 
     async fn transition_status(
@@ -163,6 +327,23 @@ impl ContactService for ContactServiceImpl {
                 self.repo.update_status(c.id(), &s)?;
                 self.invalidate([c.user_id_1().clone(), c.user_id_2().clone()])
                     .await;
+
+                // seen from the other party's side
+                let other = dto.recipient();
+                let updated = ContactDto::new(
+                    c.id().clone(),
+                    other.clone(),
+                    auth_id.clone(),
+                    s.clone(),
+                    c.note().map(String::from),
+                );
+                self.event_service
+                    .publish(
+                        &event::Subject::Notifications(other),
+                        event::Notification::ContactUpdated(updated).into(),
+                    )
+                    .await;
+
                 Ok(s)
             }
             None => Err(super::Error::NotFound(id.clone())),
@@ -172,11 +353,117 @@ impl ContactService for ContactServiceImpl {
     async fn delete(&self, auth_id: &user::Id, contact: &user::Id) -> super::Result<()> {
         assert_ne!(auth_id, contact);
 
+        let c = self.repo.find(auth_id, contact)?;
+
         self.repo.delete(auth_id, contact)?;
         self.invalidate([auth_id.clone(), contact.clone()]).await;
 
+        if let Some(c) = c {
+            self.event_service
+                .publish(
+                    &event::Subject::Notifications(contact),
+                    event::Notification::ContactRemoved {
+                        contact_id: c.id().clone(),
+                    }
+                    .into(),
+                )
+                .await;
+        }
+
+        Ok(())
+    }
+
+    fn find_suggestions(&self, auth_id: &user::Id) -> super::Result<Vec<Suggestion>> {
+        self.repo.find_suggestions(auth_id, SUGGESTIONS_LIMIT)
+    }
+
+    fn find_relation(&self, auth_id: &user::Id, user_id: &user::Id) -> super::Result<Relation> {
+        self.repo.find_relation(auth_id, user_id)
+    }
+
+    async fn block_user(&self, auth_id: &user::Id, user_id: &user::Id) -> super::Result<()> {
+        if auth_id.eq(user_id) {
+            return Err(super::Error::SameUsers(auth_id.clone()));
+        }
+
+        self.repo.block(&NewBlock::new(auth_id, user_id))?;
+
+        if let Some(c) = self.repo.find(auth_id, user_id)?
+            && Status::from(&c).is_pending()
+        {
+            self.repo.delete(auth_id, user_id)?;
+        }
+        self.invalidate([auth_id.clone(), user_id.clone()]).await;
+
         Ok(())
     }
+
+    fn unblock_user(&self, auth_id: &user::Id, user_id: &user::Id) -> super::Result<()> {
+        self.repo.unblock(auth_id, user_id)?;
+        Ok(())
+    }
+
+    fn find_blocked_users(&self, auth_id: &user::Id) -> super::Result<Vec<BlockedUser>> {
+        self.repo.find_blocked(auth_id)
+    }
+
+    async fn annotate(
+        &self,
+        auth_id: &user::Id,
+        user_id: &user::Id,
+        alias: &str,
+        labels: &[&str],
+    ) -> super::Result<()> {
+        match self.find(auth_id, user_id).await? {
+            Some(c) if !c.status().is_blocked() => {}
+            _ => return Err(super::Error::NotContact(user_id.clone())),
+        }
+
+        let alias = alias.trim();
+        let alias_len = alias.chars().count();
+        if alias_len > ALIAS_MAX_LEN {
+            return Err(super::Error::AliasTooLong(alias_len));
+        }
+
+        if let Some(l) = labels
+            .iter()
+            .find(|l| l.is_empty() || l.chars().count() > LABEL_MAX_LEN)
+        {
+            return Err(super::Error::InvalidLabel((*l).to_string()));
+        }
+
+        if alias.is_empty() {
+            self.repo.delete_alias(auth_id, user_id)?;
+        } else {
+            self.repo
+                .upsert_alias(&NewAlias::new(auth_id, user_id, alias))?;
+        }
+
+        let labels = labels
+            .iter()
+            .map(|l| NewLabel::new(auth_id, user_id, l))
+            .collect::<Vec<_>>();
+        self.repo.replace_labels(auth_id, user_id, &labels)
+    }
+
+    fn find_alias(&self, auth_id: &user::Id, user_id: &user::Id) -> super::Result<Option<String>> {
+        self.repo.find_alias(auth_id, user_id)
+    }
+
+    fn find_aliases(&self, auth_id: &user::Id) -> super::Result<HashMap<user::Id, String>> {
+        Ok(self.repo.find_aliases(auth_id)?.into_iter().collect())
+    }
+
+    fn find_annotations(&self, auth_id: &user::Id) -> super::Result<Annotations> {
+        let aliases = self.repo.find_aliases(auth_id)?.into_iter().collect();
+        let labels = self.repo.find_labels(auth_id)?;
+
+        Ok(Annotations::new(aliases, labels))
+    }
+
+    fn find_block_relations(&self, auth_id: &user::Id) -> super::Result<Vec<user::Id>> {
+        self.repo.find_block_relations(auth_id)
+    }
 }
 
 impl ContactServiceImpl {
@@ -200,6 +487,27 @@ impl ContactServiceImpl {
         Ok(contacts)
     }
 
+    async fn expire_pending(&self) {
+        let created_before = Utc::now() - PENDING_TTL;
+
+        let repo = self.repo.clone();
+        let expired = tokio::task::spawn_blocking(move || repo.delete_expired(&created_before));
+
+        match expired.await {
+            Ok(Ok(expired)) => {
+                for c in &expired {
+                    self.invalidate([c.user_id_1().clone(), c.user_id_2().clone()])
+                        .await;
+                }
+                if !expired.is_empty() {
+                    debug!("Expired {} pending contact requests", expired.len());
+                }
+            }
+            Ok(Err(e)) => error!("Failed to expire pending contact requests: {e:?}"),
+            Err(e) => error!("Failed to run expiry of pending contact requests: {e:?}"),
+        }
+    }
+
     async fn invalidate(&self, user_ids: [user::Id; 2]) {
         tokio::join!(
             self.redis.json_del(cache::Key::Contacts(&user_ids[0])),
@@ -220,5 +528,10 @@ fn map_to_dto(auth_id: &user::Id, c: &Contact) -> ContactDto {
         sender.clone(),
         recipient.clone(),
         Status::from(c),
+        c.note().map(String::from),
     )
 }
+
+const SUGGESTIONS_LIMIT: i64 = 10;
+const PENDING_TTL: chrono::Duration = chrono::Duration::days(30);
+const EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
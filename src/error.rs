@@ -4,7 +4,7 @@ use log::error;
 use maud::{Markup, Render, html};
 use serde::Serialize;
 
-use crate::{auth, contact, event, integration, message, talk, user};
+use crate::{auth, contact, event, integration, message, settings, talk, user};
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -22,9 +22,13 @@ pub enum Error {
     #[error(transparent)]
     _Message(#[from] message::Error),
     #[error(transparent)]
+    _Settings(#[from] settings::Error),
+    #[error(transparent)]
     _User(#[from] user::Error),
     #[error(transparent)]
     _Integration(#[from] integration::Error),
+    #[error(transparent)]
+    _Join(#[from] tokio::task::JoinError),
 }
 
 impl IntoResponse for Error {
@@ -58,8 +62,9 @@ impl From<Error> for StatusCode {
             Error::_Talk(t) => t.into(),
             Error::_Event(e) => e.into(),
             Error::_Message(m) => m.into(),
+            Error::_Settings(s) => s.into(),
             Error::_User(u) => u.into(),
-            Error::_Integration(_) => Self::INTERNAL_SERVER_ERROR,
+            Error::_Integration(_) | Error::_Join(_) => Self::INTERNAL_SERVER_ERROR,
         }
     }
 }
@@ -11,16 +11,20 @@ impl From<Error> for StatusCode {
 }
 
 pub(super) mod sse {
+    use crate::event::service::PayloadStream;
     use crate::event::{self, Notification, Subject};
-    use crate::{auth, user};
+    use crate::message::model::MessageDto;
+    use crate::user::model::ConnectionId;
+    use crate::{auth, talk, user};
     use axum::Extension;
     use axum::extract::State;
     use axum::response::sse;
-    use futures::{Stream, StreamExt};
+    use futures::{Stream, StreamExt, stream::SelectAll};
     use log::{debug, error};
     use maud::Render;
     use tokio::time;
 
+    use std::collections::HashMap;
     use std::time::Duration;
 
     const ONLINE_NOTI_INTERVAL: Duration = Duration::from_secs(15);
@@ -28,28 +32,85 @@ pub(super) mod sse {
     pub async fn notifications(
         auth_user: Extension<auth::User>,
         user_service: State<user::Service>,
+        talk_service: State<talk::Service>,
         event_service: State<event::Service>,
     ) -> sse::Sse<impl Stream<Item = crate::Result<sse::Event>>> {
         let auth_id = auth_user.id().clone();
 
         let stream = async_stream::try_stream! {
-            let mut noti_stream = event_service
-                .subscribe_noti(&Subject::Notifications(&auth_id))
-                .await?;
+            let mut noti_stream = SelectAll::new();
+            noti_stream.push(
+                event_service
+                    .subscribe_noti(&Subject::Notifications(&auth_id))
+                    .await?,
+            );
 
-            let _osd = OnlineStatusDropper(&auth_id, &user_service);
+            // channel posts are published once per channel, true while still subscribed
+            let mut channels = HashMap::new();
+            for id in talk_service.find_channel_ids(&auth_id)? {
+                noti_stream.push(
+                    event_service
+                        .subscribe_noti(&Subject::ChannelNotifications(&id))
+                        .await?,
+                );
+                channels.insert(id, true);
+            }
+
+            user_service.touch(&auth_id).await;
+            let conn = ConnectionId::random();
+            let _osd = OnlineStatusDropper(&auth_id, &conn, &user_service);
             let mut interval = time::interval(ONLINE_NOTI_INTERVAL);
             loop {
                 tokio::select! {
                     next = noti_stream.next() => {
                         if let Some(noti) = next {
                             match noti {
+                                Ok(Notification::ChannelJoined { talk_id }) => {
+                                    let s = follow_channel(&event_service, &mut channels, talk_id);
+                                    if let Some(s) = s.await {
+                                        noti_stream.push(s);
+                                    }
+                                },
+                                Ok(Notification::NewTalk(t))
+                                    if t.kind().eq(&talk::Kind::Channel) =>
+                                {
+                                    let id = t.id().clone();
+                                    let s = follow_channel(&event_service, &mut channels, id);
+                                    if let Some(s) = s.await {
+                                        noti_stream.push(s);
+                                    }
+                                    yield sse::Event::from(Notification::NewTalk(t))
+                                },
+                                Ok(Notification::TalkRemoved { talk_id }) => {
+                                    if let Some(subscribed) = channels.get_mut(&talk_id) {
+                                        *subscribed = false;
+                                    }
+                                    yield sse::Event::from(Notification::TalkRemoved { talk_id })
+                                },
+                                Ok(Notification::NewMessage { talk_id, last_message, .. })
+                                    if skips_channel_post(
+                                        &channels, &auth_id, &talk_id, &last_message
+                                    ) => {},
+                                Ok(Notification::NewMessage { talk_id, last_message, .. }) => {
+                                    let muted = talk_service
+                                        .is_muted(&auth_id, &talk_id)
+                                        .await
+                                        .unwrap_or_else(|e| {
+                                            error!("Failed to resolve mute of {talk_id:?}: {e:?}");
+                                            false
+                                        });
+                                    yield sse::Event::from(Notification::NewMessage {
+                                        talk_id,
+                                        last_message,
+                                        muted,
+                                    })
+                                },
                                 Ok(n) => yield sse::Event::from(n),
                                 Err(e) => error!("Error reading notification from stream: {e:?}"),
                             }
                         }
                     },
-                    _ = interval.tick() => user_service.notify_online(&auth_id).await
+                    _ = interval.tick() => user_service.heartbeat(&auth_id, &conn).await
                 }
             }
             // _osd drops here
@@ -63,17 +124,49 @@ pub(super) mod sse {
         )
     }
 
-    struct OnlineStatusDropper<'a>(&'a user::Id, &'a user::Service);
+    /// Subscribes to the notifications of a channel the user joined after connecting,
+    /// unless the stream already follows it.
+    async fn follow_channel(
+        event_service: &event::Service,
+        channels: &mut HashMap<talk::Id, bool>,
+        talk_id: talk::Id,
+    ) -> Option<PayloadStream<Notification>> {
+        if channels.insert(talk_id.clone(), true).is_some() {
+            return None;
+        }
+
+        event_service
+            .subscribe_noti(&Subject::ChannelNotifications(&talk_id))
+            .await
+            .inspect_err(|e| error!("Failed to follow channel {talk_id:?}: {e:?}"))
+            .ok()
+    }
+
+    /// Channel posts keep arriving after unsubscribing until the stream reconnects,
+    /// and the shared subject hands authors their own posts too.
+    fn skips_channel_post(
+        channels: &HashMap<talk::Id, bool>,
+        auth_id: &user::Id,
+        talk_id: &talk::Id,
+        msg: &MessageDto,
+    ) -> bool {
+        channels
+            .get(talk_id)
+            .is_some_and(|subscribed| !subscribed || (msg.owner().eq(auth_id) && !msg.is_system()))
+    }
+
+    struct OnlineStatusDropper<'a>(&'a user::Id, &'a ConnectionId, &'a user::Service);
 
     impl Drop for OnlineStatusDropper<'_> {
         fn drop(&mut self) {
             let sub = self.0.clone();
-            let user_service = self.1.clone();
+            let conn = self.1.clone();
+            let user_service = self.2.clone();
 
             debug!("SSE dropped for {sub:?}");
 
             tokio::spawn(async move {
-                user_service.notify_offline(&sub).await;
+                user_service.disconnect(&sub, &conn).await;
             });
         }
     }
@@ -83,7 +176,19 @@ pub(super) mod sse {
             let evt = match &noti {
                 Notification::OnlineStatusChange(f) => &format!("onlineStatusChange:{}", f.id()),
                 Notification::NewTalk(_) => "newTalk",
+                // muted talks still update the list, but don't trigger browser notifications
+                Notification::NewMessage {
+                    talk_id,
+                    muted: true,
+                    ..
+                } => &format!("mutedMessage:{}", &talk_id),
                 Notification::NewMessage { talk_id, .. } => &format!("newMessage:{}", &talk_id),
+                Notification::TalkRemoved { talk_id } => &format!("talkRemoved:{}", &talk_id),
+                Notification::TalkUpdated { talk_id, .. } => &format!("talkUpdated:{}", &talk_id),
+                Notification::NewContactRequest(_) => "newContactRequest",
+                Notification::ContactUpdated(_) => "contactUpdated",
+                Notification::ContactRemoved { .. } => "contactRemoved",
+                Notification::ChannelJoined { .. } => "channelJoined",
             };
 
             Self::default().event(evt).data(noti.render().into_string())
@@ -121,18 +226,24 @@ pub(super) mod ws {
         State(user_service): State<user::Service>,
         State(event_service): State<event::Service>,
         State(message_service): State<message::Service>,
+        State(talk_service): State<talk::Service>,
     ) -> crate::Result<Response> {
         debug!("Upgrading to WS for talk: {}", &talk_id);
         user_service.check_member(&talk_id, &auth_user).await?;
+        talk_service
+            .check_not_blocked(auth_user.id(), &talk_id)
+            .await?;
+        let is_channel = talk_service.find_kind(&talk_id)?.eq(&talk::Kind::Channel);
 
         let auth_id = auth_user.id().clone();
-        Ok(ws.on_upgrade(move |socket| async {
+        Ok(ws.on_upgrade(move |socket| async move {
             let (sender, recv) = socket.split();
             let close = Arc::new(Notify::new());
 
             tokio::spawn(send(
                 auth_id,
                 talk_id.clone(),
+                is_channel,
                 sender,
                 event_service,
                 message_service,
@@ -146,14 +257,18 @@ pub(super) mod ws {
     async fn send(
         auth_id: user::Id,
         talk_id: talk::Id,
+        is_channel: bool,
         mut sender: SplitSink<WebSocket, ws::Message>,
         event_service: event::Service,
         message_service: message::Service,
         close: Arc<Notify>,
     ) -> event::Result<()> {
-        let mut msg_stream = event_service
-            .subscribe_event(&Subject::Messages(&auth_id, &talk_id))
-            .await?;
+        let subject = if is_channel {
+            Subject::Channel(&talk_id)
+        } else {
+            Subject::Messages(&auth_id, &talk_id)
+        };
+        let mut msg_stream = event_service.subscribe_event(&subject).await?;
 
         loop {
             tokio::select! {
@@ -169,13 +284,23 @@ pub(super) mod ws {
                         },
                     };
 
+                    // the channel subject is shared, so authors receive their own messages too
+                    if is_channel
+                        && let Message::New(m) | Message::Updated { msg: m } = &msg
+                        && m.owner().eq(&auth_id)
+                        && !m.is_system()
+                    {
+                        continue;
+                    }
+
                     let markup = msg.render().into_string();
                     if let Err(e) = sender.send(ws::Message::Text(markup.into())).await {
                         error!("Failed to send event message to client: {e}");
                         break;
                     }
 
-                    if let Message::New(msg) = msg {
+                    // seen status is meaningless with many readers
+                    if !is_channel && let Message::New(msg) = msg {
                         if let Err(e) = message_service.mark_as_seen(&auth_id, &[msg]).await {
                             error!("Failed to mark message as seen: {e}");
                         }
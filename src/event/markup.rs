@@ -1,9 +1,10 @@
 use maud::{Markup, Render, html};
 
 use crate::{
+    contact,
     markup::IdExt,
     message::{self, markup::MESSAGE_LIST_ID},
-    user,
+    talk, user,
 };
 
 use super::{Message, Notification};
@@ -16,7 +17,17 @@ impl Render for Notification {
             Self::NewMessage {
                 talk_id,
                 last_message,
+                ..
             } => message::markup::last_message(Some(last_message), talk_id, None),
+            Self::TalkRemoved { .. } | Self::ChannelJoined { .. } => html! {},
+            Self::TalkUpdated {
+                talk_id,
+                name,
+                picture,
+            } => talk::markup::talk_updated(talk_id, name, picture),
+            Self::NewContactRequest(request) => contact::markup::IncomingRequest(request).render(),
+            Self::ContactUpdated(c) => contact::markup::contact_updated(c),
+            Self::ContactRemoved { contact_id } => contact::markup::contact_removed(contact_id),
         }
     }
 }
@@ -38,6 +49,10 @@ impl Render for Message {
                 Self::Seen(msg) => div #(msg.id().attr()) hx-swap-oob="beforeend" {
                     (message::markup::Icon::Seen)
                 },
+                Self::Pinned { talk_id, msg } => (talk::markup::PinnedMessage {
+                    talk_id,
+                    msg: msg.as_ref(),
+                }),
             }
         }
     }
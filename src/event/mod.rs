@@ -1,3 +1,4 @@
+use std::collections::HashSet;
 use std::sync::Arc;
 
 use axum::{
@@ -9,7 +10,7 @@ use serde::{Deserialize, Serialize};
 use service::EventService;
 
 use crate::state::AppServices;
-use crate::{message, talk, user};
+use crate::{contact, message, talk, user};
 
 mod handler;
 mod markup;
@@ -29,6 +30,42 @@ pub fn api<S>(s: AppServices) -> Router<S> {
 pub enum Subject<'a> {
     Notifications(&'a user::Id),
     Messages(&'a user::Id, &'a talk::Id),
+    /// Shared by all subscribers of a channel.
+    Channel(&'a talk::Id),
+    /// Notifications about channel posts, shared by all subscribers like [`Subject::Channel`].
+    ChannelNotifications(&'a talk::Id),
+}
+
+impl<'a> Subject<'a> {
+    /// Subjects of message events in a talk. Channels publish once to a shared subject
+    /// instead of once per subscriber, so their recipients are not needed.
+    pub fn messages(
+        talk_id: &'a talk::Id,
+        kind: &talk::Kind,
+        recipients: &'a HashSet<user::Id>,
+    ) -> Vec<Self> {
+        match kind {
+            talk::Kind::Channel => vec![Self::Channel(talk_id)],
+            talk::Kind::Chat | talk::Kind::Group => recipients
+                .iter()
+                .map(|r| Self::Messages(r, talk_id))
+                .collect(),
+        }
+    }
+
+    /// Subjects of new message notifications of a talk, shared by channel subscribers likewise.
+    pub fn message_notifications(
+        talk_id: &'a talk::Id,
+        kind: &talk::Kind,
+        recipients: &'a HashSet<user::Id>,
+    ) -> Vec<Self> {
+        match kind {
+            talk::Kind::Channel => vec![Self::ChannelNotifications(talk_id)],
+            talk::Kind::Chat | talk::Kind::Group => {
+                recipients.iter().map(Self::Notifications).collect()
+            }
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -39,6 +76,26 @@ pub enum Notification {
     NewMessage {
         talk_id: talk::Id,
         last_message: message::model::MessageDto,
+        // resolved per recipient when streamed
+        #[serde(skip)]
+        muted: bool,
+    },
+    TalkRemoved {
+        talk_id: talk::Id,
+    },
+    TalkUpdated {
+        talk_id: talk::Id,
+        name: String,
+        picture: talk::Picture,
+    },
+    NewContactRequest(contact::model::ContactRequest),
+    ContactUpdated(contact::model::ContactDto),
+    ContactRemoved {
+        contact_id: contact::Id,
+    },
+    /// Tells open notification streams of the user to follow the channel, never rendered.
+    ChannelJoined {
+        talk_id: talk::Id,
     },
 }
 
@@ -46,9 +103,17 @@ pub enum Notification {
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum Message {
     New(message::model::MessageDto),
-    Updated { msg: message::model::MessageDto },
-    Deleted { id: message::Id },
+    Updated {
+        msg: message::model::MessageDto,
+    },
+    Deleted {
+        id: message::Id,
+    },
     Seen(message::model::MessageDto),
+    Pinned {
+        talk_id: talk::Id,
+        msg: Option<message::model::MessageDto>,
+    },
 }
 
 #[derive(thiserror::Error, Debug)]
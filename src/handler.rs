@@ -3,17 +3,21 @@ use crate::{
     contact::{self, markup::ContactInfos, model::ContactDto},
     markup::{Tab, TabControls, Tabs, Wrappable},
     settings,
-    talk::markup::TalkWindow,
+    talk::{markup::TalkWindow, model::Page},
     user,
 };
-use axum::{Extension, extract::State};
+use axum::{
+    Extension,
+    extract::{Query, State},
+};
 use maud::{Markup, Render};
+use serde::Deserialize;
 
 use crate::{talk, user::model::UserDto};
 
 // first shown component is chats page
-pub async fn home() -> crate::Result<Wrappable> {
-    Ok(Wrappable::new(Tabs {}))
+pub async fn home(auth_user: Extension<auth::User>) -> crate::Result<Wrappable> {
+    Ok(Wrappable::new(Tabs(auth_user.settings())))
 }
 
 // GET /tabs/chats
@@ -21,9 +25,11 @@ pub async fn chats_tab(
     auth_user: Extension<auth::User>,
     talk_service: State<talk::Service>,
 ) -> crate::Result<Markup> {
-    let chats = talk_service.find_all_by_kind(&auth_user, &talk::Kind::Chat)?;
+    let kind = talk::Kind::Chat;
+    let chats = talk_service.find_all_by_kind(&auth_user, &kind, &Page::active(None))?;
+    let archived = talk_service.find_all_by_kind(&auth_user, &kind, &Page::archived())?;
 
-    let tab_content = TalkWindow::chats(&auth_user, &chats);
+    let tab_content = TalkWindow::chats(&auth_user, &chats, &archived);
     Ok(Tab::new(TabControls::Chats, tab_content).render())
 }
 
@@ -32,17 +38,39 @@ pub async fn groups_tab(
     auth_user: Extension<auth::User>,
     talk_service: State<talk::Service>,
 ) -> crate::Result<Markup> {
-    let groups = talk_service.find_all_by_kind(&auth_user, &talk::Kind::Group)?;
+    let kind = talk::Kind::Group;
+    let groups = talk_service.find_all_by_kind(&auth_user, &kind, &Page::active(None))?;
+    let archived = talk_service.find_all_by_kind(&auth_user, &kind, &Page::archived())?;
 
-    let tab_content = TalkWindow::groups(&auth_user, &groups);
+    let tab_content = TalkWindow::groups(&auth_user, &groups, &archived);
     Ok(Tab::new(TabControls::Groups, tab_content).render())
 }
 
+// GET /tabs/channels
+pub async fn channels_tab(
+    auth_user: Extension<auth::User>,
+    talk_service: State<talk::Service>,
+) -> crate::Result<Markup> {
+    let kind = talk::Kind::Channel;
+    let channels = talk_service.find_all_by_kind(&auth_user, &kind, &Page::active(None))?;
+    let archived = talk_service.find_all_by_kind(&auth_user, &kind, &Page::archived())?;
+    let unsubscribed = talk_service.find_unsubscribed_channels(auth_user.id())?;
+
+    let tab_content = TalkWindow::channels(&auth_user, &channels, &archived, unsubscribed);
+    Ok(Tab::new(TabControls::Channels, tab_content).render())
+}
+
+#[derive(Deserialize)]
+pub struct ContactsTabParams {
+    label: Option<String>,
+}
+
 // GET /tabs/contacts
 pub async fn contacts_tab(
     auth_user: Extension<auth::User>,
     contact_service: State<contact::Service>,
     user_service: State<user::Service>,
+    params: Query<ContactsTabParams>,
 ) -> crate::Result<Markup> {
     let contacts = contact_service.find_by_user_id(auth_user.id()).await?;
 
@@ -55,14 +83,28 @@ pub async fn contacts_tab(
         ci
     };
 
+    let suggestions = contact_service.find_suggestions(auth_user.id())?;
+    let annotations = contact_service.find_annotations(auth_user.id())?;
+
     Ok(Tab::new(
         TabControls::Contacts,
-        ContactInfos::new(&auth_user, &contact_infos),
+        ContactInfos::new(
+            &auth_user,
+            &contact_infos,
+            &suggestions,
+            &annotations,
+            params.label.as_deref(),
+        ),
     )
     .render())
 }
 
 // GET /tabs/settings
-pub async fn settings_tab() -> Markup {
-    Tab::new(TabControls::Settings, settings::markup::List).render()
+pub async fn settings_tab(
+    auth_user: Extension<auth::User>,
+    user_service: State<user::Service>,
+) -> crate::Result<Markup> {
+    let u = user_service.find_one(auth_user.id()).await?;
+
+    Ok(Tab::new(TabControls::Settings, settings::markup::List(u.presence())).render())
 }
@@ -9,7 +9,8 @@ use serde::Serialize;
 use uuid::Uuid;
 
 use crate::contact::model::Contacts;
-use crate::user::model::UserDto;
+use crate::settings::model::Settings;
+use crate::user::model::{Privacy, UserDto};
 use crate::{auth, talk, user};
 
 #[derive(Clone)]
@@ -36,7 +37,11 @@ impl Redis {
     {
         trace!("SET_EX -> {key:?}");
         let mut con = self.con.clone();
-        if let Err(e) = con.set_ex::<_, _, ()>(&key, value, key.ttl()).await {
+        let res = match key.ttl() {
+            Some(ttl) => con.set_ex::<_, _, ()>(&key, value, ttl).await,
+            None => con.set::<_, _, ()>(&key, value).await,
+        };
+        if let Err(e) = res {
             error!("Failed to SET_EX on {key:?}. Reason: {e:?}");
         }
     }
@@ -88,6 +93,77 @@ impl Redis {
         }
     }
 
+    /// Adds the member or updates its score.
+    pub async fn zadd<M, S>(&self, key: Key<'_>, member: M, score: S)
+    where
+        M: redis::ToRedisArgs + Send + Sync,
+        S: redis::ToRedisArgs + Send + Sync,
+    {
+        trace!("ZADD -> {key:?}");
+        let mut con = self.con.clone();
+        if let Err(e) = con.zadd::<_, _, _, ()>(&key, member, score).await {
+            error!("Failed to ZADD on {key:?}. Reason: {e:?}");
+        }
+    }
+
+    /// Whether the member was present and got removed.
+    pub async fn zrem<M>(&self, key: Key<'_>, member: M) -> bool
+    where
+        M: redis::ToRedisArgs + Send + Sync,
+    {
+        trace!("ZREM -> {key:?}");
+        let mut con = self.con.clone();
+        match con.zrem::<_, _, usize>(&key, member).await {
+            Ok(removed) => removed > 0,
+            Err(e) => {
+                error!("Failed to ZREM on {key:?}. Reason: {e:?}");
+                false
+            }
+        }
+    }
+
+    pub async fn zrembyscore<S>(&self, key: Key<'_>, min: S, max: S)
+    where
+        S: redis::ToRedisArgs + Send + Sync,
+    {
+        trace!("ZREMRANGEBYSCORE -> {key:?}");
+        let mut con = self.con.clone();
+        if let Err(e) = con.zrembyscore::<_, _, _, ()>(&key, min, max).await {
+            error!("Failed to ZREMRANGEBYSCORE on {key:?}. Reason: {e:?}");
+        }
+    }
+
+    pub async fn zcount<S>(&self, key: Key<'_>, min: S, max: S) -> usize
+    where
+        S: redis::ToRedisArgs + Send + Sync,
+    {
+        trace!("ZCOUNT -> {key:?}");
+        let mut con = self.con.clone();
+        match con.zcount::<_, _, _, usize>(&key, min, max).await {
+            Ok(count) => count,
+            Err(e) => {
+                error!("Failed to ZCOUNT on {key:?}. Reason: {e:?}");
+                0
+            }
+        }
+    }
+
+    pub async fn zrangebyscore<V, S>(&self, key: Key<'_>, min: S, max: S) -> Vec<V>
+    where
+        V: redis::FromRedisValue,
+        S: redis::ToRedisArgs + Send + Sync,
+    {
+        trace!("ZRANGEBYSCORE -> {key:?}");
+        let mut con = self.con.clone();
+        match con.zrangebyscore::<_, _, _, Vec<V>>(&key, min, max).await {
+            Ok(members) => members,
+            Err(e) => {
+                error!("Failed to ZRANGEBYSCORE on {key:?}. Reason: {e:?}");
+                Vec::with_capacity(0)
+            }
+        }
+    }
+
     pub async fn get<V>(&self, key: Key<'_>) -> Option<V>
     where
         V: redis::FromRedisValue,
@@ -135,6 +211,20 @@ impl Redis {
         }
     }
 
+    pub async fn exists(&self, key: Key<'_>) -> bool {
+        let mut con = self.con.clone();
+        match con.exists::<_, bool>(&key).await {
+            Ok(exists) => {
+                trace!("EXISTS ({exists}) -> {key:?}");
+                exists
+            }
+            Err(e) => {
+                error!("Failed to EXISTS on {key:?}. Reason: {e:?}");
+                false
+            }
+        }
+    }
+
     pub async fn get_del<V>(&self, key: Key<'_>) -> Option<V>
     where
         V: redis::FromRedisValue,
@@ -178,8 +268,12 @@ impl Redis {
 
     pub async fn expire(&self, key: Key<'_>) {
         trace!("EXPIRE -> {key:?}");
+        let Some(ttl) = key.ttl() else {
+            return;
+        };
+
         let mut con = self.con.clone();
-        match i64::try_from(key.ttl()) {
+        match i64::try_from(ttl) {
             Ok(ttl) => {
                 if let Err(e) = con.expire::<_, ()>(&key, ttl).await {
                     error!("Failed to EXPIRE on {key:?}. Reason: {e:?}");
@@ -240,24 +334,53 @@ pub enum Key<'a> {
     User(&'a user::Id),
     Sub(&'a user::Sub),
     Contacts(&'a user::Id),
+    Privacy(&'a user::Id),
+    Settings(&'a user::Id),
     Members(&'a talk::Id),
+    /// Until when the user muted the talk, in seconds since epoch, 0 if not muted.
+    Muted(&'a user::Id, &'a talk::Id),
     Session(&'a auth::Session),
+    /// Sessions of a subject, so that all of them can be revoked at once.
+    Sessions(&'a user::Sub),
     Csrf(&'a auth::Csrf),
+    /// Event streams of the user scored by their heartbeat expiry, one per tab or device.
+    Connections(&'a user::Id),
+    /// Users with at least one live connection, scored by their latest heartbeat expiry.
+    OnlineUsers,
+    /// Present while the user interacts with the app, gone once they are away.
+    Activity(&'a user::Id),
 }
 
 impl Key<'_> {
-    /// Returns a time-to-live value in seconds for the key.
-    pub const fn ttl(&self) -> u64 {
+    /// Returns a time-to-live value in seconds for the key, none for keys that never expire.
+    pub const fn ttl(&self) -> Option<u64> {
         match self {
             // Just in case if token response does not provide an expiration claim
             // fallback with 3600 for Key::Session
-            Key::User(_) | Key::Sub(_) | Key::Members(_) | Key::Session(_) | Key::Contacts(_) => {
-                3600
-            }
+            Key::User(_)
+            | Key::Sub(_)
+            | Key::Members(_)
+            | Key::Muted(..)
+            | Key::Session(_)
+            | Key::Contacts(_)
+            | Key::Privacy(_)
+            | Key::Settings(_) => Some(3600),
 
             // Since most of IDPs don't provide a code exchange TTL through
             // introspection endpoint - we set a limit of 120 seconds.
-            Key::Csrf(_) => 120,
+            Key::Csrf(_) => Some(120),
+
+            // Outlives the sessions it lists, whose TTL follows the token.
+            Key::Sessions(_) => Some(24 * 3600),
+
+            // Refreshed by the online status tick of the event stream.
+            Key::Connections(_) => Some(45),
+
+            // Members expire by their score, the set itself lives on.
+            Key::OnlineUsers => None,
+
+            // Users idle for longer are shown as away.
+            Key::Activity(_) => Some(300),
         }
     }
 }
@@ -268,9 +391,16 @@ impl Display for Key<'_> {
             Self::User(id) => write!(f, "user:{id}"),
             Self::Sub(sub) => write!(f, "sub:{sub}"),
             Self::Contacts(id) => write!(f, "contacts:{id}"),
+            Self::Privacy(id) => write!(f, "privacy:{id}"),
+            Self::Settings(id) => write!(f, "settings:{id}"),
             Self::Members(id) => write!(f, "talk:{id}"),
+            Self::Muted(user_id, talk_id) => write!(f, "muted:{user_id}:{talk_id}"),
             Self::Session(s) => write!(f, "session:{}", s.as_str()),
+            Self::Sessions(sub) => write!(f, "sessions:{sub}"),
             Self::Csrf(csrf) => write!(f, "csrf:{}", csrf.as_str()),
+            Self::Connections(id) => write!(f, "connections:{id}"),
+            Self::OnlineUsers => write!(f, "online"),
+            Self::Activity(id) => write!(f, "activity:{id}"),
         }
     }
 }
@@ -302,6 +432,15 @@ impl redis::ToRedisArgs for user::Id {
     }
 }
 
+impl redis::ToRedisArgs for user::model::ConnectionId {
+    fn write_redis_args<W>(&self, out: &mut W)
+    where
+        W: ?Sized + redis::RedisWrite,
+    {
+        self.get().hyphenated().to_string().write_redis_args(out);
+    }
+}
+
 impl redis::FromRedisValue for UserDto {
     fn from_redis_value(value: &redis::Value) -> redis::RedisResult<Self> {
         let u: Self = serde_json::from_str(&String::from_redis_value(value)?)?;
@@ -318,6 +457,20 @@ impl redis::ToRedisArgs for UserDto {
     }
 }
 
+impl redis::FromRedisValue for Privacy {
+    fn from_redis_value(value: &redis::Value) -> redis::RedisResult<Self> {
+        let p: Self = serde_json::from_str(&String::from_redis_value(value)?)?;
+        Ok(p)
+    }
+}
+
+impl redis::FromRedisValue for Settings {
+    fn from_redis_value(value: &redis::Value) -> redis::RedisResult<Self> {
+        let s: Self = serde_json::from_str(&String::from_redis_value(value)?)?;
+        Ok(s)
+    }
+}
+
 impl redis::FromRedisValue for auth::Csrf {
     fn from_redis_value(v: &redis::Value) -> redis::RedisResult<Self> {
         Ok(Self::new(String::from_redis_value(v)?))
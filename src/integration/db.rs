@@ -9,12 +9,20 @@ pub mod pg {
     use std::io::Write;
     use uuid::Uuid;
 
-    use crate::schema::sql_types::TalkKind;
-    use crate::{contact, message, talk, user};
+    use crate::schema::sql_types::{Audience, GroupRole, TalkKind, Theme, UserPresence};
+    use crate::{contact, message, settings, talk, user};
 
     use diesel::{PgConnection, r2d2::ConnectionManager};
     use log::warn;
 
+    /// Escapes `LIKE` wildcards so that user input is matched literally,
+    /// meant for patterns compared with `ESCAPE '\\'`.
+    pub fn escape_like(s: &str) -> String {
+        s.replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_")
+    }
+
     #[derive(Clone)]
     pub struct Config {
         host: String,
@@ -111,6 +119,90 @@ pub mod pg {
         }
     }
 
+    impl<DB> FromSql<GroupRole, DB> for talk::Role
+    where
+        DB: Backend,
+        String: FromSql<sql_types::Text, DB>,
+    {
+        fn from_sql(bytes: DB::RawValue<'_>) -> deserialize::Result<Self> {
+            let s = String::from_sql(bytes)?;
+            match Self::try_from(s) {
+                Ok(role) => Ok(role),
+                Err(e) => Err(Box::new(e)),
+            }
+        }
+    }
+
+    impl ToSql<GroupRole, diesel::pg::Pg> for talk::Role {
+        fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, diesel::pg::Pg>) -> serialize::Result {
+            out.write_all(self.as_str().as_bytes())?;
+            Ok(IsNull::No)
+        }
+    }
+
+    impl<DB> FromSql<UserPresence, DB> for user::Presence
+    where
+        DB: Backend,
+        String: FromSql<sql_types::Text, DB>,
+    {
+        fn from_sql(bytes: DB::RawValue<'_>) -> deserialize::Result<Self> {
+            let s = String::from_sql(bytes)?;
+            match Self::try_from(s) {
+                Ok(presence) => Ok(presence),
+                Err(e) => Err(Box::new(e)),
+            }
+        }
+    }
+
+    impl ToSql<UserPresence, diesel::pg::Pg> for user::Presence {
+        fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, diesel::pg::Pg>) -> serialize::Result {
+            out.write_all(self.as_str().as_bytes())?;
+            Ok(IsNull::No)
+        }
+    }
+
+    impl<DB> FromSql<Audience, DB> for user::Audience
+    where
+        DB: Backend,
+        String: FromSql<sql_types::Text, DB>,
+    {
+        fn from_sql(bytes: DB::RawValue<'_>) -> deserialize::Result<Self> {
+            let s = String::from_sql(bytes)?;
+            match Self::try_from(s) {
+                Ok(audience) => Ok(audience),
+                Err(e) => Err(Box::new(e)),
+            }
+        }
+    }
+
+    impl ToSql<Audience, diesel::pg::Pg> for user::Audience {
+        fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, diesel::pg::Pg>) -> serialize::Result {
+            out.write_all(self.as_str().as_bytes())?;
+            Ok(IsNull::No)
+        }
+    }
+
+    impl<DB> FromSql<Theme, DB> for settings::Theme
+    where
+        DB: Backend,
+        String: FromSql<sql_types::Text, DB>,
+    {
+        fn from_sql(bytes: DB::RawValue<'_>) -> deserialize::Result<Self> {
+            let s = String::from_sql(bytes)?;
+            match Self::try_from(s) {
+                Ok(theme) => Ok(theme),
+                Err(e) => Err(Box::new(e)),
+            }
+        }
+    }
+
+    impl ToSql<Theme, diesel::pg::Pg> for settings::Theme {
+        fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, diesel::pg::Pg>) -> serialize::Result {
+            out.write_all(self.as_str().as_bytes())?;
+            Ok(IsNull::No)
+        }
+    }
+
     impl<DB> FromSql<sql_types::Uuid, DB> for user::Id
     where
         DB: Backend,
@@ -195,4 +287,46 @@ pub mod pg {
             Ok(IsNull::No)
         }
     }
+
+    impl<DB> FromSql<sql_types::Text, DB> for settings::TimeZone
+    where
+        DB: Backend,
+        String: FromSql<sql_types::Text, DB>,
+    {
+        fn from_sql(bytes: DB::RawValue<'_>) -> deserialize::Result<Self> {
+            let s = String::from_sql(bytes)?;
+            match Self::try_from(s) {
+                Ok(tz) => Ok(tz),
+                Err(e) => Err(Box::new(e)),
+            }
+        }
+    }
+
+    impl ToSql<sql_types::Text, diesel::pg::Pg> for settings::TimeZone {
+        fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, diesel::pg::Pg>) -> serialize::Result {
+            out.write_all(self.as_str().as_bytes())?;
+            Ok(IsNull::No)
+        }
+    }
+
+    impl<DB> FromSql<sql_types::Text, DB> for settings::Locale
+    where
+        DB: Backend,
+        String: FromSql<sql_types::Text, DB>,
+    {
+        fn from_sql(bytes: DB::RawValue<'_>) -> deserialize::Result<Self> {
+            let s = String::from_sql(bytes)?;
+            match Self::try_from(s) {
+                Ok(locale) => Ok(locale),
+                Err(e) => Err(Box::new(e)),
+            }
+        }
+    }
+
+    impl ToSql<sql_types::Text, diesel::pg::Pg> for settings::Locale {
+        fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, diesel::pg::Pg>) -> serialize::Result {
+            out.write_all(self.as_str().as_bytes())?;
+            Ok(IsNull::No)
+        }
+    }
 }
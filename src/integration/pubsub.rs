@@ -51,6 +51,10 @@ impl async_nats::subject::ToSubject for &event::Subject<'_> {
             event::Subject::Messages(user_id, talk_id) => {
                 format!("messages.{user_id}.{talk_id}").into()
             }
+            event::Subject::Channel(talk_id) => format!("channel.{talk_id}").into(),
+            event::Subject::ChannelNotifications(talk_id) => {
+                format!("noti.channel.{talk_id}").into()
+            }
         }
     }
 }
@@ -60,6 +64,8 @@ impl fmt::Display for &event::Subject<'_> {
         match self {
             event::Subject::Notifications(user_id) => write!(f, "noti.{user_id}"),
             event::Subject::Messages(user_id, talk_id) => write!(f, "messages.{user_id}.{talk_id}"),
+            event::Subject::Channel(talk_id) => write!(f, "channel.{talk_id}"),
+            event::Subject::ChannelNotifications(talk_id) => write!(f, "noti.channel.{talk_id}"),
         }
     }
 }
@@ -30,6 +30,15 @@ impl S3 {
         Ok(())
     }
 
+    pub async fn put(&self, blob: Blob<'_>, data: Vec<u8>) -> super::Result<()> {
+        self.client
+            .put_object_content(BUCKET, blob, ObjectContent::from(data))
+            .send()
+            .await
+            .map_err(Box::new)?;
+        Ok(())
+    }
+
     pub async fn find_one(&self, blob: Blob<'_>) -> super::Result<ObjectContent> {
         let res = self
             .client
@@ -64,6 +64,7 @@ fn app(s: &AppServices, env: &Env) -> Router {
             Router::new()
                 .route("/chats", get(handler::chats_tab))
                 .route("/groups", get(handler::groups_tab))
+                .route("/channels", get(handler::channels_tab))
                 .route("/contacts", get(handler::contacts_tab))
                 .route("/settings", get(handler::settings_tab)),
         )
@@ -72,14 +73,18 @@ fn app(s: &AppServices, env: &Env) -> Router {
             Router::new()
                 .merge(contact::api(s.clone()))
                 .merge(message::api(s.clone()))
+                .merge(settings::api(s.clone()))
                 .merge(talk::api(s.clone()))
                 .merge(user::api(s.clone())),
         )
         .nest(
             "/templates",
             Router::new()
+                .merge(contact::templates(s.clone()))
                 .merge(message::templates(s.clone()))
-                .merge(talk::templates(s.clone())),
+                .merge(settings::templates(s.clone()))
+                .merge(talk::templates(s.clone()))
+                .merge(user::templates(s.clone())),
         )
         .route_layer(
             ServiceBuilder::new()
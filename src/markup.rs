@@ -7,6 +7,8 @@ use axum::{
 use maud::{DOCTYPE, Markup, PreEscaped, Render, html};
 use reqwest::header::CONTENT_LENGTH;
 
+use crate::settings::{markup::Preferences, model::Settings};
+
 pub const EMPTY: PreEscaped<&'static str> = PreEscaped("");
 
 pub trait IdExt {
@@ -114,6 +116,7 @@ pub fn wrap_in_base(mut resp: Response) -> impl IntoResponse {
 pub enum TabControls {
     Chats,
     Groups,
+    Channels,
     Contacts,
     Settings,
 }
@@ -124,6 +127,7 @@ impl Render for TabControls {
             div ."flex flex-row text-2xl" role="tablist" {
                 (TabControlItem::Chats(self))
                 (TabControlItem::Groups(self))
+                (TabControlItem::Channels(self))
                 (TabControlItem::Contacts(self))
                 (TabControlItem::Settings(self))
             }
@@ -134,6 +138,7 @@ impl Render for TabControls {
 enum TabControlItem<'a> {
     Chats(&'a TabControls),
     Groups(&'a TabControls),
+    Channels(&'a TabControls),
     Contacts(&'a TabControls),
     Settings(&'a TabControls),
 }
@@ -151,6 +156,11 @@ impl Render for TabControlItem<'_> {
                 TabControls::Groups.eq(at),
                 "fa-solid fa-people-group",
             ),
+            TabControlItem::Channels(at) => (
+                "/tabs/channels",
+                TabControls::Channels.eq(at),
+                "fa-solid fa-bullhorn",
+            ),
             TabControlItem::Contacts(at) => (
                 "/tabs/contacts",
                 TabControls::Contacts.eq(at),
@@ -172,6 +182,9 @@ impl Render for TabControlItem<'_> {
                 aria-controls="tab-content"
             {
                 i .(i_class) {}
+                @if let TabControlItem::Contacts(_) = self {
+                    (crate::contact::markup::RequestsBadge)
+                }
             }
         }
     }
@@ -205,11 +218,15 @@ impl Render for Tab {
     }
 }
 
-pub struct Tabs;
+pub struct Tabs<'a>(pub &'a Settings);
 
-impl Render for Tabs {
+impl Render for Tabs<'_> {
     fn render(&self) -> Markup {
         html! {
+            (Preferences {
+                settings: self.0,
+                oob: false,
+            })
             #tabs
                 ."flex flex-col w-full h-full"
                 hx-get="/tabs/chats"
@@ -224,7 +241,10 @@ impl Render for Tabs {
 
 #[cfg(test)]
 mod test {
+    use uuid::Uuid;
+
     use super::*;
+    use crate::user;
 
     #[test]
     fn should_render_head() {
@@ -364,8 +384,12 @@ mod test {
             r#"<button class="basis-64 py-4 hover:bg-gray-300 cursor-pointer" hx-get="/tabs/groups" role="tab" aria-selected="false" aria-controls="tab-content">"#,
             r#"<i class="fa-solid fa-people-group"></i>"#,
             "</button>",
+            r#"<button class="basis-64 py-4 hover:bg-gray-300 cursor-pointer" hx-get="/tabs/channels" role="tab" aria-selected="false" aria-controls="tab-content">"#,
+            r#"<i class="fa-solid fa-bullhorn"></i>"#,
+            "</button>",
             r#"<button class="basis-64 py-4 hover:bg-gray-300 cursor-pointer" hx-get="/tabs/contacts" role="tab" aria-selected="false" aria-controls="tab-content">"#,
             r#"<i class="fa-regular fa-address-book"></i>"#,
+            r#"<span id="contact-requests-badge" hx-get="/api/contacts/requests/count" hx-trigger="load, sse:newContactRequest, sse:contactRemoved, contactRequestsChanged from:body" hx-swap="innerHTML"></span>"#,
             "</button>",
             r#"<button class="basis-64 py-4 hover:bg-gray-300 cursor-pointer" hx-get="/tabs/settings" role="tab" aria-selected="false" aria-controls="tab-content">"#,
             r#"<i class="fa-solid fa-gears"></i>"#,
@@ -388,8 +412,12 @@ mod test {
             r#"<button class="basis-64 py-4 hover:bg-gray-300 cursor-pointer bg-gray-100" hx-get="/tabs/groups" role="tab" aria-selected="true" aria-controls="tab-content">"#,
             r#"<i class="fa-solid fa-people-group"></i>"#,
             "</button>",
+            r#"<button class="basis-64 py-4 hover:bg-gray-300 cursor-pointer" hx-get="/tabs/channels" role="tab" aria-selected="false" aria-controls="tab-content">"#,
+            r#"<i class="fa-solid fa-bullhorn"></i>"#,
+            "</button>",
             r#"<button class="basis-64 py-4 hover:bg-gray-300 cursor-pointer" hx-get="/tabs/contacts" role="tab" aria-selected="false" aria-controls="tab-content">"#,
             r#"<i class="fa-regular fa-address-book"></i>"#,
+            r#"<span id="contact-requests-badge" hx-get="/api/contacts/requests/count" hx-trigger="load, sse:newContactRequest, sse:contactRemoved, contactRequestsChanged from:body" hx-swap="innerHTML"></span>"#,
             "</button>",
             r#"<button class="basis-64 py-4 hover:bg-gray-300 cursor-pointer" hx-get="/tabs/settings" role="tab" aria-selected="false" aria-controls="tab-content">"#,
             r#"<i class="fa-solid fa-gears"></i>"#,
@@ -402,6 +430,34 @@ mod test {
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn should_render_tab_controls_with_active_channels() {
+        let expected = concat!(
+            r#"<div class="flex flex-row text-2xl" role="tablist">"#,
+            r#"<button class="basis-64 py-4 hover:bg-gray-300 cursor-pointer" hx-get="/tabs/chats" role="tab" aria-selected="false" aria-controls="tab-content">"#,
+            r#"<i class="fa-regular fa-message"></i>"#,
+            "</button>",
+            r#"<button class="basis-64 py-4 hover:bg-gray-300 cursor-pointer" hx-get="/tabs/groups" role="tab" aria-selected="false" aria-controls="tab-content">"#,
+            r#"<i class="fa-solid fa-people-group"></i>"#,
+            "</button>",
+            r#"<button class="basis-64 py-4 hover:bg-gray-300 cursor-pointer bg-gray-100" hx-get="/tabs/channels" role="tab" aria-selected="true" aria-controls="tab-content">"#,
+            r#"<i class="fa-solid fa-bullhorn"></i>"#,
+            "</button>",
+            r#"<button class="basis-64 py-4 hover:bg-gray-300 cursor-pointer" hx-get="/tabs/contacts" role="tab" aria-selected="false" aria-controls="tab-content">"#,
+            r#"<i class="fa-regular fa-address-book"></i>"#,
+            r#"<span id="contact-requests-badge" hx-get="/api/contacts/requests/count" hx-trigger="load, sse:newContactRequest, sse:contactRemoved, contactRequestsChanged from:body" hx-swap="innerHTML"></span>"#,
+            "</button>",
+            r#"<button class="basis-64 py-4 hover:bg-gray-300 cursor-pointer" hx-get="/tabs/settings" role="tab" aria-selected="false" aria-controls="tab-content">"#,
+            r#"<i class="fa-solid fa-gears"></i>"#,
+            "</button>",
+            "</div>"
+        );
+
+        let actual = TabControls::Channels.render().into_string();
+
+        assert_eq!(actual, expected);
+    }
+
     #[test]
     fn should_render_tab_controls_with_active_contacts() {
         let expected = concat!(
@@ -412,8 +468,12 @@ mod test {
             r#"<button class="basis-64 py-4 hover:bg-gray-300 cursor-pointer" hx-get="/tabs/groups" role="tab" aria-selected="false" aria-controls="tab-content">"#,
             r#"<i class="fa-solid fa-people-group"></i>"#,
             "</button>",
+            r#"<button class="basis-64 py-4 hover:bg-gray-300 cursor-pointer" hx-get="/tabs/channels" role="tab" aria-selected="false" aria-controls="tab-content">"#,
+            r#"<i class="fa-solid fa-bullhorn"></i>"#,
+            "</button>",
             r#"<button class="basis-64 py-4 hover:bg-gray-300 cursor-pointer bg-gray-100" hx-get="/tabs/contacts" role="tab" aria-selected="true" aria-controls="tab-content">"#,
             r#"<i class="fa-regular fa-address-book"></i>"#,
+            r#"<span id="contact-requests-badge" hx-get="/api/contacts/requests/count" hx-trigger="load, sse:newContactRequest, sse:contactRemoved, contactRequestsChanged from:body" hx-swap="innerHTML"></span>"#,
             "</button>",
             r#"<button class="basis-64 py-4 hover:bg-gray-300 cursor-pointer" hx-get="/tabs/settings" role="tab" aria-selected="false" aria-controls="tab-content">"#,
             r#"<i class="fa-solid fa-gears"></i>"#,
@@ -436,8 +496,12 @@ mod test {
             r#"<button class="basis-64 py-4 hover:bg-gray-300 cursor-pointer" hx-get="/tabs/groups" role="tab" aria-selected="false" aria-controls="tab-content">"#,
             r#"<i class="fa-solid fa-people-group"></i>"#,
             "</button>",
+            r#"<button class="basis-64 py-4 hover:bg-gray-300 cursor-pointer" hx-get="/tabs/channels" role="tab" aria-selected="false" aria-controls="tab-content">"#,
+            r#"<i class="fa-solid fa-bullhorn"></i>"#,
+            "</button>",
             r#"<button class="basis-64 py-4 hover:bg-gray-300 cursor-pointer" hx-get="/tabs/contacts" role="tab" aria-selected="false" aria-controls="tab-content">"#,
             r#"<i class="fa-regular fa-address-book"></i>"#,
+            r#"<span id="contact-requests-badge" hx-get="/api/contacts/requests/count" hx-trigger="load, sse:newContactRequest, sse:contactRemoved, contactRequestsChanged from:body" hx-swap="innerHTML"></span>"#,
             "</button>",
             r#"<button class="basis-64 py-4 hover:bg-gray-300 cursor-pointer bg-gray-100" hx-get="/tabs/settings" role="tab" aria-selected="true" aria-controls="tab-content">"#,
             r#"<i class="fa-solid fa-gears"></i>"#,
@@ -472,8 +536,12 @@ mod test {
             r#"<button class="basis-64 py-4 hover:bg-gray-300 cursor-pointer" hx-get="/tabs/groups" role="tab" aria-selected="false" aria-controls="tab-content">"#,
             r#"<i class="fa-solid fa-people-group"></i>"#,
             "</button>",
+            r#"<button class="basis-64 py-4 hover:bg-gray-300 cursor-pointer" hx-get="/tabs/channels" role="tab" aria-selected="false" aria-controls="tab-content">"#,
+            r#"<i class="fa-solid fa-bullhorn"></i>"#,
+            "</button>",
             r#"<button class="basis-64 py-4 hover:bg-gray-300 cursor-pointer" hx-get="/tabs/contacts" role="tab" aria-selected="false" aria-controls="tab-content">"#,
             r#"<i class="fa-regular fa-address-book"></i>"#,
+            r#"<span id="contact-requests-badge" hx-get="/api/contacts/requests/count" hx-trigger="load, sse:newContactRequest, sse:contactRemoved, contactRequestsChanged from:body" hx-swap="innerHTML"></span>"#,
             "</button>",
             r#"<button class="basis-64 py-4 hover:bg-gray-300 cursor-pointer" hx-get="/tabs/settings" role="tab" aria-selected="false" aria-controls="tab-content">"#,
             r#"<i class="fa-solid fa-gears"></i>"#,
@@ -507,9 +575,13 @@ mod test {
 
     #[test]
     fn should_render_tabs() {
-        let expected = r##"<div class="flex flex-col w-full h-full" id="tabs" hx-get="/tabs/chats" hx-trigger="load" hx-target="#tabs" hx-swap="innerHTML" hx-ext="sse" sse-connect="/sse"></div>"##;
+        let expected = concat!(
+            r#"<div class="hidden" id="preferences" data-theme="system" data-time-zone="UTC" data-locale="en" data-notify-messages="true" data-notify-talks="true" data-notify-contact-requests="true"></div>"#,
+            r##"<div class="flex flex-col w-full h-full" id="tabs" hx-get="/tabs/chats" hx-trigger="load" hx-target="#tabs" hx-swap="innerHTML" hx-ext="sse" sse-connect="/sse"></div>"##,
+        );
 
-        let actual = Tabs.render().into_string();
+        let settings = Settings::default_for(&user::Id::from(Uuid::new_v4()));
+        let actual = Tabs(&settings).render().into_string();
 
         assert_eq!(actual, expected);
     }
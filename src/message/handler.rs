@@ -5,6 +5,7 @@ impl From<super::Error> for StatusCode {
         match e {
             super::Error::NotFound(_) => Self::NOT_FOUND,
             super::Error::EmptyContent => Self::BAD_REQUEST,
+            super::Error::_Talk(e) => e.into(),
             super::Error::_User(_) | super::Error::_R2d2(_) | super::Error::_Diesel(_) => {
                 Self::INTERNAL_SERVER_ERROR
             }
@@ -115,6 +116,19 @@ pub(super) mod api {
             Err(message::Error::NotFound(id))?
         }
     }
+
+    /// Forwards a message into the "Saved messages" self-talk.
+    pub async fn save(
+        auth_user: Extension<auth::User>,
+        Path(id): Path<message::Id>,
+        talk_service: State<talk::Service>,
+        message_service: State<message::Service>,
+    ) -> crate::Result<StatusCode> {
+        let saved = talk_service.find_or_create_saved(auth_user.id())?;
+        message_service.forward(&auth_user, &id, saved.id()).await?;
+
+        Ok(StatusCode::NO_CONTENT)
+    }
 }
 
 pub(super) mod templates {
@@ -136,8 +150,11 @@ pub(super) mod templates {
         talk_id: talk::Id,
     }
 
-    pub async fn message_input_blank(params: Query<BlankParams>) -> Markup {
-        markup::InputBlank(&params.talk_id).render()
+    pub async fn message_input_blank(
+        auth_user: Extension<auth::User>,
+        params: Query<BlankParams>,
+    ) -> Markup {
+        markup::InputBlank(&params.talk_id, auth_user.settings()).render()
     }
 
     #[derive(Deserialize)]
@@ -152,6 +169,6 @@ pub(super) mod templates {
     ) -> crate::Result<Markup> {
         let msg = message_service.find_by_id(&auth_user, &params.message_id)?;
 
-        Ok(markup::InputEdit::new(msg.id(), msg.text()).render())
+        Ok(markup::InputEdit::new(msg.id(), msg.text(), auth_user.settings()).render())
     }
 }
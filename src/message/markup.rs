@@ -1,8 +1,9 @@
 use std::fmt::Display;
 
+use chrono::{DateTime, Utc};
 use maud::{Markup, Render, html};
 
-use crate::{markup::IdExt, message, talk, user};
+use crate::{markup::IdExt, message, settings::model::Settings, talk, user};
 
 use super::model::MessageDto;
 
@@ -18,7 +19,7 @@ impl Display for super::Id {
     }
 }
 
-pub struct InputBlank<'a>(pub &'a talk::Id);
+pub struct InputBlank<'a>(pub &'a talk::Id, pub &'a Settings);
 
 impl Render for InputBlank<'_> {
     fn render(&self) -> Markup {
@@ -35,7 +36,10 @@ impl Render for InputBlank<'_> {
                 _=(send_message_handler)
             {
                 input type="hidden" name="talk_id" value=(self.0) {}
-                (InputText(None))
+                (InputText {
+                    value: None,
+                    enter_to_send: self.1.enter_to_send(),
+                })
                 (SendButton)
             }
         }
@@ -45,11 +49,16 @@ impl Render for InputBlank<'_> {
 pub struct InputEdit<'a> {
     id: &'a message::Id,
     old_text: &'a str,
+    settings: &'a Settings,
 }
 
 impl<'a> InputEdit<'a> {
-    pub const fn new(id: &'a message::Id, old_text: &'a str) -> Self {
-        Self { id, old_text }
+    pub const fn new(id: &'a message::Id, old_text: &'a str, settings: &'a Settings) -> Self {
+        Self {
+            id,
+            old_text,
+            settings,
+        }
     }
 }
 
@@ -62,7 +71,10 @@ impl Render for InputEdit<'_> {
                 hx-swap="outerHTML"
             {
                 input type="hidden" name="message_id" value=(self.id) {}
-                (InputText(Some(self.old_text)))
+                (InputText {
+                    value: Some(self.old_text),
+                    enter_to_send: self.settings.enter_to_send(),
+                })
                 (SendButton)
             }
 
@@ -70,19 +82,33 @@ impl Render for InputEdit<'_> {
     }
 }
 
-struct InputText<'a>(Option<&'a str>);
+struct InputText<'a> {
+    value: Option<&'a str>,
+    enter_to_send: bool,
+}
 
 impl Render for InputText<'_> {
     fn render(&self) -> Markup {
+        let mut handler =
+            String::from("on keyup if the event's key is 'Escape' set value of me to ''");
+        if !self.enter_to_send {
+            // a lone Enter would submit the form, only Ctrl+Enter does then
+            handler.push_str(
+                r"
+                on keydown[key is 'Enter'] halt the event
+                    if event.ctrlKey or event.metaKey call (closest <form/>).requestSubmit() end",
+            );
+        }
+
         html! {
             input ."border border-gray-300 rounded-l-md p-2 flex-1 focus:outline-none"
                 type="text"
                 name="text"
-                value=[self.0]
+                value=[self.value]
                 placeholder="Type your message..."
                 autocomplete="off"
                 hx-disabled-elt="this"
-                _="on keyup if the event's key is 'Escape' set value of me to ''" {}
+                _=(handler) {}
         }
     }
 }
@@ -150,7 +176,7 @@ impl<'a> MessageItem<'a> {
     }
 
     fn controls_handler(&self) -> Option<&str> {
-        if self.belongs_to_user() {
+        if self.auth_id.is_some() && !self.msg.is_system() {
             Some(
                 r"
                 on mouseover remove .hidden from the first <div.message-controls/> in me
@@ -172,7 +198,23 @@ impl Render for MessageItem<'_> {
     fn render(&self) -> Markup {
         let belongs_to_user = self.belongs_to_user();
 
-        let msg_timestamp = self.msg.created_at().format("%H:%M");
+        let msg_timestamp = Timestamp(self.msg.created_at());
+
+        if self.msg.is_system() {
+            return html! {
+                div #(self.msg.id().attr())
+                    .(MESSAGE_CLASS)
+                    ."justify-center"
+                    hx-trigger=[self.hx_trigger()]
+                    hx-swap=[self.hx_swap()]
+                    hx-get=[self.next_page()]
+                {
+                    span ."system-message text-xs text-gray-500 italic bg-gray-100 rounded-full px-3 py-1 mt-2" {
+                        (self.msg.text()) " · " (msg_timestamp)
+                    }
+                }
+            };
+        }
 
         html! {
             div #(self.msg.id().attr())
@@ -185,6 +227,8 @@ impl Render for MessageItem<'_> {
             {
                 @if belongs_to_user {
                     div ."message-controls hidden pb-2" {
+                        (Icon::Pin(self.msg))
+                        (Icon::Save(self.msg.id()))
                         (Icon::Delete(&self.msg.id()))
                         (Icon::Edit(&self.msg))
                     }
@@ -204,11 +248,30 @@ impl Render for MessageItem<'_> {
                     span ."message-timestamp text-xs opacity-65" { (msg_timestamp) }
 
                 }
+
+                @if !belongs_to_user && self.auth_id.is_some() {
+                    div ."message-controls hidden pb-2" {
+                        (Icon::Save(self.msg.id()))
+                        (Icon::Pin(self.msg))
+                    }
+                }
             }
         }
     }
 }
 
+/// Time of a message in UTC, localized by the browser to the time zone
+/// and locale the user picked.
+struct Timestamp<'a>(&'a DateTime<Utc>);
+
+impl Render for Timestamp<'_> {
+    fn render(&self) -> Markup {
+        html! {
+            time datetime=(self.0.to_rfc3339()) { (self.0.format("%H:%M")) }
+        }
+    }
+}
+
 pub struct MessageList<'a> {
     messages: &'a [MessageDto],
     auth_id: &'a user::Id,
@@ -294,6 +357,8 @@ impl crate::markup::IdExt for message::Id {
 pub enum Icon<'a> {
     Edit(&'a MessageDto),
     Delete(&'a message::Id),
+    Save(&'a message::Id),
+    Pin(&'a MessageDto),
     Sent,
     Seen,
 }
@@ -314,6 +379,20 @@ impl Render for Icon<'_> {
                         hx-target=(id.target())
                         hx-swap="outerHTML swap:200ms" {}
                 },
+                Self::Save(id) => {
+                    i ."fa-bookmark fa-regular ml-2 mr-2 text-blue-600 cursor-pointer"
+                        title="Save to Saved messages"
+                        hx-post={"/api/messages/" (id) "/save"}
+                        hx-swap="none" {}
+                },
+                Self::Pin(msg) => {
+                    i ."fa-thumbtack fa-solid mr-2 text-gray-600 cursor-pointer"
+                        title="Pin"
+                        hx-put={"/api/talks/" (msg.talk_id()) "/pinned-message"}
+                        hx-vals=(format!(r#"{{"message_id": "{}"}}"#, msg.id()))
+                        hx-target=(talk::markup::PINNED_MESSAGE_TARGET)
+                        hx-swap="outerHTML" {}
+                },
                 Self::Sent => i ."fa-solid fa-check absolute bottom-1 right-1 text-white opacity-65" {},
                 Self::Seen => i ."fa-solid fa-check absolute bottom-1 right-2.5 text-white opacity-65" {},
             }
@@ -11,7 +11,7 @@ use serde::{Deserialize, Serialize};
 use service::MessageService;
 use uuid::Uuid;
 
-use crate::{state::AppServices, user};
+use crate::{state::AppServices, talk, user};
 
 mod handler;
 pub mod markup;
@@ -49,6 +49,7 @@ pub fn api<S>(s: AppServices) -> Router<S> {
         .route("/messages", get(handler::api::find_all))
         .route("/messages", put(handler::api::update))
         .route("/messages/{id}", delete(handler::api::delete))
+        .route("/messages/{id}/save", post(handler::api::save))
         .with_state(s)
 }
 
@@ -76,6 +77,8 @@ pub enum Error {
     #[error(transparent)]
     _User(#[from] user::Error),
     #[error(transparent)]
+    _Talk(#[from] talk::Error),
+    #[error(transparent)]
     _R2d2(#[from] r2d2::Error),
     #[error(transparent)]
     _Diesel(#[from] diesel::result::Error),
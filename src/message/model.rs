@@ -20,6 +20,7 @@ pub struct Message {
     content: String,
     created_at: DateTime<Utc>,
     seen: bool,
+    system: bool,
 }
 
 impl Message {
@@ -30,6 +31,7 @@ impl Message {
         content: String,
         created_at: DateTime<Utc>,
         seen: bool,
+        system: bool,
     ) -> Self {
         Self {
             id,
@@ -38,6 +40,7 @@ impl Message {
             content,
             created_at,
             seen,
+            system,
         }
     }
 }
@@ -48,6 +51,8 @@ pub struct NewMessage<'a> {
     talk_id: &'a talk::Id,
     owner: &'a user::Id,
     content: &'a str,
+    seen: bool,
+    system: bool,
 }
 
 impl<'a> NewMessage<'a> {
@@ -56,6 +61,20 @@ impl<'a> NewMessage<'a> {
             talk_id,
             owner,
             content,
+            seen: false,
+            system: false,
+        }
+    }
+
+    /// A talk lifecycle event, the owner is the user who triggered it.
+    /// System messages are born seen so they never count as unread.
+    pub const fn system(talk_id: &'a talk::Id, owner: &'a user::Id, content: &'a str) -> Self {
+        Self {
+            talk_id,
+            owner,
+            content,
+            seen: true,
+            system: true,
         }
     }
 }
@@ -68,6 +87,8 @@ pub struct MessageDto {
     content: String,
     created_at: DateTime<Utc>,
     seen: bool,
+    #[serde(default)]
+    system: bool,
 }
 
 impl MessageDto {
@@ -79,6 +100,7 @@ impl MessageDto {
             content: text.into(),
             created_at: Utc::now().to_utc(),
             seen: false,
+            system: false,
         }
     }
 
@@ -106,6 +128,10 @@ impl MessageDto {
         self.seen
     }
 
+    pub const fn is_system(&self) -> bool {
+        self.system
+    }
+
     pub fn with_random_id(&self) -> Self {
         Self {
             id: Id::random(),
@@ -113,6 +139,13 @@ impl MessageDto {
         }
     }
 
+    pub fn with_seen(&self, seen: bool) -> Self {
+        Self {
+            seen,
+            ..self.clone()
+        }
+    }
+
     pub fn with_text(&self, text: &str) -> Self {
         Self {
             content: text.to_string(),
@@ -130,6 +163,7 @@ impl From<Message> for MessageDto {
             content: m.content,
             created_at: m.created_at,
             seen: m.seen,
+            system: m.system,
         }
     }
 }
@@ -8,7 +8,7 @@ use crate::{message, user};
 
 use super::model::{Message, NewMessage};
 use crate::{
-    schema::messages::dsl::{content, created_at, id, messages, owner, seen, talk_id},
+    schema::messages::dsl::{content, created_at, id, messages, owner, seen, system, talk_id},
     talk,
 };
 
@@ -19,8 +19,13 @@ pub trait MessageRepository {
 
     fn find_by_id(&self, owner: &user::Id, id: &message::Id) -> super::Result<Message>;
 
+    fn find_one(&self, id: &message::Id) -> super::Result<Message>;
+
     fn find_by_talk_id(&self, talk_id: &talk::Id) -> super::Result<Vec<Message>>;
 
+    /// Messages written by the user in any talk, oldest first.
+    fn find_by_owner(&self, owner: &user::Id) -> super::Result<Vec<Message>>;
+
     fn find_by_talk_id_limited(
         &self,
         talk_id: &talk::Id,
@@ -93,6 +98,16 @@ impl MessageRepository for PgMessageRepository {
             .map_err(super::Error::from)
     }
 
+    fn find_one(&self, m_id: &message::Id) -> super::Result<Message> {
+        let mut conn = self.pool.get()?;
+
+        messages
+            .find(m_id)
+            .select(Message::as_select())
+            .first(&mut conn)
+            .map_err(super::Error::from)
+    }
+
     fn find_by_talk_id(&self, t_id: &talk::Id) -> super::Result<Vec<Message>> {
         let mut conn = self.pool.get()?;
 
@@ -103,6 +118,17 @@ impl MessageRepository for PgMessageRepository {
             .map_err(super::Error::from)
     }
 
+    fn find_by_owner(&self, o: &user::Id) -> super::Result<Vec<Message>> {
+        let mut conn = self.pool.get()?;
+
+        messages
+            .filter(owner.eq(o))
+            .order(created_at.asc())
+            .select(Message::as_select())
+            .get_results(&mut conn)
+            .map_err(super::Error::from)
+    }
+
     fn find_by_talk_id_limited(&self, t_id: &talk::Id, limit: i64) -> super::Result<Vec<Message>> {
         let mut conn = self.pool.get()?;
 
@@ -155,11 +181,13 @@ impl MessageRepository for PgMessageRepository {
     ) -> super::Result<Option<Message>> {
         let mut conn = self.pool.get()?;
 
-        let updated_msg = update(messages.filter(id.eq(m_id).and(owner.eq(o))))
-            .set(content.eq(new_content))
-            .returning(Message::as_returning())
-            .get_result(&mut conn)
-            .optional()?;
+        // system messages are immutable
+        let updated_msg =
+            update(messages.filter(id.eq(m_id).and(owner.eq(o)).and(system.eq(false))))
+                .set(content.eq(new_content))
+                .returning(Message::as_returning())
+                .get_result(&mut conn)
+                .optional()?;
 
         Ok(updated_msg)
     }
@@ -167,10 +195,11 @@ impl MessageRepository for PgMessageRepository {
     fn delete(&self, o: &user::Id, m_id: &message::Id) -> super::Result<Option<Message>> {
         let mut conn = self.pool.get()?;
 
-        let deleted_msg = delete(messages.filter(id.eq(m_id).and(owner.eq(o))))
-            .returning(Message::as_returning())
-            .get_result(&mut conn)
-            .optional()?;
+        let deleted_msg =
+            delete(messages.filter(id.eq(m_id).and(owner.eq(o)).and(system.eq(false))))
+                .returning(Message::as_returning())
+                .get_result(&mut conn)
+                .optional()?;
 
         Ok(deleted_msg)
     }
@@ -26,6 +26,16 @@ pub trait MessageService {
 
     fn find_by_id(&self, auth_user: &auth::User, id: &message::Id) -> super::Result<MessageDto>;
 
+    fn find_by_owner(&self, auth_id: &user::Id) -> super::Result<Vec<MessageDto>>;
+
+    /// Copies a message into another talk on behalf of the user.
+    async fn forward(
+        &self,
+        auth_user: &auth::User,
+        id: &message::Id,
+        talk_id: &talk::Id,
+    ) -> super::Result<Vec<MessageDto>>;
+
     async fn update(
         &self,
         auth_user: &auth::User,
@@ -50,6 +60,7 @@ pub trait MessageService {
 pub struct MessageServiceImpl {
     repo: Repository,
     user_service: user::Service,
+    talk_service: talk::Service,
     event_service: event::Service,
     splitter: Arc<TextSplitter<Characters>>,
 }
@@ -58,11 +69,13 @@ impl MessageServiceImpl {
     pub fn new(
         repo: Repository,
         user_service: user::Service,
+        talk_service: talk::Service,
         event_service: event::Service,
     ) -> Self {
         Self {
             repo,
             user_service,
+            talk_service,
             event_service,
             splitter: Arc::new(TextSplitter::new(MAX_MESSAGE_LENGTH)),
         }
@@ -83,6 +96,7 @@ impl MessageService for MessageServiceImpl {
         }
 
         let auth_id = auth_user.id();
+        let kind = self.talk_service.authorize_post(auth_id, talk_id).await?;
 
         let msgs = match content.len() {
             text_length if text_length <= MAX_MESSAGE_LENGTH => {
@@ -101,7 +115,7 @@ impl MessageService for MessageServiceImpl {
             .map(MessageDto::from)
             .collect::<Vec<MessageDto>>();
 
-        self.notify_new(talk_id, auth_id, &msgs).await;
+        self.notify_new(talk_id, &kind, auth_id, &msgs).await;
 
         Ok(msgs)
     }
@@ -112,6 +126,29 @@ impl MessageService for MessageServiceImpl {
             .map(MessageDto::from)
     }
 
+    fn find_by_owner(&self, auth_id: &user::Id) -> super::Result<Vec<MessageDto>> {
+        let msgs = self.repo.find_by_owner(auth_id)?;
+
+        Ok(msgs.into_iter().map(MessageDto::from).collect())
+    }
+
+    async fn forward(
+        &self,
+        auth_user: &auth::User,
+        id: &message::Id,
+        talk_id: &talk::Id,
+    ) -> super::Result<Vec<MessageDto>> {
+        let msg = MessageDto::from(self.repo.find_one(id)?);
+
+        self.user_service
+            .check_member(msg.talk_id(), auth_user)
+            .await
+            .map_err(|_| super::Error::NotFound(id.clone()))?;
+        self.user_service.check_member(talk_id, auth_user).await?;
+
+        self.create(talk_id, auth_user, msg.text()).await
+    }
+
     async fn update(
         &self,
         auth_user: &auth::User,
@@ -120,7 +157,8 @@ impl MessageService for MessageServiceImpl {
     ) -> super::Result<Option<MessageDto>> {
         if let Some(updated) = self.repo.update(auth_user.id(), id, text)? {
             let msg = MessageDto::from(updated);
-            self.notify_updated(&msg).await;
+            let kind = self.talk_service.find_kind(msg.talk_id())?;
+            self.notify_updated(&msg, &kind).await;
             return Ok(Some(msg));
         }
 
@@ -129,7 +167,9 @@ impl MessageService for MessageServiceImpl {
 
     async fn delete(&self, auth_user: &auth::User, id: &message::Id) -> super::Result<bool> {
         if let Some(deleted) = self.repo.delete(auth_user.id(), id)? {
-            self.notify_deleted(&MessageDto::from(deleted)).await;
+            let msg = MessageDto::from(deleted);
+            let kind = self.talk_service.find_kind(msg.talk_id())?;
+            self.notify_deleted(&msg, &kind).await;
             return Ok(true);
         }
 
@@ -162,6 +202,24 @@ impl MessageService for MessageServiceImpl {
 
         self.mark_as_seen(auth_user.id(), &msgs).await?;
 
+        if !self
+            .user_service
+            .receipts_withheld(talk_id, auth_user.id())?
+        {
+            return Ok(msgs);
+        }
+
+        let msgs = msgs
+            .iter()
+            .map(|m| {
+                if m.owner().eq(auth_user.id()) {
+                    m.with_seen(false)
+                } else {
+                    m.clone()
+                }
+            })
+            .collect();
+
         Ok(msgs)
     }
 
@@ -173,7 +231,7 @@ impl MessageService for MessageServiceImpl {
 
         let anothers_messages = msgs
             .iter()
-            .filter(|msg| msg.owner().ne(auth_id))
+            .filter(|msg| msg.owner().ne(auth_id) && !msg.is_system())
             .collect::<Vec<_>>();
 
         if anothers_messages.is_empty() {
@@ -198,6 +256,16 @@ impl MessageService for MessageServiceImpl {
 
         self.repo.mark_as_seen(&unseen_ids)?;
 
+        // seen still counts for the reader's unread messages, owners are just not told
+        if !self
+            .user_service
+            .find_privacy(auth_id)
+            .await?
+            .share_read_receipts()
+        {
+            return Ok(());
+        }
+
         let msg_evts = unseen_msgs
             .iter()
             .map(|m| event::Message::Seen((*m).clone()))
@@ -218,8 +286,14 @@ impl MessageService for MessageServiceImpl {
 }
 
 impl MessageServiceImpl {
-    async fn notify_new(&self, talk_id: &talk::Id, owner: &user::Id, msgs: &[MessageDto]) {
-        match self.find_recipients(talk_id, owner).await {
+    async fn notify_new(
+        &self,
+        talk_id: &talk::Id,
+        kind: &talk::Kind,
+        owner: &user::Id,
+        msgs: &[MessageDto],
+    ) {
+        match self.find_recipients(talk_id, kind, owner).await {
             Ok(recipients) => {
                 let msg_evts = msgs
                     .iter()
@@ -227,10 +301,7 @@ impl MessageServiceImpl {
                     .map(bytes::Bytes::from)
                     .collect::<Vec<_>>();
 
-                let msg_subjects = recipients
-                    .iter()
-                    .map(|r| event::Subject::Messages(r, talk_id))
-                    .collect::<Vec<_>>();
+                let msg_subjects = event::Subject::messages(talk_id, kind, &recipients);
 
                 self.event_service
                     .broadcast_many(&msg_subjects, &msg_evts)
@@ -241,13 +312,12 @@ impl MessageServiceImpl {
                     .map(|lm| event::Notification::NewMessage {
                         talk_id: talk_id.clone(),
                         last_message: lm.clone(),
+                        muted: false,
                     })
                     .map(bytes::Bytes::from)
                 {
-                    let noti_subjects = recipients
-                        .iter()
-                        .map(event::Subject::Notifications)
-                        .collect::<Vec<_>>();
+                    let noti_subjects =
+                        event::Subject::message_notifications(talk_id, kind, &recipients);
 
                     self.event_service.broadcast(&noti_subjects, noti_evt).await;
                 }
@@ -256,16 +326,13 @@ impl MessageServiceImpl {
         }
     }
 
-    async fn notify_updated(&self, msg: &MessageDto) {
+    async fn notify_updated(&self, msg: &MessageDto, kind: &talk::Kind) {
         let talk_id = msg.talk_id();
         let owner = msg.owner();
 
-        match self.find_recipients(talk_id, owner).await {
+        match self.find_recipients(talk_id, kind, owner).await {
             Ok(recipients) => {
-                let subjects = recipients
-                    .iter()
-                    .map(|r| event::Subject::Messages(r, talk_id))
-                    .collect::<Vec<_>>();
+                let subjects = event::Subject::messages(talk_id, kind, &recipients);
 
                 debug!("{recipients:?}");
                 self.event_service
@@ -279,16 +346,13 @@ impl MessageServiceImpl {
         }
     }
 
-    async fn notify_deleted(&self, msg: &MessageDto) {
+    async fn notify_deleted(&self, msg: &MessageDto, kind: &talk::Kind) {
         let talk_id = msg.talk_id();
         let owner = msg.owner();
 
-        match self.find_recipients(talk_id, owner).await {
+        match self.find_recipients(talk_id, kind, owner).await {
             Ok(recipients) => {
-                let subjects = recipients
-                    .iter()
-                    .map(|r| event::Subject::Messages(r, talk_id))
-                    .collect::<Vec<_>>();
+                let subjects = event::Subject::messages(talk_id, kind, &recipients);
 
                 self.event_service
                     .broadcast(
@@ -304,11 +368,17 @@ impl MessageServiceImpl {
         }
     }
 
+    /// Channel subscribers share their subjects, so none are looked up for channels.
     async fn find_recipients(
         &self,
         talk_id: &talk::Id,
+        kind: &talk::Kind,
         exclude: &user::Id,
     ) -> super::Result<HashSet<user::Id>> {
+        if kind.eq(&talk::Kind::Channel) {
+            return Ok(HashSet::new());
+        }
+
         let recipients = {
             let mut r = self.user_service.find_members(talk_id).await?;
             r.remove(exclude);
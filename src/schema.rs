@@ -1,14 +1,31 @@
 // @generated automatically by Diesel CLI.
 
 pub mod sql_types {
+    #[derive(diesel::query_builder::QueryId, Clone, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "audience"))]
+    pub struct Audience;
+
+    #[derive(diesel::query_builder::QueryId, Clone, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "group_role"))]
+    pub struct GroupRole;
+
     #[derive(diesel::query_builder::QueryId, Clone, diesel::sql_types::SqlType)]
     #[diesel(postgres_type(name = "talk_kind"))]
     pub struct TalkKind;
+
+    #[derive(diesel::query_builder::QueryId, Clone, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "theme"))]
+    pub struct Theme;
+
+    #[derive(diesel::query_builder::QueryId, Clone, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "user_presence"))]
+    pub struct UserPresence;
 }
 
 diesel::table! {
     chats (id) {
         id -> Uuid,
+        saved_by -> Nullable<Uuid>,
     }
 }
 
@@ -19,6 +36,22 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    contact_aliases (owner, user_id) {
+        owner -> Uuid,
+        user_id -> Uuid,
+        alias -> Text,
+    }
+}
+
+diesel::table! {
+    contact_labels (owner, user_id, label) {
+        owner -> Uuid,
+        user_id -> Uuid,
+        label -> Text,
+    }
+}
+
 diesel::table! {
     contacts (id) {
         id -> Uuid,
@@ -26,6 +59,20 @@ diesel::table! {
         user_id_2 -> Uuid,
         status -> Text,
         initiator -> Nullable<Uuid>,
+        note -> Nullable<Text>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    group_invites (token) {
+        token -> Text,
+        group_id -> Uuid,
+        created_by -> Uuid,
+        created_at -> Timestamptz,
+        expires_at -> Nullable<Timestamptz>,
+        max_uses -> Nullable<Int4>,
+        uses -> Int4,
     }
 }
 
@@ -34,13 +81,18 @@ diesel::table! {
         id -> Uuid,
         owner -> Uuid,
         name -> Text,
+        avatar_version -> Int8,
     }
 }
 
 diesel::table! {
+    use diesel::sql_types::Uuid;
+    use super::sql_types::GroupRole;
+
     groups_users (group_id, user_id) {
         group_id -> Uuid,
         user_id -> Uuid,
+        role -> GroupRole,
     }
 }
 
@@ -52,6 +104,47 @@ diesel::table! {
         content -> Text,
         created_at -> Timestamptz,
         seen -> Bool,
+        system -> Bool,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::{Bool, Uuid};
+    use super::sql_types::Audience;
+
+    privacy_settings (user_id) {
+        user_id -> Uuid,
+        discoverable_by -> Audience,
+        contact_requests_from -> Audience,
+        group_adds_from -> Audience,
+        share_presence -> Bool,
+        share_read_receipts -> Bool,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::{Bool, Text, Uuid};
+    use super::sql_types::Theme;
+
+    settings (user_id) {
+        user_id -> Uuid,
+        theme -> Theme,
+        time_zone -> Text,
+        locale -> Text,
+        enter_to_send -> Bool,
+        notify_messages -> Bool,
+        notify_talks -> Bool,
+        notify_contact_requests -> Bool,
+    }
+}
+
+diesel::table! {
+    talk_preferences (talk_id, user_id) {
+        talk_id -> Uuid,
+        user_id -> Uuid,
+        archived -> Bool,
+        pinned -> Bool,
+        muted_until -> Nullable<Timestamptz>,
     }
 }
 
@@ -63,10 +156,22 @@ diesel::table! {
         id -> Uuid,
         kind -> TalkKind,
         last_message_id -> Nullable<Uuid>,
+        pinned_message_id -> Nullable<Uuid>,
     }
 }
 
 diesel::table! {
+    user_blocks (blocker, blocked) {
+        blocker -> Uuid,
+        blocked -> Uuid,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::{Bool, Nullable, Text, Timestamptz, Uuid};
+    use super::sql_types::UserPresence;
+
     users (id) {
         id -> Uuid,
         sub -> Text,
@@ -74,26 +179,44 @@ diesel::table! {
         name -> Text,
         picture -> Text,
         email -> Text,
+        bio -> Nullable<Text>,
+        name_overridden -> Bool,
+        nickname_overridden -> Bool,
+        presence -> UserPresence,
+        last_seen -> Nullable<Timestamptz>,
     }
 }
 
 diesel::joinable!(chats -> talks (id));
 diesel::joinable!(chats_users -> chats (chat_id));
 diesel::joinable!(chats_users -> users (user_id));
+diesel::joinable!(group_invites -> groups (group_id));
+diesel::joinable!(group_invites -> users (created_by));
 diesel::joinable!(groups -> talks (id));
 diesel::joinable!(groups -> users (owner));
 diesel::joinable!(groups_users -> groups (group_id));
 diesel::joinable!(groups_users -> users (user_id));
 diesel::joinable!(messages -> talks (talk_id));
 diesel::joinable!(messages -> users (owner));
+diesel::joinable!(privacy_settings -> users (user_id));
+diesel::joinable!(settings -> users (user_id));
+diesel::joinable!(talk_preferences -> talks (talk_id));
+diesel::joinable!(talk_preferences -> users (user_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
     chats,
     chats_users,
+    contact_aliases,
+    contact_labels,
     contacts,
+    group_invites,
     groups,
     groups_users,
     messages,
+    privacy_settings,
+    settings,
+    talk_preferences,
     talks,
+    user_blocks,
     users,
 );
@@ -0,0 +1,85 @@
+use axum::http::StatusCode;
+
+impl From<super::Error> for StatusCode {
+    fn from(e: super::Error) -> Self {
+        match e {
+            super::Error::UnsupportedTheme(_)
+            | super::Error::InvalidTimeZone(_)
+            | super::Error::UnsupportedLocale(_) => Self::BAD_REQUEST,
+            super::Error::_R2d2(_) | super::Error::_Diesel(_) => Self::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+pub(super) mod api {
+    use axum::{Extension, Form, extract::State};
+    use maud::{Markup, html};
+    use serde::Deserialize;
+
+    use crate::{
+        auth,
+        settings::{
+            self, Locale, Theme, TimeZone, markup,
+            model::{Notifications, Settings},
+        },
+    };
+
+    #[derive(Deserialize)]
+    pub struct SettingsParams {
+        theme: Theme,
+        time_zone: String,
+        locale: String,
+        // unchecked boxes are not submitted
+        #[serde(default)]
+        enter_to_send: bool,
+        #[serde(default)]
+        notify_messages: bool,
+        #[serde(default)]
+        notify_talks: bool,
+        #[serde(default)]
+        notify_contact_requests: bool,
+    }
+
+    pub async fn update(
+        auth_user: Extension<auth::User>,
+        settings_service: State<settings::Service>,
+        Form(params): Form<SettingsParams>,
+    ) -> crate::Result<Markup> {
+        let s = Settings::new(
+            auth_user.id().clone(),
+            params.theme,
+            TimeZone::try_from(params.time_zone)?,
+            Locale::try_from(params.locale)?,
+            params.enter_to_send,
+            Notifications {
+                messages: params.notify_messages,
+                talks: params.notify_talks,
+                contact_requests: params.notify_contact_requests,
+            },
+        );
+        settings_service.update(&s).await?;
+
+        Ok(html! {
+            (markup::SettingsForm {
+                settings: &s,
+                saved: true,
+            })
+            (markup::Preferences { settings: &s, oob: true })
+        })
+    }
+}
+
+pub(super) mod templates {
+    use axum::Extension;
+    use maud::{Markup, Render};
+
+    use crate::{auth, settings::markup};
+
+    pub async fn preferences(auth_user: Extension<auth::User>) -> Markup {
+        markup::SettingsForm {
+            settings: auth_user.settings(),
+            saved: false,
+        }
+        .render()
+    }
+}
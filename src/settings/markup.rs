@@ -1,9 +1,14 @@
 use maud::{Markup, Render, html};
+use messenger_service::AsStr;
+
+use crate::user::{self, markup::PresenceSelect};
+
+use super::{LOCALES, Locale, Theme, model::Settings};
 
 const SETTING_ITEM_CLASS: &str =
     "px-3 py-2 rounded-md bg-gray-100 hover:bg-gray-200 cursor-pointer flex items-center";
 
-pub struct List;
+pub struct List(pub user::Presence);
 
 impl Render for List {
     fn render(&self) -> Markup {
@@ -13,6 +18,32 @@ impl Render for List {
             }
 
             ul .space-y-2 {
+                li .(SETTING_ITEM_CLASS)
+                    hx-get="/templates/users/profile"
+                    hx-target="#profile-settings"
+                {
+                    i .mr-2 ."fa-solid fa-user-pen" {}
+                    "Profile"
+                }
+                li .(SETTING_ITEM_CLASS)
+                    hx-get="/templates/users/privacy"
+                    hx-target="#privacy-settings"
+                {
+                    i .mr-2 ."fa-solid fa-user-shield" {}
+                    "Privacy"
+                }
+                li .(SETTING_ITEM_CLASS)
+                    hx-get="/templates/settings/preferences"
+                    hx-target="#preferences-settings"
+                {
+                    i .mr-2 ."fa-solid fa-sliders" {}
+                    "Preferences"
+                }
+                li .(SETTING_ITEM_CLASS) {
+                    i .mr-2 ."fa-solid fa-circle-half-stroke" {}
+                    span .flex-grow { "Status" }
+                    (PresenceSelect(self.0))
+                }
                 li .(SETTING_ITEM_CLASS) {
                     a .flex-grow href="/logout" {
                         i .mr-2 ."fa-solid fa-arrow-right-from-bracket" {}
@@ -25,16 +56,177 @@ impl Render for List {
                     i .mr-2 ."fa-regular fa-bell-slash" {}
                     "Enable notifications"
                 }
+                li .(SETTING_ITEM_CLASS)
+                    hx-get="/templates/contacts/blocked"
+                    hx-target="#blocked-users"
+                {
+                    i .mr-2 ."fa-solid fa-user-slash" {}
+                    "Blocked users"
+                }
+                li .(SETTING_ITEM_CLASS) {
+                    a .flex-grow href="/api/users/export" download {
+                        i .mr-2 ."fa-solid fa-file-zipper" {}
+                        "Download my data"
+                    }
+                }
+                li .(SETTING_ITEM_CLASS) ."text-red-600"
+                    hx-delete="/api/users/account"
+                    hx-confirm="Delete your account? Your messages stay in talks as from a deleted user, everything else is gone for good."
+                {
+                    i .mr-2 ."fa-solid fa-user-xmark" {}
+                    "Delete account"
+                }
+            }
+
+            div #profile-settings {}
+            div #privacy-settings {}
+            div #preferences-settings {}
+            div #blocked-users {}
+        }
+    }
+}
+
+pub struct SettingsForm<'a> {
+    pub settings: &'a Settings,
+    pub saved: bool,
+}
+
+impl Render for SettingsForm<'_> {
+    fn render(&self) -> Markup {
+        let s = self.settings;
+        let n = s.notifications();
+        let notifications = [
+            ("notify_messages", "New messages", n.messages),
+            ("notify_talks", "Invitations to talks", n.talks),
+            (
+                "notify_contact_requests",
+                "Contact requests",
+                n.contact_requests,
+            ),
+        ];
+
+        html! {
+            form #settings-form ."flex flex-col space-y-2 p-3"
+                hx-put="/api/settings"
+                hx-target="this"
+                hx-swap="outerHTML"
+            {
+                label ."text-sm text-gray-500" for="theme" { "Theme" }
+                (ThemeSelect(s.theme()))
+
+                label ."text-sm text-gray-500" for="time-zone" { "Time zone" }
+                div ."flex space-x-2" {
+                    input #time-zone ."flex-grow px-2 py-1 rounded-md border border-gray-300"
+                        type="text"
+                        name="time_zone"
+                        value=(s.time_zone().as_str())
+                        required {}
+                    button ."px-2 py-1 rounded-md bg-gray-100 hover:bg-gray-200"
+                        type="button"
+                        _="on click set the value of #time-zone to Intl.DateTimeFormat().resolvedOptions().timeZone"
+                    { "Detect" }
+                }
+
+                label ."text-sm text-gray-500" for="locale" { "Language and formats" }
+                (LocaleSelect(s.locale()))
+
+                label ."flex items-center space-x-2" {
+                    input type="checkbox"
+                        name="enter_to_send"
+                        value="true"
+                        checked[s.enter_to_send()] {}
+                    span { "Send messages with Enter instead of Ctrl+Enter" }
+                }
+
+                span ."text-sm text-gray-500" { "Notify me about" }
+                @for (name, label, checked) in notifications {
+                    label ."flex items-center space-x-2" {
+                        input type="checkbox" name=(name) value="true" checked[checked] {}
+                        span { (label) }
+                    }
+                }
+
+                div ."flex items-center space-x-2" {
+                    input ."px-4 py-2 text-white bg-green-700 hover:bg-green-800 rounded-md cursor-pointer"
+                        type="submit"
+                        value="Save" {}
+                    @if self.saved {
+                        span ."text-green-700" { "Saved" }
+                    }
+                }
+            }
+        }
+    }
+}
+
+struct ThemeSelect(Theme);
+
+impl Render for ThemeSelect {
+    fn render(&self) -> Markup {
+        let options = [
+            (Theme::System, "Same as device"),
+            (Theme::Light, "Light"),
+            (Theme::Dark, "Dark"),
+        ];
+
+        html! {
+            select #theme ."px-2 py-1 rounded-md bg-white border border-gray-300" name="theme" {
+                @for (t, label) in options {
+                    option value=(t.as_str()) selected[t.eq(&self.0)] { (label) }
+                }
             }
         }
     }
 }
 
+struct LocaleSelect<'a>(&'a Locale);
+
+impl Render for LocaleSelect<'_> {
+    fn render(&self) -> Markup {
+        html! {
+            select #locale ."px-2 py-1 rounded-md bg-white border border-gray-300" name="locale" {
+                @for (tag, label) in LOCALES {
+                    option value=(tag) selected[tag.eq(self.0.as_str())] { (label) }
+                }
+            }
+        }
+    }
+}
+
+/// Settings the browser applies on its own, kept next to the tabs and
+/// swapped out of band whenever they are saved.
+pub struct Preferences<'a> {
+    pub settings: &'a Settings,
+    pub oob: bool,
+}
+
+impl Render for Preferences<'_> {
+    fn render(&self) -> Markup {
+        let s = self.settings;
+        let n = s.notifications();
+
+        html! {
+            div #preferences .hidden
+                hx-swap-oob=[self.oob.then_some("true")]
+                data-theme=(s.theme().as_str())
+                data-time-zone=(s.time_zone().as_str())
+                data-locale=(s.locale().as_str())
+                data-notify-messages=(n.messages)
+                data-notify-talks=(n.talks)
+                data-notify-contact-requests=(n.contact_requests) {}
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use maud::Render;
 
-    use super::List;
+    use uuid::Uuid;
+
+    use super::{List, Preferences, SettingsForm};
+    use crate::settings::model::Settings;
+    use crate::user::{self, Presence};
 
     #[test]
     fn should_render_settings_list() {
@@ -43,6 +235,28 @@ mod test {
             r#"<h2 class="text-2xl">Settings</h2>"#,
             "</header>",
             r#"<ul class="space-y-2">"#,
+            r##"<li class="px-3 py-2 rounded-md bg-gray-100 hover:bg-gray-200 cursor-pointer flex items-center" hx-get="/templates/users/profile" hx-target="#profile-settings">"##,
+            r#"<i class="mr-2 fa-solid fa-user-pen"></i>"#,
+            "Profile",
+            "</li>",
+            r##"<li class="px-3 py-2 rounded-md bg-gray-100 hover:bg-gray-200 cursor-pointer flex items-center" hx-get="/templates/users/privacy" hx-target="#privacy-settings">"##,
+            r#"<i class="mr-2 fa-solid fa-user-shield"></i>"#,
+            "Privacy",
+            "</li>",
+            r##"<li class="px-3 py-2 rounded-md bg-gray-100 hover:bg-gray-200 cursor-pointer flex items-center" hx-get="/templates/settings/preferences" hx-target="#preferences-settings">"##,
+            r#"<i class="mr-2 fa-solid fa-sliders"></i>"#,
+            "Preferences",
+            "</li>",
+            r#"<li class="px-3 py-2 rounded-md bg-gray-100 hover:bg-gray-200 cursor-pointer flex items-center">"#,
+            r#"<i class="mr-2 fa-solid fa-circle-half-stroke"></i>"#,
+            r#"<span class="flex-grow">Status</span>"#,
+            r#"<select class="px-2 py-1 rounded-md bg-white border border-gray-300" id="presence-select" name="presence" hx-put="/api/users/presence" hx-trigger="change" hx-swap="outerHTML">"#,
+            r#"<option value="available">Available</option>"#,
+            r#"<option value="away" selected>Away</option>"#,
+            r#"<option value="dnd">Do not disturb</option>"#,
+            r#"<option value="invisible">Invisible</option>"#,
+            "</select>",
+            "</li>",
             r#"<li class="px-3 py-2 rounded-md bg-gray-100 hover:bg-gray-200 cursor-pointer flex items-center">"#,
             r#"<a class="flex-grow" href="/logout">"#,
             r#"<i class="mr-2 fa-solid fa-arrow-right-from-bracket"></i>"#,
@@ -53,10 +267,106 @@ mod test {
             r#"<i class="mr-2 fa-regular fa-bell-slash"></i>"#,
             "Enable notifications",
             "</li>",
+            r##"<li class="px-3 py-2 rounded-md bg-gray-100 hover:bg-gray-200 cursor-pointer flex items-center" hx-get="/templates/contacts/blocked" hx-target="#blocked-users">"##,
+            r#"<i class="mr-2 fa-solid fa-user-slash"></i>"#,
+            "Blocked users",
+            "</li>",
+            r#"<li class="px-3 py-2 rounded-md bg-gray-100 hover:bg-gray-200 cursor-pointer flex items-center">"#,
+            r#"<a class="flex-grow" href="/api/users/export" download>"#,
+            r#"<i class="mr-2 fa-solid fa-file-zipper"></i>"#,
+            "Download my data",
+            "</a>",
+            "</li>",
+            r#"<li class="px-3 py-2 rounded-md bg-gray-100 hover:bg-gray-200 cursor-pointer flex items-center text-red-600" hx-delete="/api/users/account" hx-confirm="Delete your account? Your messages stay in talks as from a deleted user, everything else is gone for good.">"#,
+            r#"<i class="mr-2 fa-solid fa-user-xmark"></i>"#,
+            "Delete account",
+            "</li>",
             "</ul>",
+            r#"<div id="profile-settings"></div>"#,
+            r#"<div id="privacy-settings"></div>"#,
+            r#"<div id="preferences-settings"></div>"#,
+            r#"<div id="blocked-users"></div>"#,
         );
 
-        let actual = List.render().into_string();
+        let actual = List(Presence::Away).render().into_string();
+
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn should_render_settings_form() {
+        let settings = Settings::default_for(&user::Id::from(Uuid::new_v4()));
+        let expected = concat!(
+            r#"<form class="flex flex-col space-y-2 p-3" id="settings-form" hx-put="/api/settings" hx-target="this" hx-swap="outerHTML">"#,
+            r#"<label class="text-sm text-gray-500" for="theme">Theme</label>"#,
+            r#"<select class="px-2 py-1 rounded-md bg-white border border-gray-300" id="theme" name="theme">"#,
+            r#"<option value="system" selected>Same as device</option>"#,
+            r#"<option value="light">Light</option>"#,
+            r#"<option value="dark">Dark</option>"#,
+            "</select>",
+            r#"<label class="text-sm text-gray-500" for="time-zone">Time zone</label>"#,
+            r#"<div class="flex space-x-2">"#,
+            r#"<input class="flex-grow px-2 py-1 rounded-md border border-gray-300" id="time-zone" type="text" name="time_zone" value="UTC" required></input>"#,
+            r#"<button class="px-2 py-1 rounded-md bg-gray-100 hover:bg-gray-200" type="button" _="on click set the value of #time-zone to Intl.DateTimeFormat().resolvedOptions().timeZone">Detect</button>"#,
+            "</div>",
+            r#"<label class="text-sm text-gray-500" for="locale">Language and formats</label>"#,
+            r#"<select class="px-2 py-1 rounded-md bg-white border border-gray-300" id="locale" name="locale">"#,
+            r#"<option value="en" selected>English</option>"#,
+            r#"<option value="en-GB">English (UK)</option>"#,
+            r#"<option value="de">Deutsch</option>"#,
+            r#"<option value="es">Español</option>"#,
+            r#"<option value="fr">Français</option>"#,
+            r#"<option value="ro">Română</option>"#,
+            r#"<option value="ru">Русский</option>"#,
+            r#"<option value="uk">Українська</option>"#,
+            "</select>",
+            r#"<label class="flex items-center space-x-2">"#,
+            r#"<input type="checkbox" name="enter_to_send" value="true" checked></input>"#,
+            "<span>Send messages with Enter instead of Ctrl+Enter</span>",
+            "</label>",
+            r#"<span class="text-sm text-gray-500">Notify me about</span>"#,
+            r#"<label class="flex items-center space-x-2">"#,
+            r#"<input type="checkbox" name="notify_messages" value="true" checked></input>"#,
+            "<span>New messages</span>",
+            "</label>",
+            r#"<label class="flex items-center space-x-2">"#,
+            r#"<input type="checkbox" name="notify_talks" value="true" checked></input>"#,
+            "<span>Invitations to talks</span>",
+            "</label>",
+            r#"<label class="flex items-center space-x-2">"#,
+            r#"<input type="checkbox" name="notify_contact_requests" value="true" checked></input>"#,
+            "<span>Contact requests</span>",
+            "</label>",
+            r#"<div class="flex items-center space-x-2">"#,
+            r#"<input class="px-4 py-2 text-white bg-green-700 hover:bg-green-800 rounded-md cursor-pointer" type="submit" value="Save"></input>"#,
+            r#"<span class="text-green-700">Saved</span>"#,
+            "</div>",
+            "</form>",
+        );
+
+        let actual = SettingsForm {
+            settings: &settings,
+            saved: true,
+        }
+        .render()
+        .into_string();
+
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn should_render_preferences_out_of_band() {
+        let settings = Settings::default_for(&user::Id::from(Uuid::new_v4()));
+        let expected = concat!(
+            r#"<div class="hidden" id="preferences" hx-swap-oob="true" data-theme="system" data-time-zone="UTC" data-locale="en" data-notify-messages="true" data-notify-talks="true" data-notify-contact-requests="true"></div>"#,
+        );
+
+        let actual = Preferences {
+            settings: &settings,
+            oob: true,
+        }
+        .render()
+        .into_string();
 
         assert_eq!(expected, actual);
     }
@@ -1 +1,171 @@
+use std::sync::Arc;
+
+use axum::{
+    Router,
+    routing::{get, put},
+};
+use diesel::{deserialize::FromSqlRow, expression::AsExpression, sql_types};
+use messenger_service::AsStr;
+use repository::SettingsRepository;
+use serde::{Deserialize, Serialize};
+use service::SettingsService;
+
+use crate::state::AppServices;
+
+mod handler;
 pub mod markup;
+pub mod model;
+pub mod repository;
+pub mod service;
+
+type Result<T> = std::result::Result<T, Error>;
+
+pub type Repository = Arc<dyn SettingsRepository + Send + Sync>;
+pub type Service = Arc<dyn SettingsService + Send + Sync>;
+
+/// Locales offered to users, by language tag and the name they know it by.
+pub const LOCALES: [(&str, &str); 8] = [
+    ("en", "English"),
+    ("en-GB", "English (UK)"),
+    ("de", "Deutsch"),
+    ("es", "Español"),
+    ("fr", "Français"),
+    ("ro", "Română"),
+    ("ru", "Русский"),
+    ("uk", "Українська"),
+];
+
+pub fn api<S>(s: AppServices) -> Router<S> {
+    Router::new()
+        .route("/settings", put(handler::api::update))
+        .with_state(s)
+}
+
+pub fn templates<S>(s: AppServices) -> Router<S> {
+    Router::new()
+        .route(
+            "/settings/preferences",
+            get(handler::templates::preferences),
+        )
+        .with_state(s)
+}
+
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq, AsExpression, FromSqlRow,
+)]
+#[diesel(sql_type = crate::schema::sql_types::Theme)]
+#[serde(rename_all = "snake_case")]
+pub enum Theme {
+    Light,
+    Dark,
+    /// Follows the color scheme of the device.
+    #[default]
+    System,
+}
+
+impl AsStr for Theme {
+    fn as_str(&self) -> &str {
+        match self {
+            Self::Light => "light",
+            Self::Dark => "dark",
+            Self::System => "system",
+        }
+    }
+}
+
+impl TryFrom<String> for Theme {
+    type Error = Error;
+
+    fn try_from(s: String) -> std::result::Result<Self, Self::Error> {
+        match s.as_str() {
+            "light" => Ok(Self::Light),
+            "dark" => Ok(Self::Dark),
+            "system" => Ok(Self::System),
+            _ => Err(Error::UnsupportedTheme(s)),
+        }
+    }
+}
+
+/// IANA time zone name like `Europe/Chisinau`, resolved by the browser.
+#[derive(Serialize, Deserialize, FromSqlRow, AsExpression, Clone, PartialEq, Eq, Debug)]
+#[diesel(sql_type = sql_types::Text)]
+pub struct TimeZone(String);
+
+impl TimeZone {
+    const MAX_LEN: usize = 64;
+}
+
+impl Default for TimeZone {
+    fn default() -> Self {
+        Self(String::from("UTC"))
+    }
+}
+
+impl AsStr for TimeZone {
+    fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl TryFrom<String> for TimeZone {
+    type Error = Error;
+
+    /// Only the shape of the name is checked, the zone database lives in the browser.
+    fn try_from(s: String) -> std::result::Result<Self, Self::Error> {
+        let valid = (1..=Self::MAX_LEN).contains(&s.len())
+            && !s.starts_with('/')
+            && !s.ends_with('/')
+            && s.chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | '_' | '-' | '+'));
+
+        if valid {
+            Ok(Self(s))
+        } else {
+            Err(Error::InvalidTimeZone(s))
+        }
+    }
+}
+
+/// Language tag of one of the [`LOCALES`].
+#[derive(Serialize, Deserialize, FromSqlRow, AsExpression, Clone, PartialEq, Eq, Debug)]
+#[diesel(sql_type = sql_types::Text)]
+pub struct Locale(String);
+
+impl Default for Locale {
+    fn default() -> Self {
+        Self(String::from("en"))
+    }
+}
+
+impl AsStr for Locale {
+    fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl TryFrom<String> for Locale {
+    type Error = Error;
+
+    fn try_from(s: String) -> std::result::Result<Self, Self::Error> {
+        if LOCALES.iter().any(|(tag, _)| *tag == s) {
+            Ok(Self(s))
+        } else {
+            Err(Error::UnsupportedLocale(s))
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("unsupported theme: {0:?}")]
+    UnsupportedTheme(String),
+    #[error("invalid time zone: {0:?}")]
+    InvalidTimeZone(String),
+    #[error("unsupported locale: {0:?}")]
+    UnsupportedLocale(String),
+
+    #[error(transparent)]
+    _R2d2(#[from] r2d2::Error),
+    #[error(transparent)]
+    _Diesel(#[from] diesel::result::Error),
+}
@@ -0,0 +1,96 @@
+use diesel::prelude::{AsChangeset, Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+
+use crate::user;
+
+use super::{Locale, Theme, TimeZone};
+
+/// Preferences of a user, applied wherever the app renders for them.
+/// Users without a stored row get the defaults.
+#[derive(Queryable, Selectable, Insertable, AsChangeset, Serialize, Deserialize, Clone, Debug)]
+#[diesel(table_name = crate::schema::settings)]
+#[diesel(primary_key(user_id))]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Settings {
+    user_id: user::Id,
+    theme: Theme,
+    time_zone: TimeZone,
+    locale: Locale,
+    enter_to_send: bool,
+    notify_messages: bool,
+    notify_talks: bool,
+    notify_contact_requests: bool,
+}
+
+/// Events the user wants a desktop notification for.
+#[derive(Clone, Copy)]
+pub struct Notifications {
+    pub messages: bool,
+    pub talks: bool,
+    pub contact_requests: bool,
+}
+
+impl Settings {
+    pub const fn new(
+        user_id: user::Id,
+        theme: Theme,
+        time_zone: TimeZone,
+        locale: Locale,
+        enter_to_send: bool,
+        n: Notifications,
+    ) -> Self {
+        Self {
+            user_id,
+            theme,
+            time_zone,
+            locale,
+            enter_to_send,
+            notify_messages: n.messages,
+            notify_talks: n.talks,
+            notify_contact_requests: n.contact_requests,
+        }
+    }
+
+    pub fn default_for(user_id: &user::Id) -> Self {
+        Self::new(
+            user_id.clone(),
+            Theme::default(),
+            TimeZone::default(),
+            Locale::default(),
+            true,
+            Notifications {
+                messages: true,
+                talks: true,
+                contact_requests: true,
+            },
+        )
+    }
+
+    pub const fn user_id(&self) -> &user::Id {
+        &self.user_id
+    }
+
+    pub const fn theme(&self) -> Theme {
+        self.theme
+    }
+
+    pub const fn time_zone(&self) -> &TimeZone {
+        &self.time_zone
+    }
+
+    pub const fn locale(&self) -> &Locale {
+        &self.locale
+    }
+
+    pub const fn enter_to_send(&self) -> bool {
+        self.enter_to_send
+    }
+
+    pub const fn notifications(&self) -> Notifications {
+        Notifications {
+            messages: self.notify_messages,
+            talks: self.notify_talks,
+            contact_requests: self.notify_contact_requests,
+        }
+    }
+}
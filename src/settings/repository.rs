@@ -0,0 +1,57 @@
+use diesel::OptionalExtension;
+use diesel::PgConnection;
+use diesel::QueryDsl;
+use diesel::RunQueryDsl;
+use diesel::SelectableHelper;
+use diesel::insert_into;
+use diesel::r2d2::ConnectionManager;
+
+use crate::user;
+
+use super::model::Settings;
+
+pub trait SettingsRepository {
+    fn find(&self, user_id: &user::Id) -> super::Result<Option<Settings>>;
+
+    fn save(&self, s: &Settings) -> super::Result<()>;
+}
+
+pub struct PgSettingsRepository {
+    pool: r2d2::Pool<ConnectionManager<PgConnection>>,
+}
+
+impl PgSettingsRepository {
+    pub const fn new(pool: r2d2::Pool<ConnectionManager<PgConnection>>) -> Self {
+        Self { pool }
+    }
+}
+
+impl SettingsRepository for PgSettingsRepository {
+    fn find(&self, u_id: &user::Id) -> super::Result<Option<Settings>> {
+        use crate::schema::settings::dsl::settings;
+
+        let mut conn = self.pool.get()?;
+
+        settings
+            .find(u_id)
+            .select(Settings::as_select())
+            .first(&mut conn)
+            .optional()
+            .map_err(super::Error::from)
+    }
+
+    fn save(&self, s: &Settings) -> super::Result<()> {
+        use crate::schema::settings::dsl::{settings, user_id};
+
+        let mut conn = self.pool.get()?;
+
+        insert_into(settings)
+            .values(s)
+            .on_conflict(user_id)
+            .do_update()
+            .set(s)
+            .execute(&mut conn)?;
+
+        Ok(())
+    }
+}
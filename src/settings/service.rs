@@ -0,0 +1,52 @@
+use async_trait::async_trait;
+
+use crate::integration::cache;
+use crate::user;
+
+use super::Repository;
+use super::model::Settings;
+
+#[async_trait]
+pub trait SettingsService {
+    /// Stored settings of the user, or the defaults if they never saved any.
+    async fn find(&self, user_id: &user::Id) -> super::Result<Settings>;
+
+    async fn update(&self, s: &Settings) -> super::Result<()>;
+}
+
+#[derive(Clone)]
+pub struct SettingsServiceImpl {
+    repo: Repository,
+    redis: cache::Redis,
+}
+
+impl SettingsServiceImpl {
+    pub fn new(repo: Repository, redis: cache::Redis) -> Self {
+        Self { repo, redis }
+    }
+}
+
+#[async_trait]
+impl SettingsService for SettingsServiceImpl {
+    async fn find(&self, user_id: &user::Id) -> super::Result<Settings> {
+        let key = cache::Key::Settings(user_id);
+        if let Some(s) = self.redis.json_get::<Settings>(key.clone(), None).await {
+            return Ok(s);
+        }
+
+        let s = self
+            .repo
+            .find(user_id)?
+            .unwrap_or_else(|| Settings::default_for(user_id));
+        self.redis.json_set_ex(key, &s).await;
+
+        Ok(s)
+    }
+
+    async fn update(&self, s: &Settings) -> super::Result<()> {
+        self.repo.save(s)?;
+        self.redis.json_del(cache::Key::Settings(s.user_id())).await;
+
+        Ok(())
+    }
+}
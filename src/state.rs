@@ -8,11 +8,13 @@ use crate::contact::service::ContactServiceImpl;
 use crate::event::service::EventServiceImpl;
 use crate::message::repository::PgMessageRepository;
 use crate::message::service::MessageServiceImpl;
+use crate::settings::repository::PgSettingsRepository;
+use crate::settings::service::SettingsServiceImpl;
 use crate::talk::repository::PgTalkRepository;
 use crate::talk::service::TalkServiceImpl;
 use crate::user::repository::PgUserRepository;
 use crate::user::service::UserServiceImpl;
-use crate::{auth, contact, event, message, talk, user};
+use crate::{auth, contact, event, message, settings, talk, user};
 
 use super::integration;
 
@@ -23,6 +25,7 @@ pub struct AppServices {
     contact: contact::Service,
     talk: talk::Service,
     message: message::Service,
+    settings: settings::Service,
     event: event::Service,
 }
 
@@ -37,7 +40,12 @@ impl AppServices {
         let event_service = Arc::new(EventServiceImpl::new(pubsub));
 
         let contact_repo = Arc::new(PgContactRepository::new(pg.clone()));
-        let contact_service = Arc::new(ContactServiceImpl::new(contact_repo, redis.clone()));
+        let contact_service = Arc::new(ContactServiceImpl::new(
+            contact_repo,
+            event_service.clone(),
+            redis.clone(),
+        ));
+        contact_service.spawn_expiry();
 
         let user_repo = Arc::new(PgUserRepository::new(pg.clone()));
         let user_service = Arc::new(UserServiceImpl::new(
@@ -46,12 +54,17 @@ impl AppServices {
             event_service.clone(),
             redis.clone(),
         ));
+        user_service.spawn_sweeper();
+
+        let settings_repo = Arc::new(PgSettingsRepository::new(pg.clone()));
+        let settings_service = Arc::new(SettingsServiceImpl::new(settings_repo, redis.clone()));
 
         let talk_repo = Arc::new(PgTalkRepository::new(pg.clone()));
         let message_repo = Arc::new(PgMessageRepository::new(pg));
 
         let talk_service = Arc::new(TalkServiceImpl::new(
             talk_repo,
+            message_repo.clone(),
             user_service.clone(),
             contact_service.clone(),
             event_service.clone(),
@@ -62,6 +75,7 @@ impl AppServices {
         let message_service = Arc::new(MessageServiceImpl::new(
             message_repo,
             user_service.clone(),
+            talk_service.clone(),
             event_service.clone(),
         ));
 
@@ -71,6 +85,7 @@ impl AppServices {
             contact: contact_service,
             talk: talk_service,
             message: message_service,
+            settings: settings_service,
             event: event_service,
         }
     }
@@ -106,6 +121,12 @@ impl FromRef<AppServices> for message::Service {
     }
 }
 
+impl FromRef<AppServices> for settings::Service {
+    fn from_ref(s: &AppServices) -> Self {
+        s.settings.clone()
+    }
+}
+
 impl FromRef<AppServices> for event::Service {
     fn from_ref(s: &AppServices) -> Self {
         s.event.clone()
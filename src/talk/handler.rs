@@ -3,18 +3,33 @@ use axum::http::StatusCode;
 impl From<super::Error> for StatusCode {
     fn from(e: super::Error) -> Self {
         match e {
-            super::Error::NotFound(_) => Self::NOT_FOUND,
-            super::Error::AlreadyExists => Self::CONFLICT,
+            super::Error::NotFound(_) | super::Error::InviteNotFound => Self::NOT_FOUND,
+            super::Error::InviteExpired => Self::GONE,
+            super::Error::AlreadyExists | super::Error::AlreadyMember(_) => Self::CONFLICT,
+            super::Error::Forbidden
+            | super::Error::PostingRestricted
+            | super::Error::Blocked(_)
+            | super::Error::AddsRestricted(_) => Self::FORBIDDEN,
             super::Error::NotEnoughMembers(_)
             | super::Error::MissingName
             | super::Error::NonExistingUser(_)
-            | super::Error::UnsupportedStatus => Self::BAD_REQUEST,
+            | super::Error::UnsupportedStatus
+            | super::Error::OwnerCannotLeave
+            | super::Error::NoMembersSelected
+            | super::Error::NotMember(_)
+            | super::Error::UnsupportedRole(_)
+            | super::Error::InvalidAvatar(_)
+            | super::Error::InvalidInvite(_)
+            | super::Error::InvalidMute
+            | super::Error::InvalidCursor(_) => Self::BAD_REQUEST,
             super::Error::NotCreated
             | super::Error::UnsupportedKind(_)
+            | super::Error::_Contact(_)
             | super::Error::_User(_)
             | super::Error::_Integration(_)
             | super::Error::_R2d2(_)
-            | super::Error::_Diesel(_) => Self::INTERNAL_SERVER_ERROR,
+            | super::Error::_Diesel(_)
+            | super::Error::_Join(_) => Self::INTERNAL_SERVER_ERROR,
         }
     }
 }
@@ -29,6 +44,7 @@ pub(super) mod pages {
 
     use crate::{
         auth,
+        markup::Wrappable,
         talk::{self, Kind, markup},
     };
 
@@ -37,13 +53,41 @@ pub(super) mod pages {
         kind: Kind,
     }
 
+    pub async fn join(
+        Path(token): Path<String>,
+        talk_service: State<talk::Service>,
+    ) -> crate::Result<Wrappable> {
+        match talk_service.find_invite(&token) {
+            Ok((_, name, picture)) => Ok(Wrappable::new(markup::JoinGroup::Invite {
+                token: &token,
+                picture: &picture,
+                name: &name,
+            })),
+            Err(talk::Error::InviteNotFound | talk::Error::InviteExpired) => {
+                Ok(Wrappable::new(markup::JoinGroup::Unavailable))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
     pub async fn active_talk(
         id: Path<talk::Id>,
         auth_user: Extension<auth::User>,
         params: Query<KindParams>,
         talk_service: State<talk::Service>,
     ) -> crate::Result<Markup> {
-        let talk = &talk_service.find_by_id_and_user_id(&params.kind, &id, auth_user.id())?;
+        let talk = &talk_service
+            .find_by_id_and_user_id(&params.kind, &id, auth_user.id())
+            .await?;
+
+        Ok(markup::ActiveTalk(&auth_user, talk).render())
+    }
+
+    pub async fn saved(
+        auth_user: Extension<auth::User>,
+        talk_service: State<talk::Service>,
+    ) -> crate::Result<Markup> {
+        let talk = &talk_service.find_or_create_saved(auth_user.id())?;
 
         Ok(markup::ActiveTalk(&auth_user, talk).render())
     }
@@ -51,16 +95,20 @@ pub(super) mod pages {
 
 pub(super) mod api {
     use axum::{
-        Extension, Json,
-        extract::{Path, Query, State},
+        Extension, Form, Json,
+        extract::{Multipart, Path, Query, State},
         response::IntoResponse,
     };
+    use chrono::{DateTime, Duration, Utc};
     use maud::{Markup, Render};
     use serde::Deserialize;
 
     use crate::{
-        auth,
-        talk::{self, Kind, markup},
+        auth, contact, message,
+        talk::{
+            self, Kind, Role, markup,
+            model::{Cursor, Page, PreferenceChange},
+        },
         user,
     };
 
@@ -69,13 +117,55 @@ pub(super) mod api {
         kind: Kind,
     }
 
+    #[derive(Deserialize)]
+    pub struct FindAllParams {
+        kind: Kind,
+        cursor: Option<Cursor>,
+    }
+
+    pub async fn find_all(
+        auth_user: Extension<auth::User>,
+        Query(params): Query<FindAllParams>,
+        talk_service: State<talk::Service>,
+    ) -> crate::Result<Markup> {
+        let page = Page::active(params.cursor);
+        let talks = talk_service.find_all_by_kind(&auth_user, &params.kind, &page)?;
+
+        Ok(markup::TalkPage::new(&talks, &params.kind).render())
+    }
+
+    #[derive(Deserialize)]
+    pub struct SearchParams {
+        kind: Kind,
+        query: String,
+    }
+
+    pub async fn search(
+        auth_user: Extension<auth::User>,
+        talk_service: State<talk::Service>,
+        Form(params): Form<SearchParams>,
+    ) -> crate::Result<Markup> {
+        // an emptied search box brings the regular list back
+        if params.query.trim().is_empty() {
+            let talks =
+                talk_service.find_all_by_kind(&auth_user, &params.kind, &Page::active(None))?;
+            return Ok(markup::TalkPage::new(&talks, &params.kind).render());
+        }
+
+        let talks = talk_service.search(&auth_user, &params.kind, &params.query)?;
+
+        Ok(markup::TalkPage::search_result(&talks, &params.kind).render())
+    }
+
     pub async fn find_one(
         auth_user: Extension<auth::User>,
         params: Query<KindParams>,
         talk_service: State<talk::Service>,
         Path(id): Path<talk::Id>,
     ) -> crate::Result<Markup> {
-        let t = talk_service.find_by_id_and_user_id(&params.kind, &id, auth_user.id())?;
+        let t = talk_service
+            .find_by_id_and_user_id(&params.kind, &id, auth_user.id())
+            .await?;
 
         Ok(t.render())
     }
@@ -94,6 +184,7 @@ pub(super) mod api {
     pub enum CreateParams {
         Chat { user_id: user::Id },
         Group(CreateGroupParams),
+        Channel { name: String },
     }
 
     // This enum is needed to match the case when no users were selected
@@ -127,6 +218,7 @@ pub(super) mod api {
 
                 talk_service.create_group(auth_id, &name, &members).await
             }
+            CreateParams::Channel { name } => talk_service.create_channel(auth_id, &name).await,
         }?;
 
         Ok(markup::ActiveTalk(&auth_user, &talk).render())
@@ -141,10 +233,412 @@ pub(super) mod api {
 
         Ok([("HX-Redirect", "/")])
     }
+
+    // Same as CreateGroupParams, a single selected user can't be deserialized into Vec
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    pub enum MembersParams {
+        Many {
+            #[serde(default)]
+            members: Vec<user::Id>,
+        },
+        Single {
+            members: user::Id,
+        },
+    }
+
+    pub async fn add_members(
+        Path(id): Path<talk::Id>,
+        auth_user: Extension<auth::User>,
+        talk_service: State<talk::Service>,
+        contact_service: State<contact::Service>,
+        user_service: State<user::Service>,
+        Json(params): Json<MembersParams>,
+    ) -> crate::Result<Markup> {
+        let members = match params {
+            MembersParams::Many { members } => members,
+            MembersParams::Single { members } => vec![members],
+        };
+
+        talk_service
+            .add_members(auth_user.id(), &id, &members)
+            .await?;
+
+        super::templates::group_members(
+            Path(id),
+            auth_user,
+            talk_service,
+            contact_service,
+            user_service,
+        )
+        .await
+    }
+
+    pub async fn remove_member(
+        Path((id, user_id)): Path<(talk::Id, user::Id)>,
+        auth_user: Extension<auth::User>,
+        talk_service: State<talk::Service>,
+    ) -> crate::Result<()> {
+        talk_service
+            .remove_member(auth_user.id(), &id, &user_id)
+            .await?;
+
+        Ok(())
+    }
+
+    #[derive(Deserialize)]
+    pub struct RoleParams {
+        role: Role,
+    }
+
+    pub async fn set_role(
+        Path((id, user_id)): Path<(talk::Id, user::Id)>,
+        auth_user: Extension<auth::User>,
+        talk_service: State<talk::Service>,
+        contact_service: State<contact::Service>,
+        user_service: State<user::Service>,
+        Form(params): Form<RoleParams>,
+    ) -> crate::Result<Markup> {
+        talk_service
+            .set_role(auth_user.id(), &id, &user_id, &params.role)
+            .await?;
+
+        super::templates::group_members(
+            Path(id),
+            auth_user,
+            talk_service,
+            contact_service,
+            user_service,
+        )
+        .await
+    }
+
+    #[derive(Deserialize)]
+    pub struct TransferOwnershipParams {
+        user_id: user::Id,
+    }
+
+    pub async fn transfer_ownership(
+        Path(id): Path<talk::Id>,
+        auth_user: Extension<auth::User>,
+        talk_service: State<talk::Service>,
+        contact_service: State<contact::Service>,
+        user_service: State<user::Service>,
+        Form(params): Form<TransferOwnershipParams>,
+    ) -> crate::Result<Markup> {
+        talk_service
+            .transfer_ownership(auth_user.id(), &id, &params.user_id)
+            .await?;
+
+        super::templates::group_members(
+            Path(id),
+            auth_user,
+            talk_service,
+            contact_service,
+            user_service,
+        )
+        .await
+    }
+
+    #[derive(Deserialize)]
+    pub struct RenameParams {
+        name: String,
+    }
+
+    pub async fn rename(
+        Path(id): Path<talk::Id>,
+        auth_user: Extension<auth::User>,
+        talk_service: State<talk::Service>,
+        Form(params): Form<RenameParams>,
+    ) -> crate::Result<Markup> {
+        talk_service
+            .rename(auth_user.id(), &id, &params.name)
+            .await?;
+
+        super::templates::edit_group(Path(id), auth_user, talk_service).await
+    }
+
+    pub async fn upload_avatar(
+        Path(id): Path<talk::Id>,
+        auth_user: Extension<auth::User>,
+        talk_service: State<talk::Service>,
+        mut multipart: Multipart,
+    ) -> crate::Result<Markup> {
+        let data = loop {
+            let field = multipart
+                .next_field()
+                .await
+                .map_err(|e| talk::Error::InvalidAvatar(e.body_text()))?;
+
+            match field {
+                Some(f) if f.name() == Some("avatar") => {
+                    break f
+                        .bytes()
+                        .await
+                        .map_err(|e| talk::Error::InvalidAvatar(e.body_text()))?;
+                }
+                Some(_) => {}
+                None => return Err(talk::Error::InvalidAvatar("missing file".into()).into()),
+            }
+        };
+
+        talk_service
+            .update_avatar(auth_user.id(), &id, data)
+            .await?;
+
+        super::templates::edit_group(Path(id), auth_user, talk_service).await
+    }
+
+    pub async fn reset_avatar(
+        Path(id): Path<talk::Id>,
+        auth_user: Extension<auth::User>,
+        talk_service: State<talk::Service>,
+    ) -> crate::Result<Markup> {
+        talk_service.reset_avatar(auth_user.id(), &id).await?;
+
+        super::templates::edit_group(Path(id), auth_user, talk_service).await
+    }
+
+    #[derive(Deserialize)]
+    pub struct InviteParams {
+        // hours, 0 never expires
+        expires_in: i64,
+        // 0 is unlimited
+        max_uses: i32,
+    }
+
+    pub async fn create_invite(
+        Path(id): Path<talk::Id>,
+        auth_user: Extension<auth::User>,
+        talk_service: State<talk::Service>,
+        Form(params): Form<InviteParams>,
+    ) -> crate::Result<Markup> {
+        let expires_in = match params.expires_in {
+            0 => None,
+            h => Some(
+                Duration::try_hours(h)
+                    .ok_or_else(|| talk::Error::InvalidInvite("expiry is out of range".into()))?,
+            ),
+        };
+        let max_uses = (params.max_uses != 0).then_some(params.max_uses);
+
+        talk_service.create_invite(auth_user.id(), &id, expires_in, max_uses)?;
+
+        super::templates::group_invites(Path(id), auth_user, talk_service).await
+    }
+
+    pub async fn revoke_invite(
+        Path((id, token)): Path<(talk::Id, String)>,
+        auth_user: Extension<auth::User>,
+        talk_service: State<talk::Service>,
+    ) -> crate::Result<()> {
+        talk_service.revoke_invite(auth_user.id(), &id, &token)?;
+
+        Ok(())
+    }
+
+    pub async fn join(
+        Path(token): Path<String>,
+        auth_user: Extension<auth::User>,
+        talk_service: State<talk::Service>,
+    ) -> crate::Result<impl IntoResponse> {
+        talk_service.join(auth_user.id(), &token).await?;
+
+        Ok([("HX-Redirect", "/")])
+    }
+
+    pub async fn archive(
+        Path(id): Path<talk::Id>,
+        auth_user: Extension<auth::User>,
+        params: Query<KindParams>,
+        talk_service: State<talk::Service>,
+    ) -> crate::Result<Markup> {
+        update_preferences(
+            &id,
+            &params.kind,
+            &auth_user,
+            &talk_service,
+            PreferenceChange::Archive(true),
+        )
+        .await
+    }
+
+    pub async fn unarchive(
+        Path(id): Path<talk::Id>,
+        auth_user: Extension<auth::User>,
+        params: Query<KindParams>,
+        talk_service: State<talk::Service>,
+    ) -> crate::Result<Markup> {
+        update_preferences(
+            &id,
+            &params.kind,
+            &auth_user,
+            &talk_service,
+            PreferenceChange::Archive(false),
+        )
+        .await
+    }
+
+    pub async fn pin(
+        Path(id): Path<talk::Id>,
+        auth_user: Extension<auth::User>,
+        params: Query<KindParams>,
+        talk_service: State<talk::Service>,
+    ) -> crate::Result<Markup> {
+        update_preferences(
+            &id,
+            &params.kind,
+            &auth_user,
+            &talk_service,
+            PreferenceChange::Pin(true),
+        )
+        .await
+    }
+
+    pub async fn unpin(
+        Path(id): Path<talk::Id>,
+        auth_user: Extension<auth::User>,
+        params: Query<KindParams>,
+        talk_service: State<talk::Service>,
+    ) -> crate::Result<Markup> {
+        update_preferences(
+            &id,
+            &params.kind,
+            &auth_user,
+            &talk_service,
+            PreferenceChange::Pin(false),
+        )
+        .await
+    }
+
+    #[derive(Deserialize)]
+    pub struct MuteParams {
+        // 0 mutes until unmuted
+        hours: i64,
+    }
+
+    pub async fn mute(
+        Path(id): Path<talk::Id>,
+        auth_user: Extension<auth::User>,
+        params: Query<KindParams>,
+        talk_service: State<talk::Service>,
+        Form(mute_params): Form<MuteParams>,
+    ) -> crate::Result<Markup> {
+        let until = match mute_params.hours {
+            0 => DateTime::<Utc>::MAX_UTC,
+            h if h > 0 => Duration::try_hours(h)
+                .and_then(|d| Utc::now().checked_add_signed(d))
+                .ok_or(talk::Error::InvalidMute)?,
+            _ => return Err(talk::Error::InvalidMute.into()),
+        };
+
+        update_preferences(
+            &id,
+            &params.kind,
+            &auth_user,
+            &talk_service,
+            PreferenceChange::MuteUntil(Some(until)),
+        )
+        .await
+    }
+
+    pub async fn unmute(
+        Path(id): Path<talk::Id>,
+        auth_user: Extension<auth::User>,
+        params: Query<KindParams>,
+        talk_service: State<talk::Service>,
+    ) -> crate::Result<Markup> {
+        update_preferences(
+            &id,
+            &params.kind,
+            &auth_user,
+            &talk_service,
+            PreferenceChange::MuteUntil(None),
+        )
+        .await
+    }
+
+    async fn update_preferences(
+        id: &talk::Id,
+        kind: &Kind,
+        auth_user: &auth::User,
+        talk_service: &talk::Service,
+        change: PreferenceChange,
+    ) -> crate::Result<Markup> {
+        talk_service
+            .update_preferences(auth_user.id(), id, change)
+            .await?;
+
+        let talk = talk_service
+            .find_by_id_and_user_id(kind, id, auth_user.id())
+            .await?;
+        Ok(markup::ActiveTalk(auth_user, &talk).render())
+    }
+
+    #[derive(Deserialize)]
+    pub struct PinParams {
+        message_id: message::Id,
+    }
+
+    pub async fn pin_message(
+        Path(id): Path<talk::Id>,
+        auth_user: Extension<auth::User>,
+        talk_service: State<talk::Service>,
+        Form(params): Form<PinParams>,
+    ) -> crate::Result<Markup> {
+        let msg = talk_service
+            .pin_message(auth_user.id(), &id, Some(&params.message_id))
+            .await?;
+
+        Ok(markup::PinnedMessage {
+            talk_id: &id,
+            msg: msg.as_ref(),
+        }
+        .render())
+    }
+
+    pub async fn unpin_message(
+        Path(id): Path<talk::Id>,
+        auth_user: Extension<auth::User>,
+        talk_service: State<talk::Service>,
+    ) -> crate::Result<Markup> {
+        talk_service.pin_message(auth_user.id(), &id, None).await?;
+
+        Ok(markup::PinnedMessage {
+            talk_id: &id,
+            msg: None,
+        }
+        .render())
+    }
+
+    pub async fn leave(
+        id: Path<talk::Id>,
+        auth_user: Extension<auth::User>,
+        talk_service: State<talk::Service>,
+    ) -> crate::Result<impl IntoResponse> {
+        talk_service.leave(auth_user.id(), &id).await?;
+
+        Ok([("HX-Redirect", "/")])
+    }
+
+    pub async fn subscribe(
+        id: Path<talk::Id>,
+        Extension(auth_user): Extension<auth::User>,
+        talk_service: State<talk::Service>,
+    ) -> crate::Result<Markup> {
+        let talk = talk_service.subscribe(auth_user.id(), &id).await?;
+
+        Ok(markup::ActiveTalk(&auth_user, &talk).render())
+    }
 }
 
 pub(super) mod templates {
-    use axum::{Extension, extract::State};
+    use std::collections::HashMap;
+
+    use axum::{
+        Extension,
+        extract::{Path, State},
+    };
     use maud::{Markup, Render};
 
     use crate::{auth, contact, talk, user};
@@ -153,6 +647,7 @@ pub(super) mod templates {
         user_id: user::Id,
         name: String,
         picture: String,
+        role: Option<talk::Role>,
     }
 
     impl GroupMemberDto {
@@ -161,9 +656,15 @@ pub(super) mod templates {
                 user_id,
                 name: name.into(),
                 picture: picture.into(),
+                role: None,
             }
         }
 
+        pub fn with_role(mut self, role: talk::Role) -> Self {
+            self.role = Some(role);
+            self
+        }
+
         pub const fn user_id(&self) -> &user::Id {
             &self.user_id
         }
@@ -175,6 +676,10 @@ pub(super) mod templates {
         pub fn picture(&self) -> &str {
             &self.picture
         }
+
+        pub const fn role(&self) -> Option<&talk::Role> {
+            self.role.as_ref()
+        }
     }
 
     pub async fn create_group(
@@ -186,9 +691,11 @@ pub(super) mod templates {
             .find_by_user_id_and_status(auth_user.id(), &contact::Status::Accepted)
             .await?;
 
+        let aliases = contact_service.find_aliases(auth_user.id())?;
+
         let mut members: Vec<GroupMemberDto> = Vec::with_capacity(contacts.len());
         for c in contacts {
-            let name = user_service.find_name(c.recipient()).await?;
+            let name = display_name(&user_service, &aliases, c.recipient()).await?;
             let picture = user_service.find_picture(c.recipient()).await?;
             members.push(GroupMemberDto::new(
                 c.recipient().clone(),
@@ -199,4 +706,115 @@ pub(super) mod templates {
 
         Ok(talk::markup::CreateGroupForm::new(&auth_user, &members).render())
     }
+
+    pub async fn create_channel() -> Markup {
+        talk::markup::CreateChannelForm.render()
+    }
+
+    pub async fn group_members(
+        Path(id): Path<talk::Id>,
+        auth_user: Extension<auth::User>,
+        talk_service: State<talk::Service>,
+        contact_service: State<contact::Service>,
+        user_service: State<user::Service>,
+    ) -> crate::Result<Markup> {
+        let talk = talk_service
+            .find_by_id_and_user_id(&talk::Kind::Group, &id, auth_user.id())
+            .await?;
+        let roles = talk_service.find_roles(&id)?;
+        let member_ids: Vec<&user::Id> = roles.iter().map(|(m, _)| m).collect();
+
+        let aliases = contact_service.find_aliases(auth_user.id())?;
+
+        let mut members: Vec<GroupMemberDto> = Vec::with_capacity(roles.len());
+        for (m, role) in &roles {
+            let name = display_name(&user_service, &aliases, m).await?;
+            let picture = user_service.find_picture(m).await?;
+            members.push(
+                GroupMemberDto::new(m.clone(), name, picture.to_string()).with_role(role.clone()),
+            );
+        }
+        members.sort_by(|a, b| a.name().cmp(b.name()));
+
+        let contacts = contact_service
+            .find_by_user_id_and_status(auth_user.id(), &contact::Status::Accepted)
+            .await?;
+
+        let mut candidates: Vec<GroupMemberDto> = Vec::with_capacity(contacts.len());
+        for c in contacts {
+            if member_ids.contains(&c.recipient()) {
+                continue;
+            }
+
+            let name = display_name(&user_service, &aliases, c.recipient()).await?;
+            let picture = user_service.find_picture(c.recipient()).await?;
+            candidates.push(GroupMemberDto::new(
+                c.recipient().clone(),
+                name,
+                picture.to_string(),
+            ));
+        }
+
+        Ok(talk::markup::GroupMembers::new(&auth_user, &talk, &members, &candidates).render())
+    }
+
+    /// Name of the user as the viewer knows them, their private alias if they gave one.
+    async fn display_name(
+        user_service: &user::Service,
+        aliases: &HashMap<user::Id, String>,
+        id: &user::Id,
+    ) -> crate::Result<String> {
+        match aliases.get(id) {
+            Some(alias) => Ok(alias.clone()),
+            None => Ok(user_service.find_name(id).await?),
+        }
+    }
+
+    pub async fn edit_group(
+        Path(id): Path<talk::Id>,
+        auth_user: Extension<auth::User>,
+        talk_service: State<talk::Service>,
+    ) -> crate::Result<Markup> {
+        let talk = talk_service
+            .find_by_id_and_user_id(&talk::Kind::Group, &id, auth_user.id())
+            .await?;
+
+        match talk.details() {
+            talk::model::DetailsDto::Group { role, .. }
+            | talk::model::DetailsDto::Channel { role, .. }
+                if role.permits(&talk::Action::Rename)
+                    || role.permits(&talk::Action::ChangeAvatar) =>
+            {
+                Ok(talk::markup::EditGroupForm::new(&talk).render())
+            }
+            _ => Err(talk::Error::Forbidden.into()),
+        }
+    }
+
+    pub async fn group_invites(
+        Path(id): Path<talk::Id>,
+        auth_user: Extension<auth::User>,
+        talk_service: State<talk::Service>,
+    ) -> crate::Result<Markup> {
+        let talk = talk_service
+            .find_by_id_and_user_id(&talk::Kind::Group, &id, auth_user.id())
+            .await?;
+        let invites = talk_service.find_invites(auth_user.id(), &id)?;
+
+        Ok(talk::markup::GroupInvites::new(&talk, &invites, chrono::Utc::now()).render())
+    }
+
+    pub async fn pinned_message(
+        Path(id): Path<talk::Id>,
+        auth_user: Extension<auth::User>,
+        talk_service: State<talk::Service>,
+    ) -> crate::Result<Markup> {
+        let msg = talk_service.find_pinned_message(auth_user.id(), &id)?;
+
+        Ok(talk::markup::PinnedMessage {
+            talk_id: &id,
+            msg: msg.as_ref(),
+        }
+        .render())
+    }
 }
@@ -1,17 +1,19 @@
 use std::fmt::Display;
 use std::rc::Rc;
 
+use chrono::{DateTime, Datelike, Utc};
 use maud::{Markup, Render, html};
 use messenger_service::AsStr;
 
 use crate::markup::IdExt;
 use crate::message::markup::{MESSAGE_INPUT_TARGET, MESSAGE_LIST_ID, MESSAGE_LIST_TARGET};
-use crate::talk::Kind;
+use crate::message::model::MessageDto;
 use crate::talk::model::DetailsDto;
+use crate::talk::{Action, Kind, Role};
 use crate::{auth, message, talk, user};
 
 use super::handler::templates::GroupMemberDto;
-use super::model::TalkDto;
+use super::model::{ChannelSummary, Cursor, Invite, Page, TalkDto};
 
 impl Display for super::Id {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -25,23 +27,44 @@ pub const TALK_WINDOW_TARGET: &str = "#talk-window";
 pub struct TalkWindow<'a> {
     auth_user: &'a auth::User,
     talks: Rc<[TalkDto]>,
+    archived: Rc<[TalkDto]>,
     kind: talk::Kind,
+    unsubscribed: Rc<[ChannelSummary]>,
 }
 
 impl<'a> TalkWindow<'a> {
-    pub fn chats(auth_user: &'a auth::User, talks: &[TalkDto]) -> Self {
+    pub fn chats(auth_user: &'a auth::User, talks: &[TalkDto], archived: &[TalkDto]) -> Self {
         Self {
             auth_user,
             talks: talks.into(),
+            archived: archived.into(),
             kind: talk::Kind::Chat,
+            unsubscribed: Rc::new([]),
         }
     }
 
-    pub fn groups(auth_user: &'a auth::User, talks: &[TalkDto]) -> Self {
+    pub fn groups(auth_user: &'a auth::User, talks: &[TalkDto], archived: &[TalkDto]) -> Self {
         Self {
             auth_user,
             talks: talks.into(),
+            archived: archived.into(),
             kind: talk::Kind::Group,
+            unsubscribed: Rc::new([]),
+        }
+    }
+
+    pub fn channels(
+        auth_user: &'a auth::User,
+        talks: &[TalkDto],
+        archived: &[TalkDto],
+        unsubscribed: Vec<ChannelSummary>,
+    ) -> Self {
+        Self {
+            auth_user,
+            talks: talks.into(),
+            archived: archived.into(),
+            kind: talk::Kind::Channel,
+            unsubscribed: unsubscribed.into(),
         }
     }
 
@@ -58,6 +81,17 @@ impl Render for TalkWindow<'_> {
                     talk::Kind::Chat => {
                         (user::markup::Header(self.auth_user))
                         (user::markup::Search)
+                        (TalkSearch(&self.kind))
+
+                        @if !self.talks.iter().any(TalkDto::is_saved) {
+                            a #saved-messages ."text-center cursor-pointer text-blue-600 hover:underline mb-4"
+                                hx-get="/talks/saved"
+                                hx-target=(TALK_WINDOW_TARGET)
+                            {
+                                i ."fa-solid fa-bookmark mr-2" {}
+                                "Saved messages"
+                            }
+                        }
                     },
                     talk::Kind::Group => {
                         header ."text-center mb-4"{
@@ -69,38 +103,165 @@ impl Render for TalkWindow<'_> {
                             ."py-2 px-4 mb-4"
                             hx-get="/templates/talks/group/create"
                             hx-target=(TALK_WINDOW_TARGET) { "Create group" }
+
+                        (TalkSearch(&self.kind))
                     },
+                    talk::Kind::Channel => {
+                        header ."text-center mb-4"{
+                            h2.text-2xl { "Channels" }
+                        }
+
+                        a ."text-center text-white font-bold cursor-pointer"
+                            ."bg-blue-500 hover:bg-blue-400 rounded"
+                            ."py-2 px-4 mb-4"
+                            hx-get="/templates/talks/channel/create"
+                            hx-target=(TALK_WINDOW_TARGET) { "Create channel" }
+                    },
+                }
+
+                (TalkList::new(self.get_talks(), &self.kind))
+
+                @if !self.archived.is_empty() {
+                    details #archived-talks ."mt-4" {
+                        summary ."cursor-pointer text-gray-500 mb-2" {
+                            "Archived (" (self.archived.len()) ")"
+                        }
+                        div ."flex flex-col space-y-2" {
+                            @for talk in self.archived.iter() {
+                                (talk)
+                            }
+                        }
+                    }
                 }
 
-                (TalkList::new(self.get_talks()))
+                @if !self.unsubscribed.is_empty() {
+                    div #discover-channels ."mt-4" {
+                        h3 ."text-gray-500 mb-2" { "Discover" }
+                        div ."flex flex-col space-y-2" {
+                            @for c in self.unsubscribed.iter() {
+                                div ."px-3 py-2 rounded-md bg-gray-100 flex items-center" {
+                                    img ."w-8 h-8 rounded-full"
+                                        src=(c.picture().as_str())
+                                        alt="Talk avatar" {}
+                                    span ."font-bold mx-2" { (c.name()) }
+                                    span ."flex-grow text-sm text-gray-500" {
+                                        (c.subscribers()) " subscribers"
+                                    }
+                                    button ."text-white px-3 py-1 rounded-md bg-blue-600 hover:bg-blue-700"
+                                        hx-post={"/api/talks/" (c.id()) "/subscribe"}
+                                        hx-target=(TALK_WINDOW_TARGET)
+                                        hx-disabled-elt="this" { "Subscribe" }
+                                }
+                            }
+                        }
+                    }
+                }
             }
         }
     }
 }
 
-struct TalkList(Rc<[TalkDto]>);
+struct TalkList<'a>(TalkPage<'a>);
 
-impl TalkList {
-    fn new(talks: &[TalkDto]) -> Self {
-        Self(talks.into())
-    }
-
-    fn get_talks(&self) -> &[TalkDto] {
-        &self.0
+impl<'a> TalkList<'a> {
+    const fn new(talks: &'a [TalkDto], kind: &'a Kind) -> Self {
+        Self(TalkPage::new(talks, kind))
     }
 }
 
-impl Render for TalkList {
+impl Render for TalkList<'_> {
     fn render(&self) -> Markup {
         html! {
             div #talk-list ."flex flex-col space-y-2 h-full overflow-y-auto"
                 sse-swap="newTalk"
-                hx-swap="beforeend"
+                hx-swap="afterbegin"
                 hx-target="#talk-list"
             {
-                @for talk in self.get_talks() {
-                    (talk)
-                }
+                (self.0)
+            }
+        }
+    }
+}
+
+struct TalkSearch<'a>(&'a Kind);
+
+impl Render for TalkSearch<'_> {
+    fn render(&self) -> Markup {
+        html! {
+            input #talk-search ."mb-4 w-full px-3 py-2 border border-gray-300 rounded-md focus:outline-none"
+                type="search"
+                name="query"
+                placeholder="Filter talks..."
+                autocomplete="off"
+                hx-post="/api/talks/search"
+                hx-vals=(format!(r#"{{"kind": "{}"}}"#, self.0.as_str()))
+                hx-trigger="input changed delay:500ms, search"
+                hx-target="#talk-list"
+                hx-swap="innerHTML" {}
+        }
+    }
+}
+
+/// One page of a talk list, followed by a loader of the next one when the page is full.
+pub struct TalkPage<'a> {
+    talks: &'a [TalkDto],
+    kind: &'a Kind,
+    searched: bool,
+}
+
+impl<'a> TalkPage<'a> {
+    pub const fn new(talks: &'a [TalkDto], kind: &'a Kind) -> Self {
+        Self {
+            talks,
+            kind,
+            searched: false,
+        }
+    }
+
+    /// Search results are never paginated.
+    pub const fn search_result(talks: &'a [TalkDto], kind: &'a Kind) -> Self {
+        Self {
+            talks,
+            kind,
+            searched: true,
+        }
+    }
+
+    fn next_page(&self) -> Option<String> {
+        if self.searched {
+            return None;
+        }
+
+        // saved messages are an extra on top of the first page
+        let paged: Vec<&TalkDto> = self.talks.iter().filter(|t| !t.is_saved()).collect();
+        if (paged.len() as i64) < Page::SIZE {
+            return None;
+        }
+
+        paged.last().map(|t| {
+            format!(
+                "/api/talks?kind={}&cursor={}",
+                self.kind.as_str(),
+                Cursor::from(*t)
+            )
+        })
+    }
+}
+
+impl Render for TalkPage<'_> {
+    fn render(&self) -> Markup {
+        html! {
+            @for talk in self.talks {
+                (talk)
+            }
+            @if self.searched && self.talks.is_empty() {
+                div ."text-center text-gray-500 py-2" { "No talks found" }
+            }
+            @if let Some(next) = self.next_page() {
+                div .talk-list-next
+                    hx-get=(next)
+                    hx-trigger="intersect once"
+                    hx-swap="outerHTML" {}
             }
         }
     }
@@ -113,6 +274,7 @@ impl Render for Header<'_> {
         let back_url = match self.0.details() {
             DetailsDto::Chat { .. } => "/tabs/chats",
             DetailsDto::Group { .. } => "/tabs/groups",
+            DetailsDto::Channel { .. } => "/tabs/channels",
         };
 
         html! {
@@ -122,11 +284,24 @@ impl Render for Header<'_> {
                     hx-target="#tabs"
                     hx-swap="innerHTML" { "X" }
                 ."flex text-2xl" {
-                    @if let DetailsDto::Chat{ recipient, .. } = &self.0.details() {
+                    @if self.0.is_saved() {
+                        i ."fa-solid fa-bookmark text-blue-600 mr-2" {}
+                    } @else if let DetailsDto::Chat{ recipient, .. } = &self.0.details() {
                         (user::model::OnlineStatus::from_ref(recipient, false))
                     }
 
-                    (self.0.name())
+                    span #(name_id(self.0.id())) { (self.0.name()) }
+
+                    @if let DetailsDto::Chat{ recipient, .. } = &self.0.details()
+                        && !self.0.is_saved()
+                    {
+                        (user::markup::LastSeenLoader(recipient))
+                    }
+                }
+                @if !self.0.is_saved() {
+                    div .hidden
+                        sse-swap={"talkUpdated:"(self.0.id())}
+                        hx-swap="none" {}
                 }
                 (Icon::TalkControls)
             }
@@ -141,6 +316,11 @@ impl Render for ActiveTalk<'_> {
         html! {
             (Header(self.1))
 
+            div #(PINNED_MESSAGE_ID)
+                hx-get={ "/templates/talks/" (self.1.id()) "/pinned-message" }
+                hx-trigger="load"
+                hx-swap="outerHTML" {}
+
             #active-talk ."flex-grow overflow-auto mt-4 mb-4"
                 hx-ext="ws"
                 ws-connect={ "/ws/" (self.1.id()) }
@@ -151,18 +331,68 @@ impl Render for ActiveTalk<'_> {
                     hx-target=(MESSAGE_LIST_TARGET) {}
             }
 
-            (message::markup::InputBlank(self.1.id()))
+            @if can_post(self.1) {
+                (message::markup::InputBlank(self.1.id(), self.0.settings()))
+            } @else if self.1.is_blocked() {
+                div #blocked-banner ."text-center text-gray-500 bg-gray-100 rounded-md py-2" {
+                    i ."fa-solid fa-ban mr-2" {}
+                    "This chat is read-only because the contact is blocked"
+                }
+            } @else {
+                div ."text-center text-gray-500 py-2" { "Only admins can post to this channel" }
+            }
             (TalkControls(&self.0, self.1))
 
-            div .hidden
-                hx-trigger="msg:afterUpdate from:body"
-                hx-target=(MESSAGE_INPUT_TARGET)
-                hx-swap="outerHTML"
-                hx-get={"/templates/messages/input/blank?talk_id=" (self.1.id())} {}
+            @if can_post(self.1) {
+                div .hidden
+                    hx-trigger="msg:afterUpdate from:body"
+                    hx-target=(MESSAGE_INPUT_TARGET)
+                    hx-swap="outerHTML"
+                    hx-get={"/templates/messages/input/blank?talk_id=" (self.1.id())} {}
+            }
+        }
+    }
+}
+
+const PINNED_MESSAGE_ID: &str = "pinned-message";
+pub const PINNED_MESSAGE_TARGET: &str = "#pinned-message";
+
+/// Message pinned above the message list. Rendered hidden when nothing is pinned,
+/// so that pinning later can still swap it by id.
+pub struct PinnedMessage<'a> {
+    pub talk_id: &'a talk::Id,
+    pub msg: Option<&'a MessageDto>,
+}
+
+impl Render for PinnedMessage<'_> {
+    fn render(&self) -> Markup {
+        html! {
+            @if let Some(msg) = self.msg {
+                div #(PINNED_MESSAGE_ID)
+                    ."flex items-center bg-gray-100 rounded-md px-3 py-2 mt-2 text-sm" {
+                    i ."fa-solid fa-thumbtack text-blue-600 mr-2" {}
+                    span ."flex-grow truncate" { (msg.text()) }
+                    i ."fa-solid fa-xmark text-gray-500 ml-2 cursor-pointer"
+                        title="Unpin"
+                        hx-delete={ "/api/talks/" (self.talk_id) "/pinned-message" }
+                        hx-target=(PINNED_MESSAGE_TARGET)
+                        hx-swap="outerHTML" {}
+                }
+            } @else {
+                div #(PINNED_MESSAGE_ID) .hidden {}
+            }
         }
     }
 }
 
+fn can_post(talk: &TalkDto) -> bool {
+    match talk.details() {
+        DetailsDto::Chat { .. } => !talk.is_blocked(),
+        DetailsDto::Group { .. } => true,
+        DetailsDto::Channel { role, .. } => role.permits(&Action::Post),
+    }
+}
+
 const TALK_CONTROLS_ID: &str = "talk-controls";
 pub const TALK_CONTROLS_TARGET: &str = "#talk-controls";
 
@@ -172,10 +402,29 @@ impl Render for TalkControls<'_> {
     fn render(&self) -> Markup {
         let controls_item_class = "text-lg py-3 cursor-pointer hover:bg-gray-300";
 
-        let can_delete = match self.1.details() {
-            DetailsDto::Chat { .. } => true,
-            DetailsDto::Group { owner, .. } => owner.eq(self.0.id()),
+        let (is_group, can_delete, is_owner) = match self.1.details() {
+            DetailsDto::Chat { .. } => (false, true, false),
+            DetailsDto::Group { owner, role, .. } | DetailsDto::Channel { owner, role, .. } => {
+                (true, role.permits(&Action::Delete), owner.eq(self.0.id()))
+            }
+        };
+        let is_channel = matches!(self.1.details(), DetailsDto::Channel { .. });
+        let (can_edit, can_invite) = match self.1.details() {
+            DetailsDto::Chat { .. } => (false, false),
+            DetailsDto::Group { role, .. } | DetailsDto::Channel { role, .. } => (
+                role.permits(&Action::Rename) || role.permits(&Action::ChangeAvatar),
+                role.permits(&Action::ManageMembers),
+            ),
+        };
+
+        let prefs_url = |p: &str| {
+            format!(
+                "/api/talks/{}/{p}?kind={}",
+                self.1.id(),
+                self.1.kind().as_str()
+            )
         };
+        let prefs = self.1.preferences();
 
         html! {
             div #(TALK_CONTROLS_ID) ."flex flex-row h-full w-full absolute top-0 left-0 invisible" {
@@ -184,10 +433,82 @@ impl Render for TalkControls<'_> {
 
                 div ."flex flex-col bg-white h-full w-1/3 py-4 text-center" {
                     div ."text-2xl py-3" { "Settings" }
+                    @if is_group {
+                        div .(controls_item_class)
+                            hx-get={"/templates/talks/" (self.1.id()) "/members"}
+                            hx-target=(TALK_WINDOW_TARGET) {
+                                @if is_channel { "Subscribers" } @else { "Members" }
+                            }
+                    }
+                    @if can_invite {
+                        div .(controls_item_class)
+                            hx-get={"/templates/talks/" (self.1.id()) "/invites"}
+                            hx-target=(TALK_WINDOW_TARGET) { "Invite links" }
+                    }
+                    @if can_edit {
+                        div .(controls_item_class)
+                            hx-get={"/templates/talks/" (self.1.id()) "/edit"}
+                            hx-target=(TALK_WINDOW_TARGET) {
+                                @if is_channel { "Edit channel" } @else { "Edit group" }
+                            }
+                    }
+                    @if prefs.pinned() {
+                        div .(controls_item_class)
+                            hx-delete=(prefs_url("pin"))
+                            hx-target=(TALK_WINDOW_TARGET) { "Unpin" }
+                    } @else {
+                        div .(controls_item_class)
+                            hx-put=(prefs_url("pin"))
+                            hx-target=(TALK_WINDOW_TARGET) { "Pin to top" }
+                    }
+                    @if let Some(until) = prefs.muted_until() {
+                        div .(controls_item_class)
+                            hx-delete=(prefs_url("mute"))
+                            hx-target=(TALK_WINDOW_TARGET)
+                        {
+                            "Unmute"
+                            // muting forever is stored as the max timestamp
+                            @if until.year() < 9999 {
+                                div ."text-xs text-gray-500" {
+                                    "muted until " (until.format("%d.%m.%Y %H:%M"))
+                                }
+                            }
+                        }
+                    } @else {
+                        div ."text-lg py-3" {
+                            "Mute"
+                            div ."flex justify-center space-x-3 text-sm" {
+                                @for (hours, label) in [(1, "1h"), (8, "8h"), (168, "7d"), (0, "forever")] {
+                                    a ."cursor-pointer text-blue-600 hover:underline"
+                                        hx-put=(prefs_url("mute"))
+                                        hx-vals={"{\"hours\": " (hours) "}"}
+                                        hx-target=(TALK_WINDOW_TARGET) { (label) }
+                                }
+                            }
+                        }
+                    }
+                    @if prefs.archived() {
+                        div .(controls_item_class)
+                            hx-delete=(prefs_url("archive"))
+                            hx-target=(TALK_WINDOW_TARGET) { "Unarchive" }
+                    } @else {
+                        div .(controls_item_class)
+                            hx-put=(prefs_url("archive"))
+                            hx-target=(TALK_WINDOW_TARGET) { "Archive" }
+                    }
                     @if can_delete {
                         div .(controls_item_class)
                             hx-delete={"/api/talks/" (self.1.id())} { "Delete talk" }
                     }
+                    @if is_channel && !is_owner {
+                        div .(controls_item_class)
+                            hx-post={"/api/talks/" (self.1.id()) "/leave"}
+                            hx-confirm="Unsubscribe from this channel?" { "Unsubscribe" }
+                    } @else if is_group && !is_owner {
+                        div .(controls_item_class)
+                            hx-post={"/api/talks/" (self.1.id()) "/leave"}
+                            hx-confirm="Leave this group?" { "Leave group" }
+                    }
                 }
             }
         }
@@ -206,10 +527,7 @@ impl crate::markup::IdExt for talk::Id {
 
 impl Render for TalkDto {
     fn render(&self) -> Markup {
-        let kind = match self.details() {
-            DetailsDto::Chat { .. } => Kind::Chat,
-            DetailsDto::Group { .. } => Kind::Group,
-        };
+        let kind = self.kind();
 
         html! {
             div #(self.id().attr())
@@ -217,23 +535,48 @@ impl Render for TalkDto {
                 hx-get={"/talks/" (self.id()) "?kind=" (kind.as_str())}
                 hx-target=(TALK_WINDOW_TARGET)
                 hx-swap="innerHTML"
+                data-pinned[self.preferences().pinned()]
+                data-saved[self.is_saved()]
             {
-                @if let DetailsDto::Chat{recipient, ..} = &self.details() {
-                    (user::model::OnlineStatus::from_ref(recipient, false))
+                div .hidden
+                    sse-swap={"talkRemoved:"(self.id())}
+                    hx-target=(self.id().target())
+                    hx-swap="delete" {}
+
+                @match &self.details() {
+                    DetailsDto::Chat { .. } if self.is_saved() => {
+                        i ."fa-solid fa-bookmark text-blue-600 mr-2" {}
+                    },
+                    DetailsDto::Chat{recipient, ..} => {
+                        (user::model::OnlineStatus::from_ref(recipient, false))
+                    },
+                    DetailsDto::Group { .. } | DetailsDto::Channel { .. } => {},
+                }
+                @if !self.is_saved() {
+                    div .hidden
+                        sse-swap={"talkUpdated:"(self.id())}
+                        hx-swap="none" {}
                 }
-                img ."w-8 h-8 rounded-full"
-                    src=(self.picture().as_str()) alt="Talk avatar" {}
+                (TalkAvatar::new(self.id(), self.picture(), "w-8 h-8"))
+
+                span #(name_id(self.id())) ."talk-recipient font-bold mx-2" { (self.name()) }
 
-                span ."talk-recipient font-bold mx-2" { (self.name()) }
+                @if self.preferences().pinned() {
+                    i ."fa-solid fa-thumbtack text-gray-400 mr-1" title="Pinned" {}
+                }
+                @if self.preferences().is_muted() {
+                    i ."fa-solid fa-bell-slash text-gray-400 mr-1" title="Muted" {}
+                }
 
                 div ."flex-grow text-right truncate"
-                    sse-swap={"newMessage:"(self.id())}
+                    sse-swap={"newMessage:"(self.id())",mutedMessage:"(self.id())}
                     hx-target={"#lm-"(self.id())}
                 {
                     ({
                         let sender = match &self.details() {
                             DetailsDto::Chat { sender, .. }
-                            | DetailsDto::Group { sender, .. } => sender,
+                            | DetailsDto::Group { sender, .. }
+                            | DetailsDto::Channel { sender, .. } => sender,
                         };
 
                         message::markup::last_message(self.last_message(), self.id(), Some(sender))
@@ -298,6 +641,380 @@ impl Render for CreateGroupForm<'_> {
     }
 }
 
+pub struct CreateChannelForm;
+
+impl Render for CreateChannelForm {
+    fn render(&self) -> Markup {
+        html! {
+            header ."text-center mb-4"{
+                h2.text-2xl { "Create channel" }
+            }
+
+            form ."flex flex-col"
+                hx-post="/api/talks"
+                hx-target=(TALK_WINDOW_TARGET)
+                hx-ext="json-enc" {
+                input type="hidden" name="kind" value="channel" {}
+                input ."mb-4 w-full px-3 py-2 border border-gray-300 rounded-md focus:outline-none"
+                    type="text" name="name" placeholder="Channel name" required {}
+                input type="submit" value="Create"
+                    ."text-white px-4 py-2 rounded-md w-full"
+                    ."cursor-pointer bg-blue-600 hover:bg-blue-700"
+                    hx-disabled-elt="this" {}
+            }
+        }
+    }
+}
+
+fn name_id(id: &talk::Id) -> String {
+    format!("tn-{id}")
+}
+
+fn picture_id(id: &talk::Id) -> String {
+    format!("tp-{id}")
+}
+
+struct TalkAvatar<'a> {
+    id: &'a talk::Id,
+    picture: &'a talk::Picture,
+    size: &'a str,
+}
+
+impl<'a> TalkAvatar<'a> {
+    const fn new(id: &'a talk::Id, picture: &'a talk::Picture, size: &'a str) -> Self {
+        Self { id, picture, size }
+    }
+}
+
+impl Render for TalkAvatar<'_> {
+    fn render(&self) -> Markup {
+        html! {
+            span #(picture_id(self.id)) .(self.size) ."inline-block shrink-0" {
+                (avatar_img(self.picture))
+            }
+        }
+    }
+}
+
+fn avatar_img(picture: &talk::Picture) -> Markup {
+    html! {
+        img ."w-full h-full rounded-full" src=(picture.as_str()) alt="Talk avatar" {}
+    }
+}
+
+/// Out of band swaps of a talk name and avatar wherever they are rendered.
+pub fn talk_updated(id: &talk::Id, name: &str, picture: &talk::Picture) -> Markup {
+    html! {
+        span #(name_id(id)) hx-swap-oob="innerHTML" { (name) }
+        span #(picture_id(id)) hx-swap-oob="innerHTML" { (avatar_img(picture)) }
+    }
+}
+
+pub struct EditGroupForm<'a>(&'a TalkDto);
+
+impl<'a> EditGroupForm<'a> {
+    pub const fn new(talk: &'a TalkDto) -> Self {
+        Self(talk)
+    }
+}
+
+impl Render for EditGroupForm<'_> {
+    fn render(&self) -> Markup {
+        let t_id = self.0.id();
+        let submit_class =
+            "text-white px-4 py-2 rounded-md w-full cursor-pointer bg-blue-600 hover:bg-blue-700";
+
+        html! {
+            header #edit-group-header ."flex items-center mb-4" {
+                a ."cursor-pointer border-2 border-red-500 text-red-500 px-4 py-2 rounded-2xl mr-4"
+                    hx-get={"/talks/" (t_id) "?kind=" (self.0.kind().as_str())}
+                    hx-target=(TALK_WINDOW_TARGET)
+                    hx-swap="innerHTML" { "X" }
+                h2 .text-2xl {
+                    @if self.0.kind().eq(&Kind::Channel) { "Edit channel" } @else { "Edit group" }
+                }
+            }
+
+            form ."flex flex-col mb-4"
+                hx-put={"/api/talks/" (t_id) "/name"}
+                hx-target=(TALK_WINDOW_TARGET)
+            {
+                input ."px-4 py-2 mb-2 border border-gray-300 rounded-md"
+                    type="text"
+                    name="name"
+                    value=(self.0.name())
+                    required {}
+                input type="submit" value="Rename" .(submit_class) hx-disabled-elt="this" {}
+            }
+
+            div ."flex flex-col items-center" {
+                (TalkAvatar::new(t_id, self.0.picture(), "w-24 h-24 mb-2"))
+
+                form ."flex flex-col w-full mb-2"
+                    hx-post={"/api/talks/" (t_id) "/avatar"}
+                    hx-encoding="multipart/form-data"
+                    hx-target=(TALK_WINDOW_TARGET)
+                {
+                    input ."mb-2" type="file" name="avatar"
+                        accept="image/png,image/jpeg,image/gif,image/webp"
+                        required {}
+                    input type="submit" value="Upload avatar" .(submit_class) hx-disabled-elt="this" {}
+                }
+
+                button ."text-red-500 px-4 py-2 rounded-md w-full border border-red-500 hover:bg-red-100"
+                    hx-delete={"/api/talks/" (t_id) "/avatar"}
+                    hx-target=(TALK_WINDOW_TARGET)
+                    hx-confirm="Reset avatar to identicon?" { "Reset to identicon" }
+            }
+        }
+    }
+}
+
+pub struct GroupInvites<'a> {
+    talk: &'a TalkDto,
+    invites: &'a [Invite],
+    now: DateTime<Utc>,
+}
+
+impl<'a> GroupInvites<'a> {
+    pub const fn new(talk: &'a TalkDto, invites: &'a [Invite], now: DateTime<Utc>) -> Self {
+        Self { talk, invites, now }
+    }
+}
+
+impl Render for GroupInvites<'_> {
+    fn render(&self) -> Markup {
+        let t_id = self.talk.id();
+        let select_class = "px-4 py-2 mb-2 border border-gray-300 rounded-md";
+
+        html! {
+            header #group-invites-header ."flex items-center mb-4" {
+                a ."cursor-pointer border-2 border-red-500 text-red-500 px-4 py-2 rounded-2xl mr-4"
+                    hx-get={"/talks/" (t_id) "?kind=" (self.talk.kind().as_str())}
+                    hx-target=(TALK_WINDOW_TARGET)
+                    hx-swap="innerHTML" { "X" }
+                h2 .text-2xl { "Invite links" }
+            }
+
+            form ."flex flex-col mb-4"
+                hx-post={"/api/talks/" (t_id) "/invites"}
+                hx-target=(TALK_WINDOW_TARGET)
+            {
+                label for="expires_in" { "Expires" }
+                select #expires_in .(select_class) name="expires_in" {
+                    option value="0" { "Never" }
+                    option value="1" { "In 1 hour" }
+                    option value="24" selected { "In 1 day" }
+                    option value="168" { "In 7 days" }
+                }
+                label for="max_uses" { "Max uses" }
+                select #max_uses .(select_class) name="max_uses" {
+                    option value="0" selected { "Unlimited" }
+                    option value="1" { "1" }
+                    option value="5" { "5" }
+                    option value="10" { "10" }
+                    option value="50" { "50" }
+                }
+                input type="submit" value="Create link"
+                    ."text-white px-4 py-2 rounded-md w-full"
+                    ."cursor-pointer bg-blue-600 hover:bg-blue-700"
+                    hx-disabled-elt="this" {}
+            }
+
+            ul #group-invites ."flex flex-col space-y-2" {
+                @for i in self.invites {
+                    @let active = i.is_active(&self.now);
+                    li ."flex items-center justify-between px-3 py-2 rounded-md bg-gray-100"
+                        .text-gray-400[!active]
+                    {
+                        div ."flex flex-col truncate" {
+                            a ."font-bold truncate" href={"/join/" (i.token())} { "/join/" (i.token()) }
+                            span ."text-xs" {
+                                @match i.expires_at() {
+                                    Some(e) if active => { "expires " (e.format("%d.%m.%Y %H:%M")) }
+                                    Some(_) => "expired",
+                                    None => "never expires",
+                                }
+                                " · "
+                                @match i.max_uses() {
+                                    Some(m) => { (i.uses()) "/" (m) " uses" }
+                                    None => { (i.uses()) " uses" }
+                                }
+                            }
+                        }
+                        i ."fa-solid fa-trash-can text-red-500 cursor-pointer ml-3"
+                            title="Revoke"
+                            hx-delete={"/api/talks/" (t_id) "/invites/" (i.token())}
+                            hx-target="closest li"
+                            hx-swap="outerHTML" {}
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub enum JoinGroup<'a> {
+    Invite {
+        token: &'a str,
+        picture: &'a talk::Picture,
+        name: &'a str,
+    },
+    Unavailable,
+}
+
+impl Render for JoinGroup<'_> {
+    fn render(&self) -> Markup {
+        html! {
+            div ."h-full flex flex-col justify-center items-center" {
+                @match self {
+                    Self::Invite { token, picture, name } => {
+                        img ."w-24 h-24 rounded-full mb-4"
+                            src=(picture.as_str())
+                            alt="Talk avatar" {}
+                        p ."text-gray-500" { "You are invited to join" }
+                        h2 ."text-2xl font-bold mb-4" { (name) }
+                        button ."bg-blue-500 hover:bg-blue-400 text-white font-bold py-2 px-4 border-b-4 border-blue-700 hover:border-blue-500 rounded"
+                            hx-post={"/join/" (token)} { "Join group" }
+                    },
+                    Self::Unavailable => {
+                        p ."text-2xl mb-4" { "This invite link is invalid or expired" }
+                        a ."text-blue-500 hover:underline" href="/" { "Back to messenger" }
+                    },
+                }
+                #errors {}
+            }
+        }
+    }
+}
+
+pub struct GroupMembers<'a> {
+    auth_user: &'a auth::User,
+    talk: &'a TalkDto,
+    members: &'a [GroupMemberDto],
+    candidates: &'a [GroupMemberDto],
+}
+
+impl<'a> GroupMembers<'a> {
+    pub const fn new(
+        auth_user: &'a auth::User,
+        talk: &'a TalkDto,
+        members: &'a [GroupMemberDto],
+        candidates: &'a [GroupMemberDto],
+    ) -> Self {
+        Self {
+            auth_user,
+            talk,
+            members,
+            candidates,
+        }
+    }
+}
+
+impl Render for GroupMembers<'_> {
+    fn render(&self) -> Markup {
+        let t_id = self.talk.id();
+        let role = match self.talk.details() {
+            DetailsDto::Group { role, .. } | DetailsDto::Channel { role, .. } => role,
+            DetailsDto::Chat { .. } => &Role::Member,
+        };
+        let can_manage_members = role.permits(&Action::ManageMembers);
+        let can_manage_roles = role.permits(&Action::ManageRoles);
+        let can_transfer = role.permits(&Action::TransferOwnership);
+        let icon_class = "cursor-pointer ml-3";
+
+        html! {
+            header #group-members-header ."flex items-center mb-4" {
+                a ."cursor-pointer border-2 border-red-500 text-red-500 px-4 py-2 rounded-2xl mr-4"
+                    hx-get={"/talks/" (t_id) "?kind=" (self.talk.kind().as_str())}
+                    hx-target=(TALK_WINDOW_TARGET)
+                    hx-swap="innerHTML" { "X" }
+                h2 .text-2xl { (self.talk.name()) }
+            }
+
+            ul #group-members ."flex flex-col space-y-2 mb-4" {
+                @for m in self.members {
+                    @let m_role = m.role().unwrap_or(&Role::Member);
+                    @let outranks = m.user_id().ne(self.auth_user.id()) && role.outranks(m_role);
+                    li ."flex items-center justify-between px-3 py-2 rounded-md bg-gray-100" {
+                        div ."member-details flex items-center" {
+                            img class="w-9 h-9 rounded-full float-left mr-2"
+                                src=(m.picture())
+                                alt="User avatar" {}
+                            span ."font-bold mx-2" { (m.name()) }
+                            @if m_role.ne(&Role::Member) {
+                                span ."text-xs text-gray-500" { (m_role.as_str()) }
+                            }
+                        }
+                        div ."member-actions flex items-center" {
+                            @if can_manage_roles && outranks {
+                                @if m_role.eq(&Role::Admin) {
+                                    i ."fa-solid fa-user-shield text-gray-500" .(icon_class)
+                                        title="Revoke admin"
+                                        hx-put={"/api/talks/" (t_id) "/members/" (m.user_id()) "/role"}
+                                        hx-vals=r#"{"role": "member"}"#
+                                        hx-target=(TALK_WINDOW_TARGET) {}
+                                } @else {
+                                    i ."fa-solid fa-user-shield text-blue-500" .(icon_class)
+                                        title="Make admin"
+                                        hx-put={"/api/talks/" (t_id) "/members/" (m.user_id()) "/role"}
+                                        hx-vals=r#"{"role": "admin"}"#
+                                        hx-target=(TALK_WINDOW_TARGET) {}
+                                }
+                            }
+                            @if can_transfer && outranks {
+                                i ."fa-solid fa-crown text-yellow-500" .(icon_class)
+                                    title="Transfer ownership"
+                                    hx-put={"/api/talks/" (t_id) "/owner"}
+                                    hx-vals={"{\"user_id\": \"" (m.user_id()) "\"}"}
+                                    hx-confirm={"Transfer ownership to " (m.name()) "?"}
+                                    hx-target=(TALK_WINDOW_TARGET) {}
+                            }
+                            @if can_manage_members && outranks {
+                                i ."fa-solid fa-user-minus text-red-500" .(icon_class)
+                                    title="Remove member"
+                                    hx-delete={"/api/talks/" (t_id) "/members/" (m.user_id())}
+                                    hx-target="closest li"
+                                    hx-swap="outerHTML" {}
+                            }
+                        }
+                    }
+                }
+            }
+
+            @if can_manage_members && !self.candidates.is_empty() {
+                form ."flex flex-col"
+                    hx-post={"/api/talks/" (t_id) "/members"}
+                    hx-target=(TALK_WINDOW_TARGET)
+                    hx-ext="json-enc" {
+                    fieldset ."sticky flex flex-col overflow-auto"
+                        ."space-y-2 border border-gray-300 rounded-md px-3 pb-3 mb-4"
+                    {
+                        legend { "Add members" }
+                        @for c in self.candidates {
+                            label ."flex items-center justify-between px-3 py-2"
+                                ."rounded-md bg-gray-100 hover:bg-gray-200 cursor-pointer"
+                            {
+                                div ."member-details flex items-center" {
+                                    img class="w-9 h-9 rounded-full float-left mr-2"
+                                        src=(c.picture())
+                                        alt="User avatar" {}
+                                    span ."font-bold mx-2" { (c.name()) }
+                                }
+                                input type="checkbox" name="members" value=(c.user_id()) {}
+                            }
+                        }
+                    }
+                    input type="submit" value="Add"
+                        ."text-white px-4 py-2 rounded-md w-full"
+                        ."cursor-pointer bg-blue-600 hover:bg-blue-700"
+                        hx-disabled-elt="this" {}
+                }
+            }
+        }
+    }
+}
+
 pub enum Icon {
     TalkControls,
     Unseen,
@@ -317,3 +1034,252 @@ impl Render for Icon {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use chrono::Utc;
+    use maud::Render;
+    use uuid::Uuid;
+
+    use super::{EditGroupForm, GroupInvites, GroupMembers, PinnedMessage, TalkControls};
+    use crate::message::model::MessageDto;
+    use crate::talk::handler::templates::GroupMemberDto;
+    use crate::talk::model::{DetailsDto, TalkDto};
+    use crate::talk::{Id, Picture, Role};
+    use crate::{auth, user};
+
+    fn user_id(n: u128) -> user::Id {
+        user::Id::from(Uuid::from_u128(n))
+    }
+
+    fn auth_user(id: user::Id) -> auth::User {
+        auth::User::new(
+            id,
+            user::Sub::new("idp|ion"),
+            user::Nickname::from("ion"),
+            "Ion",
+            user::Picture::from(String::from("https://idp.example/ion.png")),
+        )
+    }
+
+    fn talk(details: DetailsDto) -> TalkDto {
+        TalkDto::new(
+            Id::from(Uuid::from_u128(9)),
+            Picture::from(String::from("/static/avatars/team.png")),
+            "Team",
+            details,
+            None,
+        )
+    }
+
+    #[test]
+    fn should_render_group_members() {
+        let owner = auth_user(user_id(1));
+        let group = talk(DetailsDto::Group {
+            owner: user_id(1),
+            sender: user_id(1),
+            role: Role::Owner,
+        });
+        let members = [
+            GroupMemberDto::new(user_id(1), "Ion", "https://idp.example/ion.png")
+                .with_role(Role::Owner),
+            GroupMemberDto::new(user_id(2), "Ana", "https://idp.example/ana.png"),
+        ];
+        let candidates = [GroupMemberDto::new(
+            user_id(3),
+            "Vlad",
+            "https://idp.example/vlad.png",
+        )];
+        let expected = concat!(
+            r#"<header class="flex items-center mb-4" id="group-members-header">"#,
+            r##"<a class="cursor-pointer border-2 border-red-500 text-red-500 px-4 py-2 rounded-2xl mr-4" hx-get="/talks/00000000-0000-0000-0000-000000000009?kind=group" hx-target="#talk-window" hx-swap="innerHTML">X</a>"##,
+            r#"<h2 class="text-2xl">Team</h2>"#,
+            "</header>",
+            r#"<ul class="flex flex-col space-y-2 mb-4" id="group-members">"#,
+            r#"<li class="flex items-center justify-between px-3 py-2 rounded-md bg-gray-100">"#,
+            r#"<div class="member-details flex items-center">"#,
+            r#"<img class="w-9 h-9 rounded-full float-left mr-2" src="https://idp.example/ion.png" alt="User avatar"></img>"#,
+            r#"<span class="font-bold mx-2">Ion</span>"#,
+            r#"<span class="text-xs text-gray-500">owner</span>"#,
+            "</div>",
+            r#"<div class="member-actions flex items-center"></div>"#,
+            "</li>",
+            r#"<li class="flex items-center justify-between px-3 py-2 rounded-md bg-gray-100">"#,
+            r#"<div class="member-details flex items-center">"#,
+            r#"<img class="w-9 h-9 rounded-full float-left mr-2" src="https://idp.example/ana.png" alt="User avatar"></img>"#,
+            r#"<span class="font-bold mx-2">Ana</span>"#,
+            "</div>",
+            r#"<div class="member-actions flex items-center">"#,
+            r##"<i class="fa-solid fa-user-shield text-blue-500 cursor-pointer ml-3" title="Make admin" hx-put="/api/talks/00000000-0000-0000-0000-000000000009/members/00000000-0000-0000-0000-000000000002/role" hx-vals="{&quot;role&quot;: &quot;admin&quot;}" hx-target="#talk-window"></i>"##,
+            r##"<i class="fa-solid fa-crown text-yellow-500 cursor-pointer ml-3" title="Transfer ownership" hx-put="/api/talks/00000000-0000-0000-0000-000000000009/owner" hx-vals="{&quot;user_id&quot;: &quot;00000000-0000-0000-0000-000000000002&quot;}" hx-confirm="Transfer ownership to Ana?" hx-target="#talk-window"></i>"##,
+            r#"<i class="fa-solid fa-user-minus text-red-500 cursor-pointer ml-3" title="Remove member" hx-delete="/api/talks/00000000-0000-0000-0000-000000000009/members/00000000-0000-0000-0000-000000000002" hx-target="closest li" hx-swap="outerHTML"></i>"#,
+            "</div>",
+            "</li>",
+            "</ul>",
+            r##"<form class="flex flex-col" hx-post="/api/talks/00000000-0000-0000-0000-000000000009/members" hx-target="#talk-window" hx-ext="json-enc">"##,
+            r#"<fieldset class="sticky flex flex-col overflow-auto space-y-2 border border-gray-300 rounded-md px-3 pb-3 mb-4">"#,
+            "<legend>Add members</legend>",
+            r#"<label class="flex items-center justify-between px-3 py-2 rounded-md bg-gray-100 hover:bg-gray-200 cursor-pointer">"#,
+            r#"<div class="member-details flex items-center">"#,
+            r#"<img class="w-9 h-9 rounded-full float-left mr-2" src="https://idp.example/vlad.png" alt="User avatar"></img>"#,
+            r#"<span class="font-bold mx-2">Vlad</span>"#,
+            "</div>",
+            r#"<input type="checkbox" name="members" value="00000000-0000-0000-0000-000000000003"></input>"#,
+            "</label>",
+            "</fieldset>",
+            r#"<input class="text-white px-4 py-2 rounded-md w-full cursor-pointer bg-blue-600 hover:bg-blue-700" type="submit" value="Add" hx-disabled-elt="this"></input>"#,
+            "</form>",
+        );
+
+        let actual = GroupMembers::new(&owner, &group, &members, &candidates)
+            .render()
+            .into_string();
+
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn should_render_pinned_message() {
+        let talk_id = Id::from(Uuid::from_u128(9));
+        let msg = MessageDto::new(talk_id.clone(), user_id(1), "Meeting at noon");
+        let expected = concat!(
+            r#"<div class="flex items-center bg-gray-100 rounded-md px-3 py-2 mt-2 text-sm" id="pinned-message">"#,
+            r#"<i class="fa-solid fa-thumbtack text-blue-600 mr-2"></i>"#,
+            r#"<span class="flex-grow truncate">Meeting at noon</span>"#,
+            r##"<i class="fa-solid fa-xmark text-gray-500 ml-2 cursor-pointer" title="Unpin" hx-delete="/api/talks/00000000-0000-0000-0000-000000000009/pinned-message" hx-target="#pinned-message" hx-swap="outerHTML"></i>"##,
+            "</div>",
+        );
+
+        let actual = PinnedMessage {
+            talk_id: &talk_id,
+            msg: Some(&msg),
+        }
+        .render()
+        .into_string();
+
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn should_render_hidden_pinned_message_when_nothing_is_pinned() {
+        let talk_id = Id::from(Uuid::from_u128(9));
+        let expected = r#"<div class="hidden" id="pinned-message"></div>"#;
+
+        let actual = PinnedMessage {
+            talk_id: &talk_id,
+            msg: None,
+        }
+        .render()
+        .into_string();
+
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn should_render_edit_group_form() {
+        let group = talk(DetailsDto::Group {
+            owner: user_id(1),
+            sender: user_id(1),
+            role: Role::Owner,
+        });
+        let expected = concat!(
+            r#"<header class="flex items-center mb-4" id="edit-group-header">"#,
+            r##"<a class="cursor-pointer border-2 border-red-500 text-red-500 px-4 py-2 rounded-2xl mr-4" hx-get="/talks/00000000-0000-0000-0000-000000000009?kind=group" hx-target="#talk-window" hx-swap="innerHTML">X</a>"##,
+            r#"<h2 class="text-2xl">Edit group</h2>"#,
+            "</header>",
+            r##"<form class="flex flex-col mb-4" hx-put="/api/talks/00000000-0000-0000-0000-000000000009/name" hx-target="#talk-window">"##,
+            r#"<input class="px-4 py-2 mb-2 border border-gray-300 rounded-md" type="text" name="name" value="Team" required></input>"#,
+            r#"<input class="text-white px-4 py-2 rounded-md w-full cursor-pointer bg-blue-600 hover:bg-blue-700" type="submit" value="Rename" hx-disabled-elt="this"></input>"#,
+            "</form>",
+            r#"<div class="flex flex-col items-center">"#,
+            r#"<span class="w-24 h-24 mb-2 inline-block shrink-0" id="tp-00000000-0000-0000-0000-000000000009">"#,
+            r#"<img class="w-full h-full rounded-full" src="/static/avatars/team.png" alt="Talk avatar"></img>"#,
+            "</span>",
+            r##"<form class="flex flex-col w-full mb-2" hx-post="/api/talks/00000000-0000-0000-0000-000000000009/avatar" hx-encoding="multipart/form-data" hx-target="#talk-window">"##,
+            r#"<input class="mb-2" type="file" name="avatar" accept="image/png,image/jpeg,image/gif,image/webp" required></input>"#,
+            r#"<input class="text-white px-4 py-2 rounded-md w-full cursor-pointer bg-blue-600 hover:bg-blue-700" type="submit" value="Upload avatar" hx-disabled-elt="this"></input>"#,
+            "</form>",
+            r##"<button class="text-red-500 px-4 py-2 rounded-md w-full border border-red-500 hover:bg-red-100" hx-delete="/api/talks/00000000-0000-0000-0000-000000000009/avatar" hx-target="#talk-window" hx-confirm="Reset avatar to identicon?">Reset to identicon</button>"##,
+            "</div>",
+        );
+
+        let actual = EditGroupForm::new(&group).render().into_string();
+
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn should_render_group_invites_without_links() {
+        let group = talk(DetailsDto::Group {
+            owner: user_id(1),
+            sender: user_id(1),
+            role: Role::Owner,
+        });
+        let expected = concat!(
+            r#"<header class="flex items-center mb-4" id="group-invites-header">"#,
+            r##"<a class="cursor-pointer border-2 border-red-500 text-red-500 px-4 py-2 rounded-2xl mr-4" hx-get="/talks/00000000-0000-0000-0000-000000000009?kind=group" hx-target="#talk-window" hx-swap="innerHTML">X</a>"##,
+            r#"<h2 class="text-2xl">Invite links</h2>"#,
+            "</header>",
+            r##"<form class="flex flex-col mb-4" hx-post="/api/talks/00000000-0000-0000-0000-000000000009/invites" hx-target="#talk-window">"##,
+            r#"<label for="expires_in">Expires</label>"#,
+            r#"<select class="px-4 py-2 mb-2 border border-gray-300 rounded-md" id="expires_in" name="expires_in">"#,
+            r#"<option value="0">Never</option>"#,
+            r#"<option value="1">In 1 hour</option>"#,
+            r#"<option value="24" selected>In 1 day</option>"#,
+            r#"<option value="168">In 7 days</option>"#,
+            "</select>",
+            r#"<label for="max_uses">Max uses</label>"#,
+            r#"<select class="px-4 py-2 mb-2 border border-gray-300 rounded-md" id="max_uses" name="max_uses">"#,
+            r#"<option value="0" selected>Unlimited</option>"#,
+            r#"<option value="1">1</option>"#,
+            r#"<option value="5">5</option>"#,
+            r#"<option value="10">10</option>"#,
+            r#"<option value="50">50</option>"#,
+            "</select>",
+            r#"<input class="text-white px-4 py-2 rounded-md w-full cursor-pointer bg-blue-600 hover:bg-blue-700" type="submit" value="Create link" hx-disabled-elt="this"></input>"#,
+            "</form>",
+            r#"<ul class="flex flex-col space-y-2" id="group-invites"></ul>"#,
+        );
+
+        let actual = GroupInvites::new(&group, &[], Utc::now())
+            .render()
+            .into_string();
+
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn should_render_talk_controls_for_channel_subscriber() {
+        let subscriber = auth_user(user_id(2));
+        let channel = talk(DetailsDto::Channel {
+            owner: user_id(1),
+            sender: user_id(2),
+            role: Role::Member,
+        });
+        let expected = concat!(
+            r#"<div class="flex flex-row h-full w-full absolute top-0 left-0 invisible" id="talk-controls">"#,
+            r#"<div class="talk-controls-overlay w-2/3 bg-gray-300 bg-opacity-50" _="on click add .invisible to #talk-controls"></div>"#,
+            r#"<div class="flex flex-col bg-white h-full w-1/3 py-4 text-center">"#,
+            r#"<div class="text-2xl py-3">Settings</div>"#,
+            r##"<div class="text-lg py-3 cursor-pointer hover:bg-gray-300" hx-get="/templates/talks/00000000-0000-0000-0000-000000000009/members" hx-target="#talk-window">Subscribers</div>"##,
+            r##"<div class="text-lg py-3 cursor-pointer hover:bg-gray-300" hx-put="/api/talks/00000000-0000-0000-0000-000000000009/pin?kind=channel" hx-target="#talk-window">Pin to top</div>"##,
+            r#"<div class="text-lg py-3">"#,
+            "Mute",
+            r#"<div class="flex justify-center space-x-3 text-sm">"#,
+            r##"<a class="cursor-pointer text-blue-600 hover:underline" hx-put="/api/talks/00000000-0000-0000-0000-000000000009/mute?kind=channel" hx-vals="{&quot;hours&quot;: 1}" hx-target="#talk-window">1h</a>"##,
+            r##"<a class="cursor-pointer text-blue-600 hover:underline" hx-put="/api/talks/00000000-0000-0000-0000-000000000009/mute?kind=channel" hx-vals="{&quot;hours&quot;: 8}" hx-target="#talk-window">8h</a>"##,
+            r##"<a class="cursor-pointer text-blue-600 hover:underline" hx-put="/api/talks/00000000-0000-0000-0000-000000000009/mute?kind=channel" hx-vals="{&quot;hours&quot;: 168}" hx-target="#talk-window">7d</a>"##,
+            r##"<a class="cursor-pointer text-blue-600 hover:underline" hx-put="/api/talks/00000000-0000-0000-0000-000000000009/mute?kind=channel" hx-vals="{&quot;hours&quot;: 0}" hx-target="#talk-window">forever</a>"##,
+            "</div>",
+            "</div>",
+            r##"<div class="text-lg py-3 cursor-pointer hover:bg-gray-300" hx-put="/api/talks/00000000-0000-0000-0000-000000000009/archive?kind=channel" hx-target="#talk-window">Archive</div>"##,
+            r#"<div class="text-lg py-3 cursor-pointer hover:bg-gray-300" hx-post="/api/talks/00000000-0000-0000-0000-000000000009/leave" hx-confirm="Unsubscribe from this channel?">Unsubscribe</div>"#,
+            "</div>",
+            "</div>",
+        );
+
+        let actual = TalkControls(&subscriber, &channel).render().into_string();
+
+        assert_eq!(expected, actual);
+    }
+}
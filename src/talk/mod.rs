@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use axum::{
     Router,
-    routing::{delete, get, post},
+    routing::{delete, get, post, put},
 };
 use diesel::{deserialize::FromSqlRow, expression::AsExpression};
 use log::error;
@@ -13,7 +13,7 @@ use serde::{Deserialize, Serialize};
 use service::TalkService;
 use uuid::Uuid;
 
-use crate::{integration, schema::sql_types, state::AppServices, user};
+use crate::{contact, integration, schema::sql_types, state::AppServices, user};
 
 mod handler;
 pub mod markup;
@@ -27,22 +27,84 @@ pub type Service = Arc<dyn TalkService + Send + Sync>;
 
 pub fn pages<S>(s: AppServices) -> Router<S> {
     Router::new()
+        .route("/talks/saved", get(handler::pages::saved))
         .route("/talks/{id}", get(handler::pages::active_talk))
+        .route(
+            "/join/{token}",
+            get(handler::pages::join).post(handler::api::join),
+        )
         .with_state(s)
 }
 
 pub fn api<S>(s: AppServices) -> Router<S> {
     Router::new()
+        .route("/talks", get(handler::api::find_all))
         .route("/talks/{id}", get(handler::api::find_one))
         .route("/talks/{id}/avatar.png", get(handler::api::find_avatar))
         .route("/talks", post(handler::api::create))
+        .route("/talks/search", post(handler::api::search))
         .route("/talks/{id}", delete(handler::api::delete))
+        .route("/talks/{id}/members", post(handler::api::add_members))
+        .route(
+            "/talks/{id}/members/{user_id}",
+            delete(handler::api::remove_member),
+        )
+        .route(
+            "/talks/{id}/members/{user_id}/role",
+            put(handler::api::set_role),
+        )
+        .route("/talks/{id}/owner", put(handler::api::transfer_ownership))
+        .route("/talks/{id}/leave", post(handler::api::leave))
+        .route("/talks/{id}/subscribe", post(handler::api::subscribe))
+        .route("/talks/{id}/name", put(handler::api::rename))
+        .route(
+            "/talks/{id}/avatar",
+            post(handler::api::upload_avatar).delete(handler::api::reset_avatar),
+        )
+        .route(
+            "/talks/{id}/archive",
+            put(handler::api::archive).delete(handler::api::unarchive),
+        )
+        .route(
+            "/talks/{id}/pin",
+            put(handler::api::pin).delete(handler::api::unpin),
+        )
+        .route(
+            "/talks/{id}/mute",
+            put(handler::api::mute).delete(handler::api::unmute),
+        )
+        .route(
+            "/talks/{id}/pinned-message",
+            put(handler::api::pin_message).delete(handler::api::unpin_message),
+        )
+        .route("/talks/{id}/invites", post(handler::api::create_invite))
+        .route(
+            "/talks/{id}/invites/{token}",
+            delete(handler::api::revoke_invite),
+        )
         .with_state(s)
 }
 
 pub fn templates<S>(s: AppServices) -> Router<S> {
     Router::new()
         .route("/talks/group/create", get(handler::templates::create_group))
+        .route(
+            "/talks/channel/create",
+            get(handler::templates::create_channel),
+        )
+        .route(
+            "/talks/{id}/members",
+            get(handler::templates::group_members),
+        )
+        .route("/talks/{id}/edit", get(handler::templates::edit_group))
+        .route(
+            "/talks/{id}/invites",
+            get(handler::templates::group_invites),
+        )
+        .route(
+            "/talks/{id}/pinned-message",
+            get(handler::templates::pinned_message),
+        )
         .with_state(s)
 }
 
@@ -62,13 +124,15 @@ impl From<Uuid> for Id {
     }
 }
 
-#[derive(Clone, Debug, Deserialize, AsExpression, FromSqlRow)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, AsExpression, FromSqlRow)]
 #[diesel(sql_type = sql_types::TalkKind)]
 pub enum Kind {
     #[serde(rename = "chat")]
     Chat,
     #[serde(rename = "group")]
     Group,
+    #[serde(rename = "channel")]
+    Channel,
 }
 
 impl AsStr for Kind {
@@ -76,6 +140,7 @@ impl AsStr for Kind {
         match self {
             Self::Chat => "chat",
             Self::Group => "group",
+            Self::Channel => "channel",
         }
     }
 }
@@ -88,12 +153,93 @@ impl TryFrom<String> for Kind {
             Ok(Self::Chat)
         } else if s.eq("group") {
             Ok(Self::Group)
+        } else if s.eq("channel") {
+            Ok(Self::Channel)
         } else {
             Err(Error::UnsupportedKind(s))
         }
     }
 }
 
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, AsExpression, FromSqlRow)]
+#[diesel(sql_type = sql_types::GroupRole)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    Owner,
+    Admin,
+    Member,
+}
+
+impl AsStr for Role {
+    fn as_str(&self) -> &str {
+        match self {
+            Self::Owner => "owner",
+            Self::Admin => "admin",
+            Self::Member => "member",
+        }
+    }
+}
+
+impl TryFrom<String> for Role {
+    type Error = Error;
+
+    fn try_from(s: String) -> std::result::Result<Self, Self::Error> {
+        match s.as_str() {
+            "owner" => Ok(Self::Owner),
+            "admin" => Ok(Self::Admin),
+            "member" => Ok(Self::Member),
+            _ => Err(Error::UnsupportedRole(s)),
+        }
+    }
+}
+
+impl Role {
+    /// Permissions per role:
+    /// - Owner -> everything
+    /// - Admin -> manage members, rename, change avatar, pin messages, post to channel
+    /// - Member -> nothing
+    pub const fn permits(&self, a: &Action) -> bool {
+        match self {
+            Self::Owner => true,
+            Self::Admin => matches!(
+                a,
+                Action::ManageMembers
+                    | Action::Rename
+                    | Action::ChangeAvatar
+                    | Action::PinMessage
+                    | Action::Post
+            ),
+            Self::Member => false,
+        }
+    }
+
+    /// Whether a member with this role may act upon a member with other role.
+    pub const fn outranks(&self, other: &Self) -> bool {
+        self.rank() > other.rank()
+    }
+
+    const fn rank(&self) -> u8 {
+        match self {
+            Self::Owner => 2,
+            Self::Admin => 1,
+            Self::Member => 0,
+        }
+    }
+}
+
+pub enum Action {
+    Delete,
+    ManageMembers,
+    ManageRoles,
+    TransferOwnership,
+    Rename,
+    ChangeAvatar,
+    /// Only relevant for groups and channels, both sides of a chat may pin.
+    PinMessage,
+    /// Only relevant for channels, anyone may post to chats and groups.
+    Post,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Picture(String);
 
@@ -109,6 +255,13 @@ impl From<Id> for Picture {
     }
 }
 
+impl Picture {
+    /// Appends a version to the url so browsers drop a cached avatar.
+    pub fn versioned(self, v: i64) -> Self {
+        Self(format!("{}?v={v}", self.0))
+    }
+}
+
 impl From<user::Picture> for Picture {
     fn from(p: user::Picture) -> Self {
         Self(p.as_str().to_string())
@@ -139,7 +292,39 @@ pub enum Error {
     UnsupportedStatus,
     #[error("unsupported talk kind: {0:?}")]
     UnsupportedKind(String),
+    #[error("unsupported group role: {0:?}")]
+    UnsupportedRole(String),
+    #[error("forbidden to perform this action")]
+    Forbidden,
+    #[error("group owner cannot leave the group, transfer ownership first")]
+    OwnerCannotLeave,
+    #[error("no members selected")]
+    NoMembersSelected,
+    #[error("user is already a member: {0}")]
+    AlreadyMember(user::Id),
+    #[error("user is not a member: {0}")]
+    NotMember(user::Id),
+    #[error("invalid avatar: {0}")]
+    InvalidAvatar(String),
+    #[error("invite not found")]
+    InviteNotFound,
+    #[error("invite is expired or has no uses left")]
+    InviteExpired,
+    #[error("invalid invite settings: {0}")]
+    InvalidInvite(String),
+    #[error("invalid mute duration")]
+    InvalidMute,
+    #[error("only admins can post to this channel")]
+    PostingRestricted,
+    #[error("invalid cursor: {0}")]
+    InvalidCursor(String),
+    #[error("user is blocked: {0}")]
+    Blocked(user::Id),
+    #[error("user cannot be added to groups by you: {0}")]
+    AddsRestricted(user::Id),
 
+    #[error(transparent)]
+    _Contact(#[from] contact::Error),
     #[error(transparent)]
     _User(#[from] user::Error),
     #[error(transparent)]
@@ -148,4 +333,51 @@ pub enum Error {
     _R2d2(#[from] r2d2::Error),
     #[error(transparent)]
     _Diesel(#[from] diesel::result::Error),
+    #[error(transparent)]
+    _Join(#[from] tokio::task::JoinError),
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Action, Role};
+
+    #[test]
+    fn should_permit_actions_by_role() {
+        // (action, owner, admin, member)
+        let table = [
+            (Action::Delete, true, false, false),
+            (Action::ManageMembers, true, true, false),
+            (Action::ManageRoles, true, false, false),
+            (Action::TransferOwnership, true, false, false),
+            (Action::Rename, true, true, false),
+            (Action::ChangeAvatar, true, true, false),
+            (Action::PinMessage, true, true, false),
+            (Action::Post, true, true, false),
+        ];
+
+        for (i, (a, owner, admin, member)) in table.iter().enumerate() {
+            assert_eq!(Role::Owner.permits(a), *owner, "owner, action #{i}");
+            assert_eq!(Role::Admin.permits(a), *admin, "admin, action #{i}");
+            assert_eq!(Role::Member.permits(a), *member, "member, action #{i}");
+        }
+    }
+
+    #[test]
+    fn should_outrank_only_lower_roles() {
+        let table = [
+            (Role::Owner, Role::Owner, false),
+            (Role::Owner, Role::Admin, true),
+            (Role::Owner, Role::Member, true),
+            (Role::Admin, Role::Owner, false),
+            (Role::Admin, Role::Admin, false),
+            (Role::Admin, Role::Member, true),
+            (Role::Member, Role::Owner, false),
+            (Role::Member, Role::Admin, false),
+            (Role::Member, Role::Member, false),
+        ];
+
+        for (r, other, expected) in &table {
+            assert_eq!(r.outranks(other), *expected, "{r:?} over {other:?}");
+        }
+    }
 }
@@ -1,6 +1,8 @@
 use chrono::{DateTime, Utc};
 use diesel::{
-    prelude::{Associations, Identifiable, Insertable, Queryable, QueryableByName, Selectable},
+    prelude::{
+        AsChangeset, Associations, Identifiable, Insertable, Queryable, QueryableByName, Selectable,
+    },
     sql_types,
 };
 use messenger_service::AsStr;
@@ -14,7 +16,7 @@ use crate::{
     user,
 };
 
-use super::{Id, Kind, Picture};
+use super::{Id, Kind, Picture, Role};
 
 #[derive(Queryable, Selectable, Identifiable, Associations)]
 #[diesel(table_name = crate::schema::talks)]
@@ -58,11 +60,19 @@ impl<'a> NewTalk<'a> {
 #[diesel(table_name = crate::schema::chats)]
 pub struct NewChat<'a> {
     id: &'a Id,
+    saved_by: Option<&'a user::Id>,
 }
 
 impl<'a> NewChat<'a> {
     pub const fn new(id: &'a Id) -> Self {
-        Self { id }
+        Self { id, saved_by: None }
+    }
+
+    pub const fn saved(id: &'a Id, owner: &'a user::Id) -> Self {
+        Self {
+            id,
+            saved_by: Some(owner),
+        }
     }
 }
 
@@ -98,11 +108,16 @@ impl<'a> NewGroup<'a> {
 pub struct NewGroupUser<'a> {
     group_id: &'a Id,
     user_id: &'a user::Id,
+    role: &'a Role,
 }
 
 impl<'a> NewGroupUser<'a> {
-    pub const fn new(group_id: &'a Id, user_id: &'a user::Id) -> Self {
-        Self { group_id, user_id }
+    pub const fn new(group_id: &'a Id, user_id: &'a user::Id, role: &'a Role) -> Self {
+        Self {
+            group_id,
+            user_id,
+            role,
+        }
     }
 }
 
@@ -116,6 +131,14 @@ pub enum Details {
         owner: user::Id,
         members: Vec<user::Id>,
     },
+    Channel {
+        name: String,
+        owner: user::Id,
+    },
+    /// Private notes, a chat with the owner as its only member.
+    Saved {
+        owner: user::Id,
+    },
 }
 
 pub struct ChatTalk {
@@ -158,6 +181,7 @@ impl From<ChatWithLastMessage> for ChatTalk {
                 c.content.expect("content should be present"),
                 c.created_at.expect("created_at should be present"),
                 c.seen.expect("seen should be present"),
+                c.system.expect("system should be present"),
             ))
         } else {
             None
@@ -185,6 +209,8 @@ pub(super) struct ChatWithLastMessage {
     content: Option<String>,
     #[diesel(sql_type = sql_types::Nullable<sql_types::Bool>)]
     seen: Option<bool>,
+    #[diesel(sql_type = sql_types::Nullable<sql_types::Bool>)]
+    system: Option<bool>,
     #[diesel(sql_type = sql_types::Nullable<sql_types::Timestamptz>)]
     created_at: Option<DateTime<Utc>>,
     #[diesel(sql_type = sql_types::Uuid)]
@@ -195,23 +221,96 @@ pub(super) struct ChatWithLastMessage {
     picture: Picture,
 }
 
+#[derive(QueryableByName, Debug)]
+pub(super) struct GroupWithLastMessage {
+    #[diesel(sql_type = sql_types::Uuid)]
+    id: Id,
+    #[diesel(sql_type = crate::schema::sql_types::TalkKind)]
+    kind: Kind,
+    #[diesel(sql_type = sql_types::Nullable<sql_types::Uuid>)]
+    message_id: Option<message::Id>,
+    #[diesel(sql_type = sql_types::Nullable<sql_types::Uuid>)]
+    message_owner: Option<user::Id>,
+    #[diesel(sql_type = sql_types::Nullable<sql_types::Text>)]
+    content: Option<String>,
+    #[diesel(sql_type = sql_types::Nullable<sql_types::Bool>)]
+    seen: Option<bool>,
+    #[diesel(sql_type = sql_types::Nullable<sql_types::Bool>)]
+    system: Option<bool>,
+    #[diesel(sql_type = sql_types::Nullable<sql_types::Timestamptz>)]
+    created_at: Option<DateTime<Utc>>,
+    #[diesel(sql_type = sql_types::Uuid)]
+    owner: user::Id,
+    #[diesel(sql_type = sql_types::Text)]
+    name: String,
+    #[diesel(sql_type = sql_types::BigInt)]
+    avatar_version: i64,
+    #[diesel(sql_type = crate::schema::sql_types::GroupRole)]
+    role: Role,
+}
+
+impl From<GroupWithLastMessage> for GroupTalk {
+    fn from(g: GroupWithLastMessage) -> Self {
+        let last_message = g.message_id.map(|m_id| {
+            Message::new(
+                m_id,
+                g.id.clone(),
+                g.message_owner.expect("message_owner should be present"),
+                g.content.expect("content should be present"),
+                g.created_at.expect("created_at should be present"),
+                g.seen.expect("seen should be present"),
+                g.system.expect("system should be present"),
+            )
+        });
+
+        Self::new(
+            g.id,
+            g.kind,
+            last_message,
+            g.owner,
+            g.name,
+            g.avatar_version,
+            g.role,
+        )
+    }
+}
+
+/// Either a group or a channel, both share the same storage.
 pub struct GroupTalk {
     id: Id,
+    kind: Kind,
     last_message: Option<Message>,
     owner: user::Id,
     name: String,
+    avatar_version: i64,
+    role: Role,
 }
 
 impl GroupTalk {
-    pub const fn new(id: Id, last_message: Option<Message>, owner: user::Id, name: String) -> Self {
+    pub const fn new(
+        id: Id,
+        kind: Kind,
+        last_message: Option<Message>,
+        owner: user::Id,
+        name: String,
+        avatar_version: i64,
+        role: Role,
+    ) -> Self {
         Self {
             id,
+            kind,
             last_message,
             owner,
             name,
+            avatar_version,
+            role,
         }
     }
 
+    pub const fn kind(&self) -> &Kind {
+        &self.kind
+    }
+
     pub const fn id(&self) -> &Id {
         &self.id
     }
@@ -227,6 +326,144 @@ impl GroupTalk {
     pub const fn name(&self) -> &str {
         self.name.as_str()
     }
+
+    pub fn picture(&self) -> Picture {
+        Picture::from(self.id.clone()).versioned(self.avatar_version)
+    }
+
+    /// Role of the user this group was queried for.
+    pub const fn role(&self) -> &Role {
+        &self.role
+    }
+}
+
+/// Position in a talk list, which is ordered by pinned talks first
+/// and then by last activity, newest first.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Cursor {
+    pinned: bool,
+    last_activity: DateTime<Utc>,
+    id: Id,
+}
+
+impl Cursor {
+    pub const fn pinned(&self) -> bool {
+        self.pinned
+    }
+
+    pub const fn last_activity(&self) -> &DateTime<Utc> {
+        &self.last_activity
+    }
+
+    pub const fn id(&self) -> &Id {
+        &self.id
+    }
+}
+
+impl From<&TalkDto> for Cursor {
+    fn from(t: &TalkDto) -> Self {
+        Self {
+            pinned: t.preferences().pinned(),
+            last_activity: t
+                .last_message()
+                .map_or(DateTime::UNIX_EPOCH, |m| *m.created_at()),
+            id: t.id().clone(),
+        }
+    }
+}
+
+impl std::fmt::Display for Cursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}_{}_{}",
+            u8::from(self.pinned),
+            self.last_activity.timestamp_micros(),
+            self.id
+        )
+    }
+}
+
+impl TryFrom<String> for Cursor {
+    type Error = super::Error;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        let mut parts = s.splitn(3, '_');
+        let (Some(pinned), Some(micros), Some(id)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(super::Error::InvalidCursor(s));
+        };
+
+        let pinned = match pinned {
+            "0" => false,
+            "1" => true,
+            _ => return Err(super::Error::InvalidCursor(s)),
+        };
+        let last_activity = micros
+            .parse()
+            .ok()
+            .and_then(DateTime::from_timestamp_micros)
+            .ok_or_else(|| super::Error::InvalidCursor(s.clone()))?;
+        let id = uuid::Uuid::parse_str(id)
+            .map(Id::from)
+            .map_err(|_| super::Error::InvalidCursor(s.clone()))?;
+
+        Ok(Self {
+            pinned,
+            last_activity,
+            id,
+        })
+    }
+}
+
+pub struct Page {
+    archived: bool,
+    cursor: Option<Cursor>,
+    limit: i64,
+}
+
+impl Page {
+    pub const SIZE: i64 = 20;
+
+    /// First page of active talks, or the one after `cursor`.
+    pub const fn active(cursor: Option<Cursor>) -> Self {
+        Self {
+            archived: false,
+            cursor,
+            limit: Self::SIZE,
+        }
+    }
+
+    /// Archived talks are few and loaded at once.
+    pub const fn archived() -> Self {
+        Self {
+            archived: true,
+            cursor: None,
+            limit: i64::MAX,
+        }
+    }
+
+    /// All active talks at once, e.g. for a data export.
+    pub const fn unlimited() -> Self {
+        Self {
+            archived: false,
+            cursor: None,
+            limit: i64::MAX,
+        }
+    }
+
+    pub const fn is_archived(&self) -> bool {
+        self.archived
+    }
+
+    pub const fn cursor(&self) -> Option<&Cursor> {
+        self.cursor.as_ref()
+    }
+
+    pub const fn limit(&self) -> i64 {
+        self.limit
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -237,6 +474,10 @@ pub struct TalkDto {
     details: DetailsDto,
     #[serde(skip_serializing_if = "Option::is_none")]
     last_message: Option<MessageDto>,
+    #[serde(default)]
+    preferences: PreferencesDto,
+    #[serde(default)]
+    blocked: bool,
 }
 
 impl TalkDto {
@@ -253,9 +494,21 @@ impl TalkDto {
             name: name.into(),
             details,
             last_message,
+            preferences: PreferencesDto::default(),
+            blocked: false,
         }
     }
 
+    pub fn with_preferences(mut self, preferences: PreferencesDto) -> Self {
+        self.preferences = preferences;
+        self
+    }
+
+    pub const fn with_blocked(mut self, blocked: bool) -> Self {
+        self.blocked = blocked;
+        self
+    }
+
     pub const fn id(&self) -> &Id {
         &self.id
     }
@@ -272,13 +525,35 @@ impl TalkDto {
         &self.details
     }
 
+    /// Whether this is the "Saved messages" self-talk.
+    pub fn is_saved(&self) -> bool {
+        matches!(&self.details, DetailsDto::Chat { sender, recipient } if sender.eq(recipient))
+    }
+
+    pub const fn kind(&self) -> Kind {
+        match self.details {
+            DetailsDto::Chat { .. } => Kind::Chat,
+            DetailsDto::Group { .. } => Kind::Group,
+            DetailsDto::Channel { .. } => Kind::Channel,
+        }
+    }
+
     pub const fn last_message(&self) -> Option<&MessageDto> {
         self.last_message.as_ref()
     }
+
+    pub const fn preferences(&self) -> &PreferencesDto {
+        &self.preferences
+    }
+
+    /// Whether the chat is read-only because one of its sides has blocked the other.
+    pub const fn is_blocked(&self) -> bool {
+        self.blocked
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
-#[serde(untagged)]
+#[serde(tag = "kind", rename_all = "snake_case")]
 pub enum DetailsDto {
     Chat {
         sender: user::Id,
@@ -287,5 +562,265 @@ pub enum DetailsDto {
     Group {
         owner: user::Id,
         sender: user::Id,
+        role: Role,
+    },
+    Channel {
+        owner: user::Id,
+        sender: user::Id,
+        role: Role,
     },
 }
+
+/// A channel the user is not subscribed to yet.
+#[derive(QueryableByName)]
+pub struct ChannelSummary {
+    #[diesel(sql_type = sql_types::Uuid)]
+    id: Id,
+    #[diesel(sql_type = sql_types::Text)]
+    name: String,
+    #[diesel(sql_type = sql_types::BigInt)]
+    avatar_version: i64,
+    #[diesel(sql_type = sql_types::BigInt)]
+    subscribers: i64,
+}
+
+impl ChannelSummary {
+    pub const fn id(&self) -> &Id {
+        &self.id
+    }
+
+    pub const fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    pub fn picture(&self) -> Picture {
+        Picture::from(self.id.clone()).versioned(self.avatar_version)
+    }
+
+    pub const fn subscribers(&self) -> i64 {
+        self.subscribers
+    }
+}
+
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = crate::schema::group_invites)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Invite {
+    token: String,
+    group_id: Id,
+    created_by: user::Id,
+    created_at: DateTime<Utc>,
+    expires_at: Option<DateTime<Utc>>,
+    max_uses: Option<i32>,
+    uses: i32,
+}
+
+impl Invite {
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    pub const fn group_id(&self) -> &Id {
+        &self.group_id
+    }
+
+    pub const fn created_by(&self) -> &user::Id {
+        &self.created_by
+    }
+
+    pub const fn created_at(&self) -> &DateTime<Utc> {
+        &self.created_at
+    }
+
+    pub const fn expires_at(&self) -> Option<&DateTime<Utc>> {
+        self.expires_at.as_ref()
+    }
+
+    pub const fn max_uses(&self) -> Option<i32> {
+        self.max_uses
+    }
+
+    pub const fn uses(&self) -> i32 {
+        self.uses
+    }
+
+    /// Invite can be redeemed until it expires or runs out of uses.
+    pub fn is_active(&self, now: &DateTime<Utc>) -> bool {
+        self.expires_at.is_none_or(|e| e.gt(now)) && self.max_uses.is_none_or(|m| self.uses < m)
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::group_invites)]
+pub struct NewInvite<'a> {
+    token: &'a str,
+    group_id: &'a Id,
+    created_by: &'a user::Id,
+    expires_at: Option<DateTime<Utc>>,
+    max_uses: Option<i32>,
+}
+
+impl<'a> NewInvite<'a> {
+    pub const fn new(
+        token: &'a str,
+        group_id: &'a Id,
+        created_by: &'a user::Id,
+        expires_at: Option<DateTime<Utc>>,
+        max_uses: Option<i32>,
+    ) -> Self {
+        Self {
+            token,
+            group_id,
+            created_by,
+            expires_at,
+            max_uses,
+        }
+    }
+}
+
+/// Per-user settings of a talk, absent row means defaults.
+#[derive(Queryable, Selectable, Insertable, AsChangeset)]
+#[diesel(table_name = crate::schema::talk_preferences)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[diesel(treat_none_as_null = true)]
+pub struct Preferences {
+    talk_id: Id,
+    user_id: user::Id,
+    archived: bool,
+    pinned: bool,
+    muted_until: Option<DateTime<Utc>>,
+}
+
+impl Preferences {
+    pub const fn new(talk_id: Id, user_id: user::Id) -> Self {
+        Self {
+            talk_id,
+            user_id,
+            archived: false,
+            pinned: false,
+            muted_until: None,
+        }
+    }
+
+    pub const fn talk_id(&self) -> &Id {
+        &self.talk_id
+    }
+
+    pub const fn muted_until(&self) -> Option<DateTime<Utc>> {
+        self.muted_until
+    }
+
+    pub fn apply(&mut self, change: PreferenceChange) {
+        match change {
+            PreferenceChange::Archive(a) => self.archived = a,
+            PreferenceChange::Pin(p) => self.pinned = p,
+            PreferenceChange::MuteUntil(m) => self.muted_until = m,
+        }
+    }
+}
+
+pub enum PreferenceChange {
+    Archive(bool),
+    Pin(bool),
+    MuteUntil(Option<DateTime<Utc>>),
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct PreferencesDto {
+    archived: bool,
+    pinned: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    muted_until: Option<DateTime<Utc>>,
+}
+
+impl PreferencesDto {
+    pub const fn archived(&self) -> bool {
+        self.archived
+    }
+
+    pub const fn pinned(&self) -> bool {
+        self.pinned
+    }
+
+    /// Only set while the mute is still in effect.
+    pub const fn muted_until(&self) -> Option<&DateTime<Utc>> {
+        self.muted_until.as_ref()
+    }
+
+    pub const fn is_muted(&self) -> bool {
+        self.muted_until.is_some()
+    }
+}
+
+impl From<&Preferences> for PreferencesDto {
+    fn from(p: &Preferences) -> Self {
+        let now = Utc::now();
+        Self {
+            archived: p.archived,
+            pinned: p.pinned,
+            muted_until: p.muted_until.filter(|m| m.gt(&now)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::DateTime;
+    use uuid::Uuid;
+
+    use super::Cursor;
+    use crate::talk::{Error, Id};
+
+    const ID: &str = "0b6f5e1c-3f0a-4c8e-9d2b-7a4e5f6a8b9c";
+
+    fn cursor(pinned: bool) -> Cursor {
+        Cursor {
+            pinned,
+            last_activity: DateTime::from_timestamp_micros(1_700_000_000_123_456)
+                .expect("timestamp should be in range"),
+            id: Id::from(Uuid::parse_str(ID).expect("id should be a valid uuid")),
+        }
+    }
+
+    #[test]
+    fn should_round_trip_cursor() {
+        for c in [cursor(true), cursor(false)] {
+            let s = c.to_string();
+
+            assert_eq!(Cursor::try_from(s).ok(), Some(c));
+        }
+
+        assert_eq!(cursor(true).to_string(), format!("1_1700000000123456_{ID}"));
+    }
+
+    #[test]
+    fn should_reject_malformed_cursor() {
+        for s in ["", "1", "1_1700000000123456", "_1700000000123456_", "1__"] {
+            assert!(
+                matches!(
+                    Cursor::try_from(s.to_string()),
+                    Err(Error::InvalidCursor(_))
+                ),
+                "{s:?} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn should_reject_tampered_cursor() {
+        for s in [
+            format!("2_1700000000123456_{ID}"),
+            format!("true_1700000000123456_{ID}"),
+            format!("1_17000000001234x6_{ID}"),
+            format!("1_{}_{ID}", i64::MAX),
+            format!("1_1700000000123456_{}", &ID[1..]),
+            format!("1_1700000000123456_{ID}_1"),
+            format!("1_1700000000123456_{}", ID.replace('b', "g")),
+        ] {
+            assert!(
+                matches!(Cursor::try_from(s.clone()), Err(Error::InvalidCursor(_))),
+                "{s:?} should be rejected"
+            );
+        }
+    }
+}
@@ -1,30 +1,64 @@
 use diesel::{
-    Connection, ExpressionMethods, JoinOnDsl, NullableExpressionMethods, OptionalExtension,
-    PgConnection, QueryDsl, QueryResult, RunQueryDsl, dsl::delete, insert_into,
-    r2d2::ConnectionManager, sql_query, sql_types,
+    BoolExpressionMethods, Connection, ExpressionMethods, JoinOnDsl, NullableExpressionMethods,
+    OptionalExtension, PgConnection, QueryDsl, QueryResult, RunQueryDsl, SelectableHelper,
+    dsl::delete, dsl::exists, dsl::now, insert_into, r2d2::ConnectionManager,
+    result::DatabaseErrorKind, sql_query, sql_types, update,
 };
 
 use crate::{
+    integration::db::pg,
     message::{self, model::Message},
     schema::{
         chats, chats_users, groups, groups_users,
-        talks::dsl::{id, kind, last_message_id, talks},
+        talks::dsl::{id, kind, last_message_id, pinned_message_id, talks},
     },
     talk::{
-        self, Kind,
+        self, Kind, Role,
         model::{
-            ChatTalk, Details, GroupTalk, NewChat, NewChatUser, NewGroup, NewGroupUser, NewTalk,
+            ChannelSummary, ChatTalk, Details, GroupTalk, Invite, NewChat, NewChatUser, NewGroup,
+            NewGroupUser, NewInvite, NewTalk, Preferences,
         },
     },
     user,
 };
 
-use super::model::ChatWithLastMessage;
+use super::model::{ChatWithLastMessage, Cursor, GroupWithLastMessage, Page};
 
 pub trait TalkRepository {
-    fn find_chats_by_user_id(&self, user_id: &user::Id) -> super::Result<Vec<ChatTalk>>;
+    /// Pinned chats go first, the rest are ordered by last activity, newest first.
+    fn find_chats_by_user_id(
+        &self,
+        user_id: &user::Id,
+        page: &Page,
+    ) -> super::Result<Vec<ChatTalk>>;
+
+    /// Groups and channels share storage, `k` selects which ones to load.
+    /// Ordered the same way as chats.
+    fn find_groups_by_user_id(
+        &self,
+        user_id: &user::Id,
+        k: &Kind,
+        page: &Page,
+    ) -> super::Result<Vec<GroupTalk>>;
 
-    fn find_groups_by_user_id(&self, user_id: &user::Id) -> super::Result<Vec<GroupTalk>>;
+    /// Chats of the user, archived ones included, where the recipient name,
+    /// its alias or the last message contains `query`. Ordered the same way as the list.
+    fn search_chats(
+        &self,
+        user_id: &user::Id,
+        query: &str,
+        limit: i64,
+    ) -> super::Result<Vec<ChatTalk>>;
+
+    /// Groups or channels of the user, archived ones included, where the name,
+    /// a member name or the last message contains `query`.
+    fn search_groups(
+        &self,
+        user_id: &user::Id,
+        k: &Kind,
+        query: &str,
+        limit: i64,
+    ) -> super::Result<Vec<GroupTalk>>;
 
     fn find_chat_by_id_and_user_id(
         &self,
@@ -38,13 +72,81 @@ pub trait TalkRepository {
         user_id: &user::Id,
     ) -> super::Result<Option<GroupTalk>>;
 
+    fn find_kind(&self, id: &talk::Id) -> super::Result<Option<Kind>>;
+
+    /// Finds the self-talk of the user, the only chat where the user is alone.
+    fn find_saved(&self, user_id: &user::Id) -> super::Result<Option<ChatTalk>>;
+
+    /// Channels the user is not subscribed to, most popular first.
+    fn find_unsubscribed_channels(
+        &self,
+        user_id: &user::Id,
+        limit: i64,
+    ) -> super::Result<Vec<ChannelSummary>>;
+
+    fn find_channel_ids(&self, user_id: &user::Id) -> super::Result<Vec<talk::Id>>;
+
     fn create(&self, t: &NewTalk) -> super::Result<talk::Id>;
 
-    fn delete(&self, owner: &user::Id, id: &talk::Id) -> super::Result<bool>;
+    fn delete(&self, id: &talk::Id) -> super::Result<bool>;
 
+    /// Self-talks have a single member and never count as a duplicate.
     fn exists(&self, members: &[user::Id; 2]) -> super::Result<bool>;
+
+    fn add_members(&self, id: &talk::Id, members: &[user::Id]) -> super::Result<()>;
+
+    fn remove_member(&self, id: &talk::Id, member: &user::Id) -> super::Result<bool>;
+
+    fn find_role(&self, id: &talk::Id, member: &user::Id) -> super::Result<Option<Role>>;
+
+    fn find_roles(&self, id: &talk::Id) -> super::Result<Vec<(user::Id, Role)>>;
+
+    fn update_role(&self, id: &talk::Id, member: &user::Id, role: &Role) -> super::Result<bool>;
+
+    fn transfer_ownership(
+        &self,
+        id: &talk::Id,
+        owner: &user::Id,
+        new_owner: &user::Id,
+    ) -> super::Result<()>;
+
+    fn update_name(&self, id: &talk::Id, name: &str) -> super::Result<bool>;
+
+    /// Marks the avatar as changed, returns the new version.
+    fn bump_avatar_version(&self, id: &talk::Id) -> super::Result<i64>;
+
+    /// Pins a message of the talk, or unpins the current one if none is given.
+    /// Returns false if the message is not part of the talk.
+    fn pin_message(&self, id: &talk::Id, msg_id: Option<&message::Id>) -> super::Result<bool>;
+
+    fn find_pinned_message(&self, id: &talk::Id) -> super::Result<Option<Message>>;
+
+    fn create_invite(&self, invite: &NewInvite) -> super::Result<()>;
+
+    fn find_invite(&self, token: &str) -> super::Result<Option<(Invite, String, i64)>>;
+
+    fn find_invites(&self, id: &talk::Id) -> super::Result<Vec<Invite>>;
+
+    fn delete_invite(&self, id: &talk::Id, token: &str) -> super::Result<bool>;
+
+    /// Consumes one use of an active invite and adds the user to the group.
+    /// Returns false if the invite is expired or exhausted.
+    fn redeem_invite(&self, token: &str, user_id: &user::Id) -> super::Result<bool>;
+
+    fn find_preferences(&self, user_id: &user::Id) -> super::Result<Vec<Preferences>>;
+
+    fn find_preference(
+        &self,
+        id: &talk::Id,
+        user_id: &user::Id,
+    ) -> super::Result<Option<Preferences>>;
+
+    fn save_preferences(&self, preferences: &Preferences) -> super::Result<()>;
 }
 
+/// Keeps a single saved messages chat per user.
+const SAVED_CHAT_KEY: &str = "chats_saved_by_key";
+
 #[derive(Clone)]
 pub struct PgTalkRepository {
     pool: r2d2::Pool<ConnectionManager<PgConnection>>,
@@ -57,9 +159,10 @@ impl PgTalkRepository {
 }
 
 impl TalkRepository for PgTalkRepository {
-    fn find_chats_by_user_id(&self, u_id: &user::Id) -> super::Result<Vec<ChatTalk>> {
+    fn find_chats_by_user_id(&self, u_id: &user::Id, page: &Page) -> super::Result<Vec<ChatTalk>> {
         let mut conn = self.pool.get()?;
 
+        let cursor = page.cursor();
         let res: Vec<ChatWithLastMessage> = sql_query(
             r"
             SELECT
@@ -68,56 +171,186 @@ impl TalkRepository for PgTalkRepository {
                	m.owner,
                	m.content,
                	m.seen,
+               	m.system,
                	m.created_at,
                	u.id AS recipient,
-               	u.name,
+               	COALESCE(ca.alias, u.name) AS name,
                	u.picture
             FROM talks t
             JOIN chats_users cu_self ON cu_self.chat_id = t.id AND cu_self.user_id = $1
             JOIN chats_users cu_other ON cu_other.chat_id = t.id AND cu_other.user_id != $1
             JOIN users u ON u.id = cu_other.user_id
+            LEFT JOIN contact_aliases ca ON ca.owner = $1 AND ca.user_id = u.id
             LEFT JOIN messages m ON m.id = t.last_message_id
+            LEFT JOIN talk_preferences p ON p.talk_id = t.id AND p.user_id = $1
             WHERE t.kind = 'chat'
+            AND COALESCE(p.archived, false) = $2
+            AND (
+                $3::boolean IS NULL
+                OR (COALESCE(p.pinned, false), COALESCE(m.created_at, 'epoch'), t.id) < ($3, $4, $5)
+            )
+            ORDER BY COALESCE(p.pinned, false) DESC, COALESCE(m.created_at, 'epoch') DESC, t.id DESC
+            LIMIT $6
             ",
         )
         .bind::<sql_types::Uuid, _>(u_id.get())
+        .bind::<sql_types::Bool, _>(page.is_archived())
+        .bind::<sql_types::Nullable<sql_types::Bool>, _>(cursor.map(Cursor::pinned))
+        .bind::<sql_types::Nullable<sql_types::Timestamptz>, _>(cursor.map(Cursor::last_activity))
+        .bind::<sql_types::Nullable<sql_types::Uuid>, _>(cursor.map(|c| c.id().get()))
+        .bind::<sql_types::BigInt, _>(page.limit())
         .load::<ChatWithLastMessage>(&mut conn)?;
 
         Ok(res.into_iter().map(ChatTalk::from).collect())
     }
 
-    fn find_groups_by_user_id(&self, u_id: &user::Id) -> super::Result<Vec<GroupTalk>> {
-        use crate::schema::groups::dsl as g;
-        use crate::schema::groups_users::dsl as gu;
-        use crate::schema::messages::dsl as m;
+    fn find_groups_by_user_id(
+        &self,
+        u_id: &user::Id,
+        k: &Kind,
+        page: &Page,
+    ) -> super::Result<Vec<GroupTalk>> {
+        let mut conn = self.pool.get()?;
+
+        let cursor = page.cursor();
+        let res: Vec<GroupWithLastMessage> = sql_query(
+            r"
+            SELECT
+                t.id,
+                t.kind,
+                m.id AS message_id,
+                m.owner AS message_owner,
+                m.content,
+                m.seen,
+                m.system,
+                m.created_at,
+                g.owner,
+                g.name,
+                g.avatar_version,
+                gu.role
+            FROM talks t
+            JOIN groups g ON g.id = t.id
+            JOIN groups_users gu ON gu.group_id = g.id AND gu.user_id = $1
+            LEFT JOIN messages m ON m.id = t.last_message_id
+            LEFT JOIN talk_preferences p ON p.talk_id = t.id AND p.user_id = $1
+            WHERE t.kind = $2
+            AND COALESCE(p.archived, false) = $3
+            AND (
+                $4::boolean IS NULL
+                OR (COALESCE(p.pinned, false), COALESCE(m.created_at, 'epoch'), t.id) < ($4, $5, $6)
+            )
+            ORDER BY COALESCE(p.pinned, false) DESC, COALESCE(m.created_at, 'epoch') DESC, t.id DESC
+            LIMIT $7
+            ",
+        )
+        .bind::<sql_types::Uuid, _>(u_id.get())
+        .bind::<crate::schema::sql_types::TalkKind, _>(k)
+        .bind::<sql_types::Bool, _>(page.is_archived())
+        .bind::<sql_types::Nullable<sql_types::Bool>, _>(cursor.map(Cursor::pinned))
+        .bind::<sql_types::Nullable<sql_types::Timestamptz>, _>(cursor.map(Cursor::last_activity))
+        .bind::<sql_types::Nullable<sql_types::Uuid>, _>(cursor.map(|c| c.id().get()))
+        .bind::<sql_types::BigInt, _>(page.limit())
+        .load::<GroupWithLastMessage>(&mut conn)?;
+
+        Ok(res.into_iter().map(GroupTalk::from).collect())
+    }
 
+    fn search_chats(
+        &self,
+        u_id: &user::Id,
+        query: &str,
+        limit: i64,
+    ) -> super::Result<Vec<ChatTalk>> {
         let mut conn = self.pool.get()?;
 
-        let res: Vec<(talk::Id, Option<Message>, user::Id, String)> = talks
-            .filter(kind.eq(Kind::Group))
-            .filter(gu::user_id.eq(u_id))
-            .inner_join(g::groups.inner_join(gu::groups_users))
-            .left_join(m::messages.on(last_message_id.eq(m::id.nullable())))
-            .select((
-                id,
-                (
-                    m::id,
-                    m::talk_id,
-                    m::owner,
-                    m::content,
-                    m::created_at,
-                    m::seen,
+        let res: Vec<ChatWithLastMessage> = sql_query(
+            r"
+            SELECT
+                t.id,
+                m.id AS message_id,
+                m.owner,
+                m.content,
+                m.seen,
+                m.system,
+                m.created_at,
+                u.id AS recipient,
+                COALESCE(ca.alias, u.name) AS name,
+                u.picture
+            FROM talks t
+            JOIN chats_users cu_self ON cu_self.chat_id = t.id AND cu_self.user_id = $1
+            JOIN chats_users cu_other ON cu_other.chat_id = t.id AND cu_other.user_id != $1
+            JOIN users u ON u.id = cu_other.user_id
+            LEFT JOIN contact_aliases ca ON ca.owner = $1 AND ca.user_id = u.id
+            LEFT JOIN messages m ON m.id = t.last_message_id
+            LEFT JOIN talk_preferences p ON p.talk_id = t.id AND p.user_id = $1
+            WHERE t.kind = 'chat'
+            AND (
+                u.name ILIKE $2 ESCAPE '\'
+                OR ca.alias ILIKE $2 ESCAPE '\'
+                OR m.content ILIKE $2 ESCAPE '\'
+            )
+            ORDER BY COALESCE(p.pinned, false) DESC, COALESCE(m.created_at, 'epoch') DESC, t.id DESC
+            LIMIT $3
+            ",
+        )
+        .bind::<sql_types::Uuid, _>(u_id.get())
+        .bind::<sql_types::Text, _>(format!("%{}%", pg::escape_like(query)))
+        .bind::<sql_types::BigInt, _>(limit)
+        .load::<ChatWithLastMessage>(&mut conn)?;
+
+        Ok(res.into_iter().map(ChatTalk::from).collect())
+    }
+
+    fn search_groups(
+        &self,
+        u_id: &user::Id,
+        k: &Kind,
+        query: &str,
+        limit: i64,
+    ) -> super::Result<Vec<GroupTalk>> {
+        let mut conn = self.pool.get()?;
+
+        let res: Vec<GroupWithLastMessage> = sql_query(
+            r"
+            SELECT
+                t.id,
+                t.kind,
+                m.id AS message_id,
+                m.owner AS message_owner,
+                m.content,
+                m.seen,
+                m.system,
+                m.created_at,
+                g.owner,
+                g.name,
+                g.avatar_version,
+                gu.role
+            FROM talks t
+            JOIN groups g ON g.id = t.id
+            JOIN groups_users gu ON gu.group_id = g.id AND gu.user_id = $1
+            LEFT JOIN messages m ON m.id = t.last_message_id
+            LEFT JOIN talk_preferences p ON p.talk_id = t.id AND p.user_id = $1
+            WHERE t.kind = $2
+            AND (
+                g.name ILIKE $3 ESCAPE '\'
+                OR m.content ILIKE $3 ESCAPE '\'
+                OR EXISTS (
+                    SELECT 1 FROM groups_users gm
+                    JOIN users mu ON mu.id = gm.user_id
+                    WHERE gm.group_id = g.id AND mu.name ILIKE $3 ESCAPE '\'
                 )
-                    .nullable(),
-                g::owner,
-                g::name,
-            ))
-            .get_results(&mut conn)?;
+            )
+            ORDER BY COALESCE(p.pinned, false) DESC, COALESCE(m.created_at, 'epoch') DESC, t.id DESC
+            LIMIT $4
+            ",
+        )
+        .bind::<sql_types::Uuid, _>(u_id.get())
+        .bind::<crate::schema::sql_types::TalkKind, _>(k)
+        .bind::<sql_types::Text, _>(format!("%{}%", pg::escape_like(query)))
+        .bind::<sql_types::BigInt, _>(limit)
+        .load::<GroupWithLastMessage>(&mut conn)?;
 
-        Ok(res
-            .into_iter()
-            .map(|r| GroupTalk::new(r.0, r.1, r.2, r.3))
-            .collect())
+        Ok(res.into_iter().map(GroupTalk::from).collect())
     }
 
     fn find_chat_by_id_and_user_id(
@@ -135,14 +368,16 @@ impl TalkRepository for PgTalkRepository {
                	m.owner,
                	m.content,
                	m.seen,
+               	m.system,
                	m.created_at,
                 u.id AS recipient,
-                u.name,
+                COALESCE(ca.alias, u.name) AS name,
                 u.picture
             FROM talks t
             JOIN chats_users cu_self ON cu_self.chat_id = t.id AND cu_self.user_id = $1
             JOIN chats_users cu_other ON cu_other.chat_id = t.id AND cu_other.user_id != $1
             JOIN users u ON u.id = cu_other.user_id
+            LEFT JOIN contact_aliases ca ON ca.owner = $1 AND ca.user_id = u.id
             LEFT JOIN messages m ON m.id = t.last_message_id
             WHERE t.id = $2
             AND t.kind = 'chat'
@@ -167,16 +402,20 @@ impl TalkRepository for PgTalkRepository {
         use crate::schema::groups_users::dsl as gu;
         use crate::schema::messages::dsl as m;
 
+        type Row = (talk::Id, Kind, Option<Message>, user::Id, String, i64, Role);
+
         let mut conn = self.pool.get()?;
 
-        let res: Option<(talk::Id, Option<Message>, user::Id, String)> = talks
+        let res: Option<Row> = talks
             .filter(id.eq(t_id))
-            .filter(kind.eq(Kind::Group))
+            // channels share group storage
+            .filter(kind.eq_any([Kind::Group, Kind::Channel]))
             .filter(gu::user_id.eq(u_id))
             .inner_join(g::groups.inner_join(gu::groups_users))
             .left_join(m::messages.on(last_message_id.eq(m::id.nullable())))
             .select((
                 id,
+                kind,
                 (
                     m::id,
                     m::talk_id,
@@ -184,15 +423,105 @@ impl TalkRepository for PgTalkRepository {
                     m::content,
                     m::created_at,
                     m::seen,
+                    m::system,
                 )
                     .nullable(),
                 g::owner,
                 g::name,
+                g::avatar_version,
+                gu::role,
             ))
             .get_result(&mut conn)
             .optional()?;
 
-        Ok(res.map(|r| GroupTalk::new(r.0, r.1, r.2, r.3)))
+        Ok(res.map(|r| GroupTalk::new(r.0, r.1, r.2, r.3, r.4, r.5, r.6)))
+    }
+
+    fn find_kind(&self, t_id: &talk::Id) -> super::Result<Option<Kind>> {
+        let mut conn = self.pool.get()?;
+
+        talks
+            .find(t_id)
+            .select(kind)
+            .first(&mut conn)
+            .optional()
+            .map_err(super::Error::from)
+    }
+
+    fn find_saved(&self, u_id: &user::Id) -> super::Result<Option<ChatTalk>> {
+        let mut conn = self.pool.get()?;
+
+        sql_query(
+            r"
+            SELECT
+                t.id,
+                m.id AS message_id,
+                m.owner,
+                m.content,
+                m.seen,
+                m.system,
+                m.created_at,
+                u.id AS recipient,
+                u.name,
+                u.picture
+            FROM talks t
+            JOIN chats c ON c.id = t.id AND c.saved_by = $1
+            JOIN chats_users cu ON cu.chat_id = t.id AND cu.user_id = $1
+            JOIN users u ON u.id = cu.user_id
+            LEFT JOIN messages m ON m.id = t.last_message_id
+            WHERE t.kind = 'chat'
+            ",
+        )
+        .bind::<sql_types::Uuid, _>(u_id.get())
+        .get_result::<ChatWithLastMessage>(&mut conn)
+        .map(ChatTalk::from)
+        .optional()
+        .map_err(super::Error::from)
+    }
+
+    fn find_unsubscribed_channels(
+        &self,
+        u_id: &user::Id,
+        limit: i64,
+    ) -> super::Result<Vec<ChannelSummary>> {
+        let mut conn = self.pool.get()?;
+
+        sql_query(
+            r"
+            SELECT
+                g.id,
+                g.name,
+                g.avatar_version,
+                COUNT(gu.user_id) AS subscribers
+            FROM groups g
+            JOIN talks t ON t.id = g.id AND t.kind = 'channel'
+            JOIN groups_users gu ON gu.group_id = g.id
+            WHERE NOT EXISTS (
+                SELECT 1 FROM groups_users s WHERE s.group_id = g.id AND s.user_id = $1
+            )
+            GROUP BY g.id, g.name, g.avatar_version
+            ORDER BY subscribers DESC
+            LIMIT $2
+            ",
+        )
+        .bind::<sql_types::Uuid, _>(u_id.get())
+        .bind::<sql_types::BigInt, _>(limit)
+        .load::<ChannelSummary>(&mut conn)
+        .map_err(super::Error::from)
+    }
+
+    fn find_channel_ids(&self, u_id: &user::Id) -> super::Result<Vec<talk::Id>> {
+        use crate::schema::groups_users::dsl as gu;
+
+        let mut conn = self.pool.get()?;
+
+        gu::groups_users
+            .inner_join(talks.on(id.eq(gu::group_id)))
+            .filter(gu::user_id.eq(u_id))
+            .filter(kind.eq(Kind::Channel))
+            .select(gu::group_id)
+            .load(&mut conn)
+            .map_err(super::Error::from)
     }
 
     fn create(&self, t: &NewTalk) -> super::Result<talk::Id> {
@@ -200,8 +529,9 @@ impl TalkRepository for PgTalkRepository {
 
         let tx_res: QueryResult<talk::Id> = conn.transaction(|conn| {
             let k = match t.details() {
-                Details::Chat { .. } => Kind::Chat,
+                Details::Chat { .. } | Details::Saved { .. } => Kind::Chat,
                 Details::Group { .. } => Kind::Group,
+                Details::Channel { .. } => Kind::Channel,
             };
 
             let new_talk = (kind.eq(k), last_message_id.eq::<Option<message::Id>>(None));
@@ -236,26 +566,57 @@ impl TalkRepository for PgTalkRepository {
 
                     let users: Vec<NewGroupUser> = members
                         .iter()
-                        .map(|m| NewGroupUser::new(&g_id, m))
+                        .map(|m| {
+                            let role = if m.eq(owner) {
+                                &Role::Owner
+                            } else {
+                                &Role::Member
+                            };
+                            NewGroupUser::new(&g_id, m, role)
+                        })
                         .collect();
 
                     insert_into(groups_users::table)
                         .values(users)
                         .execute(conn)?;
                 }
+                Details::Saved { owner } => {
+                    let c_id: talk::Id = insert_into(chats::table)
+                        .values(NewChat::saved(&t_id, owner))
+                        .returning(chats::id)
+                        .get_result(conn)?;
+
+                    insert_into(chats_users::table)
+                        .values(NewChatUser::new(&c_id, owner))
+                        .execute(conn)?;
+                }
+                Details::Channel { name, owner } => {
+                    let c_id: talk::Id = insert_into(groups::table)
+                        .values(NewGroup::new(&t_id, owner, name))
+                        .returning(groups::id)
+                        .get_result(conn)?;
+
+                    insert_into(groups_users::table)
+                        .values(NewGroupUser::new(&c_id, owner, &Role::Owner))
+                        .execute(conn)?;
+                }
             }
             Ok(t_id)
         });
 
-        tx_res.map_err(super::Error::from)
+        tx_res.map_err(|e| match e {
+            diesel::result::Error::DatabaseError(DatabaseErrorKind::UniqueViolation, ref info)
+                if info.constraint_name() == Some(SAVED_CHAT_KEY) =>
+            {
+                talk::Error::AlreadyExists
+            }
+            e => super::Error::from(e),
+        })
     }
 
-    fn delete(&self, _o: &user::Id, t_id: &talk::Id) -> super::Result<bool> {
+    fn delete(&self, t_id: &talk::Id) -> super::Result<bool> {
         let mut conn = self.pool.get()?;
 
-        // TODO: check if
-        // 1. chat -> user is a member
-        // 2. group -> user is owner
         let deleted_count = delete(talks.find(t_id)).execute(&mut conn)?;
 
         Ok(deleted_count > 0)
@@ -277,4 +638,302 @@ impl TalkRepository for PgTalkRepository {
             .map(|r| r.is_some())
             .map_err(super::Error::from)
     }
+
+    fn add_members(&self, t_id: &talk::Id, members: &[user::Id]) -> super::Result<()> {
+        let mut conn = self.pool.get()?;
+
+        let users: Vec<NewGroupUser> = members
+            .iter()
+            .map(|m| NewGroupUser::new(t_id, m, &Role::Member))
+            .collect();
+
+        insert_into(groups_users::table)
+            .values(users)
+            .on_conflict_do_nothing()
+            .execute(&mut conn)?;
+
+        Ok(())
+    }
+
+    fn remove_member(&self, t_id: &talk::Id, member: &user::Id) -> super::Result<bool> {
+        use crate::schema::groups_users::dsl as gu;
+
+        let mut conn = self.pool.get()?;
+
+        let deleted_count = delete(
+            gu::groups_users
+                .filter(gu::group_id.eq(t_id))
+                .filter(gu::user_id.eq(member)),
+        )
+        .execute(&mut conn)?;
+
+        Ok(deleted_count > 0)
+    }
+
+    fn find_role(&self, t_id: &talk::Id, member: &user::Id) -> super::Result<Option<Role>> {
+        use crate::schema::groups_users::dsl as gu;
+
+        let mut conn = self.pool.get()?;
+
+        gu::groups_users
+            .filter(gu::group_id.eq(t_id))
+            .filter(gu::user_id.eq(member))
+            .select(gu::role)
+            .first(&mut conn)
+            .optional()
+            .map_err(super::Error::from)
+    }
+
+    fn find_roles(&self, t_id: &talk::Id) -> super::Result<Vec<(user::Id, Role)>> {
+        use crate::schema::groups_users::dsl as gu;
+
+        let mut conn = self.pool.get()?;
+
+        gu::groups_users
+            .filter(gu::group_id.eq(t_id))
+            .select((gu::user_id, gu::role))
+            .load(&mut conn)
+            .map_err(super::Error::from)
+    }
+
+    fn update_role(&self, t_id: &talk::Id, member: &user::Id, r: &Role) -> super::Result<bool> {
+        use crate::schema::groups_users::dsl as gu;
+
+        let mut conn = self.pool.get()?;
+
+        let modified_count = update(gu::groups_users)
+            .filter(gu::group_id.eq(t_id))
+            .filter(gu::user_id.eq(member))
+            .set(gu::role.eq(r))
+            .execute(&mut conn)?;
+
+        Ok(modified_count > 0)
+    }
+
+    fn transfer_ownership(
+        &self,
+        t_id: &talk::Id,
+        o: &user::Id,
+        new_owner: &user::Id,
+    ) -> super::Result<()> {
+        use crate::schema::groups::dsl as g;
+        use crate::schema::groups_users::dsl as gu;
+
+        let mut conn = self.pool.get()?;
+
+        let tx_res: QueryResult<()> = conn.transaction(|conn| {
+            update(g::groups.find(t_id))
+                .set(g::owner.eq(new_owner))
+                .execute(conn)?;
+
+            update(gu::groups_users)
+                .filter(gu::group_id.eq(t_id))
+                .filter(gu::user_id.eq(new_owner))
+                .set(gu::role.eq(Role::Owner))
+                .execute(conn)?;
+
+            // previous owner keeps managing the group as admin
+            update(gu::groups_users)
+                .filter(gu::group_id.eq(t_id))
+                .filter(gu::user_id.eq(o))
+                .set(gu::role.eq(Role::Admin))
+                .execute(conn)?;
+
+            Ok(())
+        });
+
+        tx_res.map_err(super::Error::from)
+    }
+
+    fn update_name(&self, t_id: &talk::Id, n: &str) -> super::Result<bool> {
+        use crate::schema::groups::dsl as g;
+
+        let mut conn = self.pool.get()?;
+
+        let modified_count = update(g::groups.find(t_id))
+            .set(g::name.eq(n))
+            .execute(&mut conn)?;
+
+        Ok(modified_count > 0)
+    }
+
+    fn bump_avatar_version(&self, t_id: &talk::Id) -> super::Result<i64> {
+        use crate::schema::groups::dsl as g;
+
+        let mut conn = self.pool.get()?;
+
+        update(g::groups.find(t_id))
+            .set(g::avatar_version.eq(g::avatar_version + 1))
+            .returning(g::avatar_version)
+            .get_result(&mut conn)
+            .map_err(super::Error::from)
+    }
+
+    fn pin_message(&self, t_id: &talk::Id, msg_id: Option<&message::Id>) -> super::Result<bool> {
+        use crate::schema::messages::dsl as m;
+
+        let mut conn = self.pool.get()?;
+
+        let modified_count = match msg_id {
+            Some(m_id) => update(talks.find(t_id))
+                .filter(exists(
+                    m::messages.filter(m::id.eq(m_id).and(m::talk_id.eq(t_id))),
+                ))
+                .set(pinned_message_id.eq(m_id))
+                .execute(&mut conn)?,
+            None => update(talks.find(t_id))
+                .set(pinned_message_id.eq::<Option<message::Id>>(None))
+                .execute(&mut conn)?,
+        };
+
+        Ok(modified_count > 0)
+    }
+
+    fn find_pinned_message(&self, t_id: &talk::Id) -> super::Result<Option<Message>> {
+        use crate::schema::messages::dsl as m;
+
+        let mut conn = self.pool.get()?;
+
+        m::messages
+            .inner_join(talks)
+            .filter(id.eq(t_id))
+            .filter(pinned_message_id.eq(m::id.nullable()))
+            .select(Message::as_select())
+            .first(&mut conn)
+            .optional()
+            .map_err(super::Error::from)
+    }
+
+    fn create_invite(&self, invite: &NewInvite) -> super::Result<()> {
+        use crate::schema::group_invites::dsl as gi;
+
+        let mut conn = self.pool.get()?;
+
+        insert_into(gi::group_invites)
+            .values(invite)
+            .execute(&mut conn)?;
+
+        Ok(())
+    }
+
+    fn find_invite(&self, token: &str) -> super::Result<Option<(Invite, String, i64)>> {
+        use crate::schema::group_invites::dsl as gi;
+        use crate::schema::groups::dsl as g;
+
+        let mut conn = self.pool.get()?;
+
+        gi::group_invites
+            .inner_join(g::groups)
+            .filter(gi::token.eq(token))
+            .select((Invite::as_select(), g::name, g::avatar_version))
+            .first(&mut conn)
+            .optional()
+            .map_err(super::Error::from)
+    }
+
+    fn find_invites(&self, t_id: &talk::Id) -> super::Result<Vec<Invite>> {
+        use crate::schema::group_invites::dsl as gi;
+
+        let mut conn = self.pool.get()?;
+
+        gi::group_invites
+            .filter(gi::group_id.eq(t_id))
+            .order(gi::created_at.desc())
+            .select(Invite::as_select())
+            .load(&mut conn)
+            .map_err(super::Error::from)
+    }
+
+    fn delete_invite(&self, t_id: &talk::Id, t: &str) -> super::Result<bool> {
+        use crate::schema::group_invites::dsl as gi;
+
+        let mut conn = self.pool.get()?;
+
+        let deleted_count = delete(
+            gi::group_invites
+                .filter(gi::group_id.eq(t_id))
+                .filter(gi::token.eq(t)),
+        )
+        .execute(&mut conn)?;
+
+        Ok(deleted_count > 0)
+    }
+
+    fn redeem_invite(&self, t: &str, user_id: &user::Id) -> super::Result<bool> {
+        use crate::schema::group_invites::dsl as gi;
+
+        let mut conn = self.pool.get()?;
+
+        let tx_res: QueryResult<bool> = conn.transaction(|conn| {
+            let group_id = update(gi::group_invites)
+                .filter(gi::token.eq(t))
+                .filter(gi::expires_at.is_null().or(gi::expires_at.gt(now)))
+                .filter(
+                    gi::max_uses
+                        .is_null()
+                        .or(gi::uses.lt(gi::max_uses.assume_not_null())),
+                )
+                .set(gi::uses.eq(gi::uses + 1))
+                .returning(gi::group_id)
+                .get_result::<talk::Id>(conn)
+                .optional()?;
+
+            let Some(group_id) = group_id else {
+                return Ok(false);
+            };
+
+            insert_into(groups_users::table)
+                .values(&NewGroupUser::new(&group_id, user_id, &Role::Member))
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+
+            Ok(true)
+        });
+
+        tx_res.map_err(super::Error::from)
+    }
+
+    fn find_preferences(&self, u_id: &user::Id) -> super::Result<Vec<Preferences>> {
+        use crate::schema::talk_preferences::dsl as tp;
+
+        let mut conn = self.pool.get()?;
+
+        tp::talk_preferences
+            .filter(tp::user_id.eq(u_id))
+            .select(Preferences::as_select())
+            .load(&mut conn)
+            .map_err(super::Error::from)
+    }
+
+    fn find_preference(
+        &self,
+        t_id: &talk::Id,
+        u_id: &user::Id,
+    ) -> super::Result<Option<Preferences>> {
+        use crate::schema::talk_preferences::dsl as tp;
+
+        let mut conn = self.pool.get()?;
+
+        tp::talk_preferences
+            .find((t_id, u_id))
+            .select(Preferences::as_select())
+            .first(&mut conn)
+            .optional()
+            .map_err(super::Error::from)
+    }
+
+    fn save_preferences(&self, p: &Preferences) -> super::Result<()> {
+        use crate::schema::talk_preferences::dsl as tp;
+
+        let mut conn = self.pool.get()?;
+
+        insert_into(tp::talk_preferences)
+            .values(p)
+            .on_conflict((tp::talk_id, tp::user_id))
+            .do_update()
+            .set(p)
+            .execute(&mut conn)?;
+
+        Ok(())
+    }
 }
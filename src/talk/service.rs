@@ -1,18 +1,28 @@
+use std::collections::{HashMap, HashSet};
+use std::io::Cursor;
 use std::pin::Pin;
 
 use async_trait::async_trait;
 use bytes::Bytes;
+use chrono::{Duration, Utc};
 use futures::{Stream, TryFutureExt, TryStreamExt};
+use image::{ImageFormat, imageops::FilterType};
 use log::error;
+use messenger_service::AsStr;
+use uuid::Uuid;
 
-use super::model::{ChatTalk, Details, DetailsDto, GroupTalk, TalkDto};
-use super::{Kind, Repository};
+use super::model::{
+    ChannelSummary, ChatTalk, Details, DetailsDto, GroupTalk, Invite, NewInvite, Page,
+    PreferenceChange, Preferences, PreferencesDto, TalkDto,
+};
+use super::{Action, Kind, Repository, Role};
 use crate::integration::storage::Blob;
 use crate::integration::{self, cache, storage};
-use crate::message::model::MessageDto;
+use crate::message::model::{MessageDto, NewMessage};
 use crate::talk::Picture;
 use crate::talk::model::NewTalk;
-use crate::{auth, contact, event, talk, user};
+use crate::user::model::Erasure;
+use crate::{auth, contact, event, message, talk, user};
 
 #[async_trait]
 pub trait TalkService {
@@ -26,14 +36,47 @@ pub trait TalkService {
         members: &[user::Id],
     ) -> super::Result<TalkDto>;
 
-    fn find_by_id_and_user_id(
+    async fn create_channel(&self, auth_id: &user::Id, name: &str) -> super::Result<TalkDto>;
+
+    /// Returns the "Saved messages" self-talk of the user, creating it on first use.
+    fn find_or_create_saved(&self, auth_id: &user::Id) -> super::Result<TalkDto>;
+
+    async fn find_by_id_and_user_id(
         &self,
         kind: &Kind,
         id: &talk::Id,
         user_id: &user::Id,
     ) -> super::Result<TalkDto>;
 
-    fn find_all_by_kind(&self, auth_user: &auth::User, kind: &Kind) -> super::Result<Vec<TalkDto>>;
+    fn find_kind(&self, id: &talk::Id) -> super::Result<Kind>;
+
+    fn find_unsubscribed_channels(&self, auth_id: &user::Id) -> super::Result<Vec<ChannelSummary>>;
+
+    /// Channels the user is subscribed to.
+    fn find_channel_ids(&self, auth_id: &user::Id) -> super::Result<Vec<talk::Id>>;
+
+    async fn subscribe(&self, auth_id: &user::Id, id: &talk::Id) -> super::Result<TalkDto>;
+
+    /// Fails unless the user may post messages to the talk, returns the kind of the talk.
+    async fn authorize_post(&self, auth_id: &user::Id, id: &talk::Id) -> super::Result<Kind>;
+
+    /// Fails if the talk is a chat whose sides have blocked one another.
+    async fn check_not_blocked(&self, auth_id: &user::Id, id: &talk::Id) -> super::Result<()>;
+
+    fn find_all_by_kind(
+        &self,
+        auth_user: &auth::User,
+        kind: &Kind,
+        page: &Page,
+    ) -> super::Result<Vec<TalkDto>>;
+
+    /// Talks of the given kind matching `query` by name, member name or last message.
+    fn search(
+        &self,
+        auth_user: &auth::User,
+        kind: &Kind,
+        query: &str,
+    ) -> super::Result<Vec<TalkDto>>;
 
     async fn find_avatar(
         &self,
@@ -41,11 +84,102 @@ pub trait TalkService {
     ) -> super::Result<Pin<Box<dyn Stream<Item = super::Result<Bytes>> + Send>>>;
 
     async fn delete(&self, id: &talk::Id, auth_user: &auth::User) -> super::Result<()>;
+
+    async fn add_members(
+        &self,
+        auth_id: &user::Id,
+        id: &talk::Id,
+        members: &[user::Id],
+    ) -> super::Result<()>;
+
+    async fn remove_member(
+        &self,
+        auth_id: &user::Id,
+        id: &talk::Id,
+        member: &user::Id,
+    ) -> super::Result<()>;
+
+    async fn leave(&self, auth_id: &user::Id, id: &talk::Id) -> super::Result<()>;
+
+    fn find_roles(&self, id: &talk::Id) -> super::Result<Vec<(user::Id, Role)>>;
+
+    async fn set_role(
+        &self,
+        auth_id: &user::Id,
+        id: &talk::Id,
+        member: &user::Id,
+        role: &Role,
+    ) -> super::Result<()>;
+
+    async fn transfer_ownership(
+        &self,
+        auth_id: &user::Id,
+        id: &talk::Id,
+        new_owner: &user::Id,
+    ) -> super::Result<()>;
+
+    async fn rename(&self, auth_id: &user::Id, id: &talk::Id, name: &str) -> super::Result<()>;
+
+    /// Tells the members of groups a deleted account was part of that it is gone,
+    /// and who owns the ones it used to own.
+    async fn announce_erasure(&self, name: &str, erasure: &Erasure);
+
+    async fn update_avatar(
+        &self,
+        auth_id: &user::Id,
+        id: &talk::Id,
+        data: Bytes,
+    ) -> super::Result<()>;
+
+    async fn reset_avatar(&self, auth_id: &user::Id, id: &talk::Id) -> super::Result<()>;
+
+    fn create_invite(
+        &self,
+        auth_id: &user::Id,
+        id: &talk::Id,
+        expires_in: Option<Duration>,
+        max_uses: Option<i32>,
+    ) -> super::Result<()>;
+
+    fn find_invites(&self, auth_id: &user::Id, id: &talk::Id) -> super::Result<Vec<Invite>>;
+
+    fn revoke_invite(&self, auth_id: &user::Id, id: &talk::Id, token: &str) -> super::Result<()>;
+
+    /// Finds an active invite along with the name and picture of the group it leads to.
+    fn find_invite(&self, token: &str) -> super::Result<(Invite, String, Picture)>;
+
+    async fn join(&self, auth_id: &user::Id, token: &str) -> super::Result<talk::Id>;
+
+    /// Pins a message above the message list, or unpins the current one if none is given.
+    /// Both sides of a chat may pin, in groups and channels only admins may.
+    async fn pin_message(
+        &self,
+        auth_id: &user::Id,
+        id: &talk::Id,
+        msg_id: Option<&message::Id>,
+    ) -> super::Result<Option<MessageDto>>;
+
+    fn find_pinned_message(
+        &self,
+        auth_id: &user::Id,
+        id: &talk::Id,
+    ) -> super::Result<Option<MessageDto>>;
+
+    async fn update_preferences(
+        &self,
+        auth_id: &user::Id,
+        id: &talk::Id,
+        change: PreferenceChange,
+    ) -> super::Result<()>;
+
+    /// Cached, as it is asked for every message delivered to the user.
+    async fn is_muted(&self, auth_id: &user::Id, id: &talk::Id) -> super::Result<bool>;
 }
 
 #[derive(Clone)]
 pub struct TalkServiceImpl {
     repo: Repository,
+    message_repo: message::Repository,
     user_service: user::Service,
     contact_service: contact::Service,
     event_service: event::Service,
@@ -56,6 +190,7 @@ pub struct TalkServiceImpl {
 impl TalkServiceImpl {
     pub fn new(
         repo: Repository,
+        message_repo: message::Repository,
         user_service: user::Service,
         contact_service: contact::Service,
         event_service: event::Service,
@@ -64,6 +199,7 @@ impl TalkServiceImpl {
     ) -> Self {
         Self {
             repo,
+            message_repo,
             user_service,
             contact_service,
             event_service,
@@ -87,6 +223,10 @@ impl TalkService for TalkServiceImpl {
             return Err(talk::Error::AlreadyExists);
         }
 
+        if self.contact_service.is_blocked(auth_id, recipient).await? {
+            return Err(talk::Error::Blocked(recipient.clone()));
+        }
+
         let contact = self
             .contact_service
             .find(auth_id, recipient)
@@ -147,6 +287,7 @@ impl TalkService for TalkServiceImpl {
                 return Err(talk::Error::NonExistingUser(m.clone()));
             }
         }
+        self.check_contacts(auth_id, members).await?;
 
         let details = Details::Group {
             name: name.into(),
@@ -160,15 +301,14 @@ impl TalkService for TalkServiceImpl {
             .map_err(|e| talk::Error::from(Box::new(e)))
             .await?;
 
-        let talk_dto = TalkDto::new(
+        let group = GroupTalk::new(
             id.clone(),
-            Picture::from(id.clone()),
-            name,
-            DetailsDto::Group {
-                owner: auth_id.clone(),
-                sender: auth_id.clone(),
-            },
+            Kind::Group,
             None,
+            auth_id.clone(),
+            name.to_string(),
+            0,
+            Role::Owner,
         );
 
         for m in members {
@@ -179,52 +319,248 @@ impl TalkService for TalkServiceImpl {
             self.event_service
                 .publish(
                     &event::Subject::Notifications(m),
-                    event::Notification::NewTalk(talk_dto.clone()).into(),
+                    event::Notification::NewTalk(group_to_member_dto(&group, m, &Role::Member))
+                        .into(),
                 )
                 .await;
         }
 
+        self.post_lifecycle(&id, auth_id, Lifecycle::Created(name))
+            .await;
+
+        let talk_dto = group_to_dto(&group, auth_id);
+
         Ok(talk_dto)
     }
 
-    fn find_by_id_and_user_id(
+    async fn create_channel(&self, auth_id: &user::Id, name: &str) -> super::Result<TalkDto> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(talk::Error::MissingName);
+        }
+
+        let details = Details::Channel {
+            name: name.into(),
+            owner: auth_id.clone(),
+        };
+
+        let id = self.repo.create(&NewTalk::new(&details))?;
+        self.s3
+            .generate(Blob::Png(&id.0.to_string()))
+            .map_err(|e| talk::Error::from(Box::new(e)))
+            .await?;
+
+        self.notify_channel_joined(&id, auth_id).await;
+        self.post_lifecycle(&id, auth_id, Lifecycle::Created(name))
+            .await;
+
+        let channel = GroupTalk::new(
+            id,
+            Kind::Channel,
+            None,
+            auth_id.clone(),
+            name.to_string(),
+            0,
+            Role::Owner,
+        );
+
+        Ok(group_to_dto(&channel, auth_id))
+    }
+
+    fn find_or_create_saved(&self, auth_id: &user::Id) -> super::Result<TalkDto> {
+        if let Some(saved) = self.repo.find_saved(auth_id)? {
+            return Ok(chat_to_dto(&saved, auth_id));
+        }
+
+        match self.repo.create(&NewTalk::new(&Details::Saved {
+            owner: auth_id.clone(),
+        })) {
+            // created by a concurrent request in the meantime
+            Ok(_) | Err(talk::Error::AlreadyExists) => {}
+            Err(e) => return Err(e),
+        }
+
+        self.repo
+            .find_saved(auth_id)?
+            .map(|s| chat_to_dto(&s, auth_id))
+            .ok_or(talk::Error::NotCreated)
+    }
+
+    async fn find_by_id_and_user_id(
         &self,
         kind: &Kind,
         id: &talk::Id,
         auth_id: &user::Id,
     ) -> super::Result<TalkDto> {
-        match kind {
-            Kind::Chat => self
-                .repo
-                .find_chat_by_id_and_user_id(id, auth_id)?
-                .map(|c| chat_to_dto(&c, auth_id)),
-            Kind::Group => self
+        let talk_dto = match kind {
+            Kind::Chat => match self.find_chat(id, auth_id)? {
+                Some(c) => {
+                    let blocked = self
+                        .contact_service
+                        .is_blocked(auth_id, c.recipient())
+                        .await?;
+                    Some(chat_to_dto(&c, auth_id).with_blocked(blocked))
+                }
+                None => None,
+            },
+            Kind::Group | Kind::Channel => self
                 .repo
                 .find_group_by_id_and_user_id(id, auth_id)?
                 .map(|g| group_to_dto(&g, auth_id)),
         }
-        .ok_or(super::Error::NotFound(id.clone()))
+        .ok_or(super::Error::NotFound(id.clone()))?;
+
+        let preferences = self
+            .repo
+            .find_preference(id, auth_id)?
+            .map(|p| PreferencesDto::from(&p))
+            .unwrap_or_default();
+
+        Ok(talk_dto.with_preferences(preferences))
+    }
+
+    fn find_kind(&self, id: &talk::Id) -> super::Result<Kind> {
+        self.repo
+            .find_kind(id)?
+            .ok_or_else(|| talk::Error::NotFound(id.clone()))
+    }
+
+    fn find_unsubscribed_channels(&self, auth_id: &user::Id) -> super::Result<Vec<ChannelSummary>> {
+        self.repo
+            .find_unsubscribed_channels(auth_id, DISCOVER_CHANNELS_LIMIT)
+    }
+
+    fn find_channel_ids(&self, auth_id: &user::Id) -> super::Result<Vec<talk::Id>> {
+        self.repo.find_channel_ids(auth_id)
+    }
+
+    async fn subscribe(&self, auth_id: &user::Id, id: &talk::Id) -> super::Result<TalkDto> {
+        if self.find_kind(id)?.ne(&Kind::Channel) {
+            return Err(talk::Error::NotFound(id.clone()));
+        }
+
+        if self.repo.find_role(id, auth_id)?.is_some() {
+            return Err(talk::Error::AlreadyMember(auth_id.clone()));
+        }
+
+        self.repo.add_members(id, std::slice::from_ref(auth_id))?;
+        self.redis.del(cache::Key::Members(id)).await;
+        self.notify_channel_joined(id, auth_id).await;
+
+        let channel = self
+            .repo
+            .find_group_by_id_and_user_id(id, auth_id)?
+            .ok_or_else(|| talk::Error::NotFound(id.clone()))?;
+
+        Ok(group_to_dto(&channel, auth_id))
+    }
+
+    async fn authorize_post(&self, auth_id: &user::Id, id: &talk::Id) -> super::Result<Kind> {
+        let kind = self.find_kind(id)?;
+        if kind.ne(&Kind::Channel) {
+            self.check_not_blocked(auth_id, id).await?;
+            return Ok(kind);
+        }
+
+        let role = self
+            .repo
+            .find_role(id, auth_id)?
+            .ok_or_else(|| talk::Error::NotFound(id.clone()))?;
+
+        if !role.permits(&Action::Post) {
+            return Err(talk::Error::PostingRestricted);
+        }
+
+        Ok(kind)
     }
 
-    fn find_all_by_kind(&self, auth_user: &auth::User, kind: &Kind) -> super::Result<Vec<TalkDto>> {
+    async fn check_not_blocked(&self, auth_id: &user::Id, id: &talk::Id) -> super::Result<()> {
+        let Some(chat) = self.repo.find_chat_by_id_and_user_id(id, auth_id)? else {
+            return Ok(());
+        };
+
+        if self
+            .contact_service
+            .is_blocked(auth_id, chat.recipient())
+            .await?
+        {
+            return Err(talk::Error::Blocked(chat.recipient().clone()));
+        }
+
+        Ok(())
+    }
+
+    fn find_all_by_kind(
+        &self,
+        auth_user: &auth::User,
+        kind: &Kind,
+        page: &Page,
+    ) -> super::Result<Vec<TalkDto>> {
         let auth_id = auth_user.id();
 
         let talk_dtos: Vec<TalkDto> = match kind {
-            Kind::Chat => self
+            Kind::Chat => {
+                // saved messages head the first page
+                let saved = match page.cursor() {
+                    Some(_) => None,
+                    None => self.repo.find_saved(auth_id)?,
+                };
+
+                saved
+                    .into_iter()
+                    .chain(self.repo.find_chats_by_user_id(auth_id, page)?)
+                    .map(|c| chat_to_dto(&c, auth_id))
+                    .collect()
+            }
+            Kind::Group | Kind::Channel => self
                 .repo
-                .find_chats_by_user_id(auth_id)?
+                .find_groups_by_user_id(auth_id, kind, page)?
                 .iter()
-                .map(|c| chat_to_dto(c, auth_id))
+                .map(|g| group_to_dto(g, auth_id))
                 .collect(),
-            Kind::Group => self
+        };
+
+        let talk_dtos: Vec<TalkDto> = self
+            .apply_preferences(auth_id, talk_dtos)?
+            .into_iter()
+            .filter(|t| !t.is_saved() || t.preferences().archived() == page.is_archived())
+            .collect();
+
+        Ok(talk_dtos)
+    }
+
+    fn search(
+        &self,
+        auth_user: &auth::User,
+        kind: &Kind,
+        query: &str,
+    ) -> super::Result<Vec<TalkDto>> {
+        let auth_id = auth_user.id();
+        let query = query.trim();
+
+        let talk_dtos: Vec<TalkDto> = match kind {
+            Kind::Chat => {
+                let saved = if SAVED_NAME.to_lowercase().contains(&query.to_lowercase()) {
+                    self.repo.find_saved(auth_id)?
+                } else {
+                    None
+                };
+
+                saved
+                    .into_iter()
+                    .chain(self.repo.search_chats(auth_id, query, Page::SIZE)?)
+                    .map(|c| chat_to_dto(&c, auth_id))
+                    .collect()
+            }
+            Kind::Group | Kind::Channel => self
                 .repo
-                .find_groups_by_user_id(auth_id)?
+                .search_groups(auth_id, kind, query, Page::SIZE)?
                 .iter()
                 .map(|g| group_to_dto(g, auth_id))
                 .collect(),
         };
 
-        Ok(talk_dtos)
+        self.apply_preferences(auth_id, talk_dtos)
     }
 
     async fn find_avatar(
@@ -252,17 +588,664 @@ impl TalkService for TalkServiceImpl {
     }
 
     async fn delete(&self, id: &talk::Id, auth_user: &auth::User) -> super::Result<()> {
-        self.repo.delete(auth_user.id(), id)?;
+        let auth_id = auth_user.id();
+
+        if self
+            .repo
+            .find_group_by_id_and_user_id(id, auth_id)?
+            .is_some()
+        {
+            self.authorize(auth_id, id, &Action::Delete)?;
+        } else if self.find_chat(id, auth_id)?.is_none() {
+            return Err(talk::Error::NotFound(id.clone()));
+        }
+
+        self.repo.delete(id)?;
+        self.redis.del(cache::Key::Members(id)).await;
+        Ok(())
+    }
+
+    async fn add_members(
+        &self,
+        auth_id: &user::Id,
+        id: &talk::Id,
+        members: &[user::Id],
+    ) -> super::Result<()> {
+        if members.is_empty() {
+            return Err(talk::Error::NoMembersSelected);
+        }
+
+        let group = self.authorize(auth_id, id, &Action::ManageMembers)?;
+
+        let current = self.user_service.find_members(id).await?;
+        for m in members {
+            if current.contains(m) {
+                return Err(talk::Error::AlreadyMember(m.clone()));
+            }
+
+            if !self.user_service.exists(m)? {
+                return Err(talk::Error::NonExistingUser(m.clone()));
+            }
+        }
+        self.check_contacts(auth_id, members).await?;
+
+        self.repo.add_members(id, members)?;
+        self.redis.del(cache::Key::Members(id)).await;
+
+        for m in members {
+            self.event_service
+                .publish(
+                    &event::Subject::Notifications(m),
+                    event::Notification::NewTalk(group_to_member_dto(&group, m, &Role::Member))
+                        .into(),
+                )
+                .await;
+
+            self.post_lifecycle(id, auth_id, Lifecycle::Added(m)).await;
+        }
+
+        Ok(())
+    }
+
+    async fn remove_member(
+        &self,
+        auth_id: &user::Id,
+        id: &talk::Id,
+        member: &user::Id,
+    ) -> super::Result<()> {
+        let group = self.authorize(auth_id, id, &Action::ManageMembers)?;
+
+        let role = self
+            .repo
+            .find_role(id, member)?
+            .ok_or_else(|| talk::Error::NotMember(member.clone()))?;
+
+        if !group.role().outranks(&role) {
+            return Err(talk::Error::Forbidden);
+        }
+
+        self.repo.remove_member(id, member)?;
+        self.redis.del(cache::Key::Members(id)).await;
+        self.notify_removed(id, member).await;
+        self.post_lifecycle(id, auth_id, Lifecycle::Removed(member))
+            .await;
+
+        Ok(())
+    }
+
+    async fn leave(&self, auth_id: &user::Id, id: &talk::Id) -> super::Result<()> {
+        let group = self
+            .repo
+            .find_group_by_id_and_user_id(id, auth_id)?
+            .ok_or_else(|| talk::Error::NotFound(id.clone()))?;
+
+        if group.role().eq(&Role::Owner) {
+            return Err(talk::Error::OwnerCannotLeave);
+        }
+
+        self.repo.remove_member(id, auth_id)?;
         self.redis.del(cache::Key::Members(id)).await;
+        self.notify_removed(id, auth_id).await;
+
+        // subscribers come and go too often to announce
+        if group.kind().ne(&Kind::Channel) {
+            self.post_lifecycle(id, auth_id, Lifecycle::Left).await;
+        }
+
         Ok(())
     }
+
+    fn find_roles(&self, id: &talk::Id) -> super::Result<Vec<(user::Id, Role)>> {
+        self.repo.find_roles(id)
+    }
+
+    async fn set_role(
+        &self,
+        auth_id: &user::Id,
+        id: &talk::Id,
+        member: &user::Id,
+        role: &Role,
+    ) -> super::Result<()> {
+        if role.eq(&Role::Owner) {
+            return Err(talk::Error::UnsupportedRole(role.as_str().to_string()));
+        }
+
+        let group = self.authorize(auth_id, id, &Action::ManageRoles)?;
+
+        let current = self
+            .repo
+            .find_role(id, member)?
+            .ok_or_else(|| talk::Error::NotMember(member.clone()))?;
+
+        if !group.role().outranks(&current) {
+            return Err(talk::Error::Forbidden);
+        }
+
+        self.repo.update_role(id, member, role)?;
+        Ok(())
+    }
+
+    async fn transfer_ownership(
+        &self,
+        auth_id: &user::Id,
+        id: &talk::Id,
+        new_owner: &user::Id,
+    ) -> super::Result<()> {
+        self.authorize(auth_id, id, &Action::TransferOwnership)?;
+
+        if auth_id.eq(new_owner) {
+            return Ok(());
+        }
+
+        if self.repo.find_role(id, new_owner)?.is_none() {
+            return Err(talk::Error::NotMember(new_owner.clone()));
+        }
+
+        self.repo.transfer_ownership(id, auth_id, new_owner)?;
+        Ok(())
+    }
+
+    async fn rename(&self, auth_id: &user::Id, id: &talk::Id, name: &str) -> super::Result<()> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(talk::Error::MissingName);
+        }
+
+        let group = self.authorize(auth_id, id, &Action::Rename)?;
+
+        self.repo.update_name(id, name)?;
+        self.post_lifecycle(id, auth_id, Lifecycle::Renamed(name))
+            .await;
+        self.notify_updated(id, name, &group.picture()).await
+    }
+
+    async fn announce_erasure(&self, name: &str, erasure: &Erasure) {
+        for id in &erasure.groups {
+            // subscribers come and go too often to announce
+            if !matches!(self.repo.find_kind(id), Ok(Some(Kind::Channel))) {
+                self.post_lifecycle(id, &user::Id::DELETED, Lifecycle::Deleted(name))
+                    .await;
+            }
+        }
+
+        for (id, owner) in &erasure.owners {
+            self.post_lifecycle(id, owner, Lifecycle::TookOver).await;
+        }
+    }
+
+    async fn update_avatar(
+        &self,
+        auth_id: &user::Id,
+        id: &talk::Id,
+        data: Bytes,
+    ) -> super::Result<()> {
+        let group = self.authorize(auth_id, id, &Action::ChangeAvatar)?;
+
+        let png = tokio::task::spawn_blocking(move || resize_avatar(&data)).await??;
+        self.s3
+            .put(Blob::Png(&id.0.to_string()), png)
+            .map_err(|e| talk::Error::from(Box::new(e)))
+            .await?;
+
+        let v = self.repo.bump_avatar_version(id)?;
+        let picture = Picture::from(id.clone()).versioned(v);
+        self.notify_updated(id, group.name(), &picture).await
+    }
+
+    async fn reset_avatar(&self, auth_id: &user::Id, id: &talk::Id) -> super::Result<()> {
+        let group = self.authorize(auth_id, id, &Action::ChangeAvatar)?;
+
+        self.s3
+            .generate(Blob::Png(&id.0.to_string()))
+            .map_err(|e| talk::Error::from(Box::new(e)))
+            .await?;
+
+        let v = self.repo.bump_avatar_version(id)?;
+        let picture = Picture::from(id.clone()).versioned(v);
+        self.notify_updated(id, group.name(), &picture).await
+    }
+
+    fn create_invite(
+        &self,
+        auth_id: &user::Id,
+        id: &talk::Id,
+        expires_in: Option<Duration>,
+        max_uses: Option<i32>,
+    ) -> super::Result<()> {
+        if expires_in.is_some_and(|e| e <= Duration::zero()) {
+            return Err(talk::Error::InvalidInvite("expiry must be positive".into()));
+        }
+
+        if max_uses.is_some_and(|m| m <= 0) {
+            return Err(talk::Error::InvalidInvite(
+                "max uses must be positive".into(),
+            ));
+        }
+
+        self.authorize(auth_id, id, &Action::ManageMembers)?;
+
+        let token = Uuid::new_v4().simple().to_string();
+        let expires_at = expires_in.map(|e| Utc::now() + e);
+
+        self.repo
+            .create_invite(&NewInvite::new(&token, id, auth_id, expires_at, max_uses))
+    }
+
+    fn find_invites(&self, auth_id: &user::Id, id: &talk::Id) -> super::Result<Vec<Invite>> {
+        self.authorize(auth_id, id, &Action::ManageMembers)?;
+        self.repo.find_invites(id)
+    }
+
+    fn revoke_invite(&self, auth_id: &user::Id, id: &talk::Id, token: &str) -> super::Result<()> {
+        self.authorize(auth_id, id, &Action::ManageMembers)?;
+
+        if !self.repo.delete_invite(id, token)? {
+            return Err(talk::Error::InviteNotFound);
+        }
+
+        Ok(())
+    }
+
+    fn find_invite(&self, token: &str) -> super::Result<(Invite, String, Picture)> {
+        let (invite, name, avatar_version) = self
+            .repo
+            .find_invite(token)?
+            .ok_or(talk::Error::InviteNotFound)?;
+
+        if !invite.is_active(&Utc::now()) {
+            return Err(talk::Error::InviteExpired);
+        }
+
+        let picture = Picture::from(invite.group_id().clone()).versioned(avatar_version);
+        Ok((invite, name, picture))
+    }
+
+    async fn join(&self, auth_id: &user::Id, token: &str) -> super::Result<talk::Id> {
+        let (invite, ..) = self.find_invite(token)?;
+        let id = invite.group_id();
+
+        // following the link again just opens the group
+        if self.repo.find_role(id, auth_id)?.is_some() {
+            return Ok(id.clone());
+        }
+
+        if !self.repo.redeem_invite(token, auth_id)? {
+            return Err(talk::Error::InviteExpired);
+        }
+
+        self.redis.del(cache::Key::Members(id)).await;
+
+        if let Some(group) = self.repo.find_group_by_id_and_user_id(id, auth_id)? {
+            self.event_service
+                .publish(
+                    &event::Subject::Notifications(auth_id),
+                    event::Notification::NewTalk(group_to_dto(&group, auth_id)).into(),
+                )
+                .await;
+
+            if group.kind().ne(&Kind::Channel) {
+                self.post_lifecycle(id, auth_id, Lifecycle::Joined).await;
+            }
+        }
+
+        Ok(id.clone())
+    }
+
+    async fn pin_message(
+        &self,
+        auth_id: &user::Id,
+        id: &talk::Id,
+        msg_id: Option<&message::Id>,
+    ) -> super::Result<Option<MessageDto>> {
+        match self.find_kind(id)? {
+            Kind::Chat => {
+                if self.find_chat(id, auth_id)?.is_none() {
+                    return Err(talk::Error::NotFound(id.clone()));
+                }
+            }
+            Kind::Group | Kind::Channel => {
+                self.authorize(auth_id, id, &Action::PinMessage)?;
+            }
+        }
+
+        if !self.repo.pin_message(id, msg_id)? {
+            return Err(talk::Error::NotFound(id.clone()));
+        }
+
+        let pinned = self.repo.find_pinned_message(id)?.map(MessageDto::from);
+
+        let (kind, members) = self.find_recipients(id).await?;
+        let subjects = event::Subject::messages(id, &kind, &members);
+
+        self.event_service
+            .broadcast(
+                &subjects,
+                event::Message::Pinned {
+                    talk_id: id.clone(),
+                    msg: pinned.clone(),
+                }
+                .into(),
+            )
+            .await;
+
+        Ok(pinned)
+    }
+
+    fn find_pinned_message(
+        &self,
+        auth_id: &user::Id,
+        id: &talk::Id,
+    ) -> super::Result<Option<MessageDto>> {
+        let is_member = match self.find_kind(id)? {
+            Kind::Chat => self.find_chat(id, auth_id)?.is_some(),
+            Kind::Group | Kind::Channel => self.repo.find_role(id, auth_id)?.is_some(),
+        };
+
+        if !is_member {
+            return Err(talk::Error::NotFound(id.clone()));
+        }
+
+        Ok(self.repo.find_pinned_message(id)?.map(MessageDto::from))
+    }
+
+    async fn update_preferences(
+        &self,
+        auth_id: &user::Id,
+        id: &talk::Id,
+        change: PreferenceChange,
+    ) -> super::Result<()> {
+        let is_member = self.find_chat(id, auth_id)?.is_some()
+            || self
+                .repo
+                .find_group_by_id_and_user_id(id, auth_id)?
+                .is_some();
+
+        if !is_member {
+            return Err(talk::Error::NotFound(id.clone()));
+        }
+
+        let mut preferences = self
+            .repo
+            .find_preference(id, auth_id)?
+            .unwrap_or_else(|| Preferences::new(id.clone(), auth_id.clone()));
+
+        preferences.apply(change);
+        self.repo.save_preferences(&preferences)?;
+        self.redis.del(cache::Key::Muted(auth_id, id)).await;
+
+        Ok(())
+    }
+
+    async fn is_muted(&self, auth_id: &user::Id, id: &talk::Id) -> super::Result<bool> {
+        let key = cache::Key::Muted(auth_id, id);
+        let muted_until = match self.redis.get::<i64>(key.clone()).await {
+            Some(m) => m,
+            None => {
+                let m = self
+                    .repo
+                    .find_preference(id, auth_id)?
+                    .and_then(|p| p.muted_until())
+                    .map_or(0, |m| m.timestamp());
+                self.redis.set_ex(key, m).await;
+                m
+            }
+        };
+
+        Ok(muted_until > Utc::now().timestamp())
+    }
 }
 
+impl TalkServiceImpl {
+    /// Finds a chat including the self-talk of the user.
+    fn find_chat(&self, id: &talk::Id, auth_id: &user::Id) -> super::Result<Option<ChatTalk>> {
+        if let Some(chat) = self.repo.find_chat_by_id_and_user_id(id, auth_id)? {
+            return Ok(Some(chat));
+        }
+
+        Ok(self.repo.find_saved(auth_id)?.filter(|s| s.id().eq(id)))
+    }
+
+    fn apply_preferences(
+        &self,
+        auth_id: &user::Id,
+        talk_dtos: Vec<TalkDto>,
+    ) -> super::Result<Vec<TalkDto>> {
+        let mut preferences: HashMap<talk::Id, PreferencesDto> = self
+            .repo
+            .find_preferences(auth_id)?
+            .iter()
+            .map(|p| (p.talk_id().clone(), PreferencesDto::from(p)))
+            .collect();
+
+        Ok(talk_dtos
+            .into_iter()
+            .map(|t| match preferences.remove(t.id()) {
+                Some(p) => t.with_preferences(p),
+                None => t,
+            })
+            .collect())
+    }
+
+    /// Fails if any of the members is blocked by or has blocked the user adding them,
+    /// or does not let them add to groups.
+    async fn check_contacts(&self, auth_id: &user::Id, members: &[user::Id]) -> super::Result<()> {
+        for m in members {
+            if self.contact_service.is_blocked(auth_id, m).await? {
+                return Err(talk::Error::Blocked(m.clone()));
+            }
+
+            if m.eq(auth_id) {
+                continue;
+            }
+
+            let audience = self.user_service.find_privacy(m).await?.group_adds_from();
+            if audience.ne(&user::Audience::Everyone)
+                && !audience.admits(self.contact_service.find_relation(auth_id, m)?)
+            {
+                return Err(talk::Error::AddsRestricted(m.clone()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Central permission check for group operations.
+    /// Returns the group as seen by the authenticated user if the action is permitted.
+    fn authorize(&self, auth_id: &user::Id, id: &talk::Id, a: &Action) -> super::Result<GroupTalk> {
+        let group = self
+            .repo
+            .find_group_by_id_and_user_id(id, auth_id)?
+            .ok_or_else(|| talk::Error::NotFound(id.clone()))?;
+
+        if !group.role().permits(a) {
+            return Err(talk::Error::Forbidden);
+        }
+
+        Ok(group)
+    }
+
+    async fn notify_updated(
+        &self,
+        id: &talk::Id,
+        name: &str,
+        picture: &Picture,
+    ) -> super::Result<()> {
+        for m in self.user_service.find_members(id).await? {
+            self.event_service
+                .publish(
+                    &event::Subject::Notifications(&m),
+                    event::Notification::TalkUpdated {
+                        talk_id: id.clone(),
+                        name: name.to_string(),
+                        picture: picture.clone(),
+                    }
+                    .into(),
+                )
+                .await;
+        }
+
+        Ok(())
+    }
+
+    /// Posts a system message about a lifecycle event to all members of the talk.
+    /// Failures are only logged, the event itself has already happened.
+    async fn post_lifecycle(&self, id: &talk::Id, actor: &user::Id, l: Lifecycle<'_>) {
+        let text = match self.lifecycle_text(actor, &l).await {
+            Ok(t) => t,
+            Err(e) => {
+                error!("could not describe talk lifecycle event: {e:?}");
+                return;
+            }
+        };
+
+        let msg = match self
+            .message_repo
+            .insert(&NewMessage::system(id, actor, &text))
+        {
+            Ok(m) => MessageDto::from(m),
+            Err(e) => {
+                error!("could not create system message: {e:?}");
+                return;
+            }
+        };
+
+        let (kind, members) = match self.find_recipients(id).await {
+            Ok(r) => r,
+            Err(e) => {
+                error!("could not find talk members: {e:?}");
+                return;
+            }
+        };
+
+        let msg_subjects = event::Subject::messages(id, &kind, &members);
+
+        self.event_service
+            .broadcast(&msg_subjects, event::Message::New(msg.clone()).into())
+            .await;
+
+        let noti_subjects = event::Subject::message_notifications(id, &kind, &members);
+
+        self.event_service
+            .broadcast(
+                &noti_subjects,
+                event::Notification::NewMessage {
+                    talk_id: id.clone(),
+                    last_message: msg,
+                    muted: false,
+                }
+                .into(),
+            )
+            .await;
+    }
+
+    /// Kind of the talk and the members message events go out to,
+    /// none for channels as their subscribers share subjects.
+    async fn find_recipients(&self, id: &talk::Id) -> super::Result<(Kind, HashSet<user::Id>)> {
+        let kind = self.find_kind(id)?;
+        if kind.eq(&Kind::Channel) {
+            return Ok((kind, HashSet::new()));
+        }
+
+        Ok((kind, self.user_service.find_members(id).await?))
+    }
+
+    async fn lifecycle_text(&self, actor: &user::Id, l: &Lifecycle<'_>) -> super::Result<String> {
+        let actor = self.user_service.find_name(actor).await?;
+
+        let text = match l {
+            Lifecycle::Created(name) => format!("{actor} created \"{name}\""),
+            Lifecycle::Renamed(name) => format!("{actor} changed the name to \"{name}\""),
+            Lifecycle::Joined => format!("{actor} joined"),
+            Lifecycle::Left => format!("{actor} left"),
+            Lifecycle::Deleted(name) => format!("{name} deleted their account"),
+            Lifecycle::TookOver => format!("{actor} is now the owner"),
+            Lifecycle::Added(m) => {
+                format!("{actor} added {}", self.user_service.find_name(m).await?)
+            }
+            Lifecycle::Removed(m) => {
+                format!("{actor} removed {}", self.user_service.find_name(m).await?)
+            }
+        };
+
+        Ok(text)
+    }
+
+    async fn notify_channel_joined(&self, id: &talk::Id, member: &user::Id) {
+        self.event_service
+            .publish(
+                &event::Subject::Notifications(member),
+                event::Notification::ChannelJoined {
+                    talk_id: id.clone(),
+                }
+                .into(),
+            )
+            .await;
+    }
+
+    async fn notify_removed(&self, id: &talk::Id, member: &user::Id) {
+        self.event_service
+            .publish(
+                &event::Subject::Notifications(member),
+                event::Notification::TalkRemoved {
+                    talk_id: id.clone(),
+                }
+                .into(),
+            )
+            .await;
+    }
+}
+
+enum Lifecycle<'a> {
+    Created(&'a str),
+    Renamed(&'a str),
+    Joined,
+    Left,
+    Added(&'a user::Id),
+    Removed(&'a user::Id),
+    Deleted(&'a str),
+    TookOver,
+}
+
+const DISCOVER_CHANNELS_LIMIT: i64 = 20;
+const AVATAR_SIZE: u32 = 256;
+const AVATAR_MAX_BYTES: usize = 1024 * 1024;
+
+/// Validates an uploaded image and converts it into a square PNG avatar.
+fn resize_avatar(data: &[u8]) -> super::Result<Vec<u8>> {
+    if data.is_empty() {
+        return Err(talk::Error::InvalidAvatar("empty file".into()));
+    }
+
+    if data.len() > AVATAR_MAX_BYTES {
+        return Err(talk::Error::InvalidAvatar(format!(
+            "file exceeds {AVATAR_MAX_BYTES} bytes"
+        )));
+    }
+
+    let img =
+        image::load_from_memory(data).map_err(|e| talk::Error::InvalidAvatar(e.to_string()))?;
+
+    let mut png = Vec::new();
+    img.resize_to_fill(AVATAR_SIZE, AVATAR_SIZE, FilterType::Triangle)
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(|e| talk::Error::InvalidAvatar(e.to_string()))?;
+
+    Ok(png)
+}
+
+const SAVED_NAME: &str = "Saved messages";
+
 fn chat_to_dto(c: &ChatTalk, auth_id: &user::Id) -> TalkDto {
+    let name = if c.recipient().eq(auth_id) {
+        SAVED_NAME
+    } else {
+        c.name()
+    };
+
     TalkDto::new(
         c.id().clone(),
         Picture::from(user::Picture::from(c.picture().to_string())),
-        c.name(),
+        name,
         DetailsDto::Chat {
             sender: auth_id.clone(),
             recipient: c.recipient().clone(),
@@ -272,14 +1255,30 @@ fn chat_to_dto(c: &ChatTalk, auth_id: &user::Id) -> TalkDto {
 }
 
 fn group_to_dto(g: &GroupTalk, auth_id: &user::Id) -> TalkDto {
+    group_to_member_dto(g, auth_id, g.role())
+}
+
+fn group_to_member_dto(g: &GroupTalk, member: &user::Id, role: &Role) -> TalkDto {
+    let (owner, sender, role) = (g.owner().clone(), member.clone(), role.clone());
+    let details = match g.kind() {
+        Kind::Channel => DetailsDto::Channel {
+            owner,
+            sender,
+            role,
+        },
+        Kind::Group => DetailsDto::Group {
+            owner,
+            sender,
+            role,
+        },
+        Kind::Chat => unreachable!("chats are never loaded as groups"),
+    };
+
     TalkDto::new(
         g.id().clone(),
-        Picture::from(g.id().clone()),
+        g.picture(),
         g.name(),
-        DetailsDto::Group {
-            owner: g.owner().clone(),
-            sender: auth_id.clone(),
-        },
+        details,
         g.last_message().map(|m| MessageDto::from(m.clone())),
     )
 }
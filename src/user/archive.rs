@@ -0,0 +1,100 @@
+use std::io::{Cursor, Write};
+
+use chrono::{Datelike, Timelike, Utc};
+use serde::Serialize;
+use zip::{CompressionMethod, DateTime, ZipWriter, write::SimpleFileOptions};
+
+/// Zip archive built in memory, entries are deflated.
+pub struct Zip {
+    writer: ZipWriter<Cursor<Vec<u8>>>,
+    options: SimpleFileOptions,
+}
+
+impl Default for Zip {
+    fn default() -> Self {
+        let options = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            .last_modified_time(now());
+
+        Self {
+            writer: ZipWriter::new(Cursor::new(Vec::new())),
+            options,
+        }
+    }
+}
+
+impl Zip {
+    pub fn json<T>(self, name: &str, value: &T) -> super::Result<Self>
+    where
+        T: Serialize + ?Sized,
+    {
+        let content = serde_json::to_vec_pretty(value)?;
+        self.file(name, &content)
+    }
+
+    pub fn file(mut self, name: &str, content: &[u8]) -> super::Result<Self> {
+        self.writer.start_file(name, self.options)?;
+        self.writer.write_all(content)?;
+
+        Ok(self)
+    }
+
+    pub fn finish(self) -> super::Result<Vec<u8>> {
+        let cursor = self.writer.finish()?;
+        Ok(cursor.into_inner())
+    }
+}
+
+/// Entries carry the MS-DOS timestamp of when the archive was built.
+fn now() -> DateTime {
+    let now = Utc::now();
+
+    let date_time = u16::try_from(now.year()).ok().and_then(|year| {
+        DateTime::from_date_and_time(
+            year,
+            u8::try_from(now.month()).ok()?,
+            u8::try_from(now.day()).ok()?,
+            u8::try_from(now.hour()).ok()?,
+            u8::try_from(now.minute()).ok()?,
+            u8::try_from(now.second()).ok()?,
+        )
+        .ok()
+    });
+
+    date_time.unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{Cursor, Read};
+
+    use zip::ZipArchive;
+
+    use super::Zip;
+
+    #[test]
+    fn should_read_back_written_entries() {
+        let data = Zip::default()
+            .json("profile.json", &serde_json::json!({ "name": "Ion" }))
+            .and_then(|z| z.file("notes.txt", "ăîșț".repeat(100).as_bytes()))
+            .and_then(Zip::finish)
+            .expect("archive should be written");
+
+        let mut archive = ZipArchive::new(Cursor::new(data)).expect("archive should be readable");
+        assert_eq!(archive.len(), 2);
+
+        let mut profile = String::new();
+        archive
+            .by_name("profile.json")
+            .and_then(|mut f| Ok(f.read_to_string(&mut profile)?))
+            .expect("profile.json should be present");
+        assert_eq!(profile, "{\n  \"name\": \"Ion\"\n}");
+
+        let mut notes = String::new();
+        archive
+            .by_name("notes.txt")
+            .and_then(|mut f| Ok(f.read_to_string(&mut notes)?))
+            .expect("notes.txt should be present");
+        assert_eq!(notes, "ăîșț".repeat(100));
+    }
+}
@@ -4,27 +4,45 @@ impl From<super::Error> for StatusCode {
     fn from(e: super::Error) -> Self {
         match e {
             super::Error::NotMember => Self::FORBIDDEN,
+            super::Error::InvalidNickname(_)
+            | super::Error::InvalidName
+            | super::Error::BioTooLong(_)
+            | super::Error::UnsupportedPresence(_)
+            | super::Error::UnsupportedAudience(_) => Self::BAD_REQUEST,
+            super::Error::NicknameTaken(_) => Self::CONFLICT,
             super::Error::MalformedPicture(_)
             | super::Error::MalformedEmail(_)
+            | super::Error::_Contact(_)
             | super::Error::_R2d2(_)
-            | super::Error::_Diesel(_) => Self::INTERNAL_SERVER_ERROR,
+            | super::Error::_Diesel(_)
+            | super::Error::_Io(_)
+            | super::Error::_SerdeJson(_)
+            | super::Error::_Zip(_) => Self::INTERNAL_SERVER_ERROR,
         }
     }
 }
 
 pub(super) mod api {
-    use axum::{Extension, Form, extract::State};
+    use axum::{
+        Extension, Form,
+        extract::State,
+        http::header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+        response::IntoResponse,
+    };
     use maud::{Markup, Render, html};
     use serde::Deserialize;
 
     use crate::{
-        auth, contact,
-        user::{self, Nickname, markup},
+        auth, contact, message,
+        talk::{self, Kind, model::Page},
+        user::{self, Audience, Nickname, Presence, archive::Zip, markup, model::Privacy},
     };
 
     #[derive(Deserialize)]
     pub struct FindParams {
-        nickname: Nickname,
+        query: String,
+        #[serde(default)]
+        page: u32,
     }
 
     pub async fn search(
@@ -33,17 +51,212 @@ pub(super) mod api {
         contact_service: State<contact::Service>,
         params: Form<FindParams>,
     ) -> crate::Result<Markup> {
-        if params.nickname.is_empty() {
+        if params.query.trim().is_empty() {
             return Ok(html! {(crate::markup::EMPTY)});
         }
 
-        let users = user_service.search(&params.nickname, &auth_user)?;
+        let users = user_service
+            .search(&params.query, &auth_user, params.page)
+            .await?;
 
         let contacts = contact_service
             .find_by_user_id(auth_user.id())
             .await
             .unwrap_or_else(|_| Vec::with_capacity(0));
 
-        Ok(markup::SearchResult::new(&contacts, &users).render())
+        Ok(markup::SearchResult::new(&contacts, &users, &params.query, params.page).render())
+    }
+
+    #[derive(Deserialize)]
+    pub struct ProfileParams {
+        name: String,
+        nickname: Nickname,
+        bio: Option<String>,
+    }
+
+    pub async fn update_profile(
+        auth_user: Extension<auth::User>,
+        user_service: State<user::Service>,
+        params: Form<ProfileParams>,
+    ) -> crate::Result<Markup> {
+        let u = user_service
+            .update_profile(
+                &auth_user,
+                &params.name,
+                &params.nickname,
+                params.bio.as_deref(),
+            )
+            .await?;
+
+        Ok(markup::ProfileForm {
+            user: &u,
+            saved: true,
+        }
+        .render())
+    }
+
+    #[derive(Deserialize)]
+    pub struct PresenceParams {
+        presence: Presence,
+    }
+
+    pub async fn set_presence(
+        auth_user: Extension<auth::User>,
+        user_service: State<user::Service>,
+        params: Form<PresenceParams>,
+    ) -> crate::Result<Markup> {
+        user_service
+            .set_presence(&auth_user, params.presence)
+            .await?;
+
+        Ok(markup::PresenceSelect(params.presence).render())
+    }
+
+    #[derive(Deserialize)]
+    pub struct PrivacyParams {
+        discoverable_by: Audience,
+        contact_requests_from: Audience,
+        group_adds_from: Audience,
+        // unchecked boxes are not submitted
+        #[serde(default)]
+        share_presence: bool,
+        #[serde(default)]
+        share_read_receipts: bool,
+    }
+
+    pub async fn update_privacy(
+        auth_user: Extension<auth::User>,
+        user_service: State<user::Service>,
+        params: Form<PrivacyParams>,
+    ) -> crate::Result<Markup> {
+        let p = Privacy::new(
+            auth_user.id().clone(),
+            params.discoverable_by,
+            params.contact_requests_from,
+            params.group_adds_from,
+            params.share_presence,
+            params.share_read_receipts,
+        );
+        user_service.update_privacy(&auth_user, &p).await?;
+
+        Ok(markup::PrivacyForm {
+            privacy: &p,
+            saved: true,
+        }
+        .render())
+    }
+
+    pub async fn touch(auth_user: Extension<auth::User>, user_service: State<user::Service>) {
+        user_service.touch(auth_user.id()).await;
+    }
+
+    pub async fn export(
+        auth_user: Extension<auth::User>,
+        user_service: State<user::Service>,
+        contact_service: State<contact::Service>,
+        talk_service: State<talk::Service>,
+        message_service: State<message::Service>,
+    ) -> crate::Result<impl IntoResponse> {
+        let profile = user_service.find_one(auth_user.id()).await?;
+        let contacts = contact_service.find_by_user_id(auth_user.id()).await?;
+
+        // queries and compression would hold up the runtime for large histories
+        let zip = tokio::task::spawn_blocking(move || -> crate::Result<Vec<u8>> {
+            let mut talks = Vec::new();
+            for kind in [Kind::Chat, Kind::Group, Kind::Channel] {
+                for page in [Page::unlimited(), Page::archived()] {
+                    talks.extend(talk_service.find_all_by_kind(&auth_user, &kind, &page)?);
+                }
+            }
+
+            let messages = message_service.find_by_owner(auth_user.id())?;
+
+            let zip = Zip::default()
+                .json("profile.json", &profile)?
+                .json("contacts.json", &contacts)?
+                .json("talks.json", &talks)?
+                .json("messages.json", &messages)?
+                .finish()?;
+
+            Ok(zip)
+        })
+        .await??;
+
+        Ok((
+            [
+                (CONTENT_TYPE, "application/zip"),
+                (
+                    CONTENT_DISPOSITION,
+                    "attachment; filename=\"messenger-data.zip\"",
+                ),
+            ],
+            zip,
+        ))
+    }
+
+    pub async fn delete_account(
+        auth_user: Extension<auth::User>,
+        user_service: State<user::Service>,
+        talk_service: State<talk::Service>,
+        auth_service: State<auth::Service>,
+    ) -> crate::Result<impl IntoResponse> {
+        let erasure = user_service.delete_account(&auth_user).await?;
+        auth_service.revoke_sessions(auth_user.sub()).await;
+        talk_service
+            .announce_erasure(auth_user.name(), &erasure)
+            .await;
+
+        Ok([("HX-Redirect", "/login")])
+    }
+}
+
+pub(super) mod templates {
+    use axum::{
+        Extension,
+        extract::{Path, State},
+    };
+    use maud::{Markup, Render};
+
+    use crate::{
+        auth,
+        user::{self, markup},
+    };
+
+    pub async fn presence(
+        auth_user: Extension<auth::User>,
+        user_service: State<user::Service>,
+        Path(id): Path<user::Id>,
+    ) -> crate::Result<Markup> {
+        let status = user_service
+            .find_online_status_for(auth_user.id(), &id)
+            .await?;
+
+        Ok(markup::LastSeen(&status).render())
+    }
+
+    pub async fn privacy(
+        auth_user: Extension<auth::User>,
+        user_service: State<user::Service>,
+    ) -> crate::Result<Markup> {
+        let p = user_service.find_privacy(auth_user.id()).await?;
+
+        Ok(markup::PrivacyForm {
+            privacy: &p,
+            saved: false,
+        }
+        .render())
+    }
+
+    pub async fn profile(
+        auth_user: Extension<auth::User>,
+        user_service: State<user::Service>,
+    ) -> crate::Result<Markup> {
+        let u = user_service.find_one(auth_user.id()).await?;
+
+        Ok(markup::ProfileForm {
+            user: &u,
+            saved: false,
+        }
+        .render())
     }
 }
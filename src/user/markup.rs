@@ -7,11 +7,13 @@ use crate::{
     markup::IdExt,
     talk::markup::TALK_WINDOW_TARGET,
 };
+use chrono::Utc;
 use maud::{Markup, Render, html};
+use messenger_service::AsStr;
 
 use super::{
-    Sub,
-    model::{OnlineStatus, UserDto},
+    Audience, Presence, Sub,
+    model::{OnlineStatus, Privacy, UserDto},
 };
 
 impl Display for Sub {
@@ -52,8 +54,8 @@ impl Render for Search {
         html! {
             input ."mb-4 w-full px-3 py-2 border border-gray-300 rounded-md focus:outline-none"
                 type="search"
-                name="nickname"
-                placeholder="Search users..."
+                name="query"
+                placeholder="Search by name, nickname or email..."
                 autocomplete="off"
                 hx-post="/api/users/search"
                 hx-trigger="input changed delay:500ms"
@@ -95,6 +97,12 @@ impl Render for AddContact<'_> {
                 hx-swap="outerHTML"
             {
                 input type="hidden" name="user_id" value=(self.0) {}
+                input ."px-2 py-1 mr-2 border border-gray-300 rounded-lg text-xs focus:outline-none"
+                    type="text"
+                    name="note"
+                    placeholder="Add a note..."
+                    maxlength=(contact::NOTE_MAX_LEN)
+                    autocomplete="off" {}
                 input ."px-2 py-1 text-white bg-green-700 hover:bg-green-800 font-medium rounded-lg text-xs focus:outline-none"
                     type="submit"
                     value="Add contact" {}
@@ -103,42 +111,220 @@ impl Render for AddContact<'_> {
     }
 }
 
+/// Profile editor shown in settings.
+pub struct ProfileForm<'a> {
+    pub user: &'a UserDto,
+    pub saved: bool,
+}
+
+impl Render for ProfileForm<'_> {
+    fn render(&self) -> Markup {
+        let input_class = "w-full px-3 py-2 border border-gray-300 rounded-md focus:outline-none";
+
+        html! {
+            form #profile-form ."flex flex-col space-y-2 p-3"
+                hx-put="/api/users/profile"
+                hx-target="this"
+                hx-swap="outerHTML"
+            {
+                label ."text-sm text-gray-500" for="profile-name" { "Display name" }
+                input #profile-name .(input_class)
+                    type="text"
+                    name="name"
+                    required
+                    maxlength=(user::NAME_MAX_LEN)
+                    value=(self.user.name()) {}
+                label ."text-sm text-gray-500" for="profile-nickname" { "Nickname" }
+                input #profile-nickname .(input_class)
+                    type="text"
+                    name="nickname"
+                    required
+                    minlength="3"
+                    maxlength="32"
+                    pattern="[A-Za-z0-9_.\\-]+"
+                    value=(self.user.nickname().0) {}
+                label ."text-sm text-gray-500" for="profile-bio" { "Bio" }
+                textarea #profile-bio .(input_class)
+                    name="bio"
+                    rows="2"
+                    maxlength=(user::BIO_MAX_LEN) { (self.user.bio().unwrap_or_default()) }
+                div ."flex items-center space-x-2" {
+                    input ."px-4 py-2 text-white bg-green-700 hover:bg-green-800 rounded-md cursor-pointer"
+                        type="submit"
+                        value="Save" {}
+                    @if self.saved {
+                        span ."text-green-700" { "Saved" }
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub struct PrivacyForm<'a> {
+    pub privacy: &'a Privacy,
+    pub saved: bool,
+}
+
+impl Render for PrivacyForm<'_> {
+    fn render(&self) -> Markup {
+        let audiences = [
+            (
+                "discoverable_by",
+                "Who can find me",
+                self.privacy.discoverable_by(),
+            ),
+            (
+                "contact_requests_from",
+                "Who can send me contact requests",
+                self.privacy.contact_requests_from(),
+            ),
+            (
+                "group_adds_from",
+                "Who can add me to groups",
+                self.privacy.group_adds_from(),
+            ),
+        ];
+
+        html! {
+            form #privacy-form ."flex flex-col space-y-2 p-3"
+                hx-put="/api/users/privacy"
+                hx-target="this"
+                hx-swap="outerHTML"
+            {
+                @for (name, label, selected) in audiences {
+                    label ."text-sm text-gray-500" for=(name) { (label) }
+                    (AudienceSelect { name, selected })
+                }
+                label ."flex items-center space-x-2" {
+                    input type="checkbox"
+                        name="share_presence"
+                        value="true"
+                        checked[self.privacy.share_presence()] {}
+                    span { "Share my online status" }
+                }
+                label ."flex items-center space-x-2" {
+                    input type="checkbox"
+                        name="share_read_receipts"
+                        value="true"
+                        checked[self.privacy.share_read_receipts()] {}
+                    span { "Send read receipts" }
+                }
+                div ."flex items-center space-x-2" {
+                    input ."px-4 py-2 text-white bg-green-700 hover:bg-green-800 rounded-md cursor-pointer"
+                        type="submit"
+                        value="Save" {}
+                    @if self.saved {
+                        span ."text-green-700" { "Saved" }
+                    }
+                }
+            }
+        }
+    }
+}
+
+struct AudienceSelect<'a> {
+    name: &'a str,
+    selected: Audience,
+}
+
+impl Render for AudienceSelect<'_> {
+    fn render(&self) -> Markup {
+        let options = [
+            (Audience::Everyone, "Everyone"),
+            (Audience::ContactsOfContacts, "Contacts of contacts"),
+            (Audience::Contacts, "Contacts"),
+            (Audience::Nobody, "Nobody"),
+        ];
+
+        html! {
+            select ."px-2 py-1 rounded-md bg-white border border-gray-300"
+                id=(self.name)
+                name=(self.name)
+            {
+                @for (a, label) in options {
+                    option value=(a.as_str()) selected[a.eq(&self.selected)] { (label) }
+                }
+            }
+        }
+    }
+}
+
+/// A page of search results, the first one wrapped in the result list,
+/// further ones appended in place of the sentinel loading them.
 pub struct SearchResult<'a> {
     contacts: &'a [ContactDto],
     users: &'a [UserDto],
+    query: &'a str,
+    page: u32,
 }
 
 impl<'a> SearchResult<'a> {
-    pub const fn new(contacts: &'a [ContactDto], users: &'a [UserDto]) -> Self {
-        Self { contacts, users }
+    pub const fn new(
+        contacts: &'a [ContactDto],
+        users: &'a [UserDto],
+        query: &'a str,
+        page: u32,
+    ) -> Self {
+        Self {
+            contacts,
+            users,
+            query,
+            page,
+        }
+    }
+
+    fn items(&self) -> Markup {
+        let has_more = i64::try_from(self.users.len()).is_ok_and(|l| l >= user::SEARCH_PAGE_SIZE);
+
+        html! {
+            @for user in self.users {
+                li ."px-3 py-2" {
+                    img ."w-6 h-6 rounded-full float-left"
+                        src=(user.picture())
+                        alt="User avatar" {}
+                    strong .px-3 {(user.name())} "@" (user.nickname().0)
+
+                    @match self.contacts.iter().find(|c| user.id().eq(c.recipient())) {
+                        Some(c) => @match c.status() {
+                            contact::Status::Accepted => {
+                                (StartTalk(user.id()))
+                                (c.status())
+                            },
+                            _ => (c.status())
+                        },
+                        None => {
+                            (AddContact(user.id()))
+                            (contact::markup::BlockUser(user.id()))
+                        }
+                    }
+                }
+            }
+            @if has_more {
+                li ."px-3 py-2 text-center text-gray-500"
+                    hx-post="/api/users/search"
+                    hx-vals=(serde_json::json!({ "query": self.query, "page": self.page.saturating_add(1) }).to_string())
+                    hx-trigger="intersect once"
+                    hx-swap="outerHTML"
+                { "Loading..." }
+            }
+        }
     }
 }
 
 impl Render for SearchResult<'_> {
     fn render(&self) -> Markup {
-        let search_result_class =
-            "absolute w-full bg-white border border-gray-300 rounded-md shadow-lg";
+        let search_result_class = "absolute w-full max-h-96 overflow-auto bg-white border border-gray-300 rounded-md shadow-lg";
 
         html! {
-            ul .(search_result_class) {
-                @if self.users.is_empty() {
-                    li ."px-3 py-2" { "No users found" }
-                } @else {
-                    @for user in self.users {
-                        li ."px-3 py-2" {
-                            img ."w-6 h-6 rounded-full float-left"
-                                src=(user.picture())
-                                alt="User avatar" {}
-                            strong .px-3 {(user.name())} (user.nickname().0)
-
-                            @match self.contacts.iter().find(|c| user.id().eq(c.recipient())) {
-                                Some(c) => @match c.status() {
-                                    contact::Status::Accepted => (StartTalk(user.id())),
-                                    _ => (c.status())
-                                },
-                                None => (AddContact(user.id()))
-                            }
-                        }
+            @if self.page > 0 {
+                (self.items())
+            } @else {
+                ul .(search_result_class) {
+                    @if self.users.is_empty() {
+                        li ."px-3 py-2" { "No users found" }
+                    } @else {
+                        (self.items())
                     }
                 }
             }
@@ -154,7 +340,7 @@ impl Render for contact::Status {
         html! {
             @match self {
                 Self::Pending{ .. } => span .(status_class) .bg-gray-400 { "Pending" },
-                Self::Accepted => span .(status_class) .bg-green-700 { "Accepted" },
+                Self::Accepted => span .(status_class) ."bg-green-700 mr-2" { "Contact" },
                 Self::Rejected => span .(status_class) .bg-red-500 { "Rejected" },
                 Self::Blocked { .. } => span .(status_class) .bg-red-700 { "Blocked" },
             }
@@ -198,11 +384,210 @@ impl Render for Icon<'_> {
                 } else {
                     "fa-regular"
                 };
+                let color = match os.presence() {
+                    _ if !os.online() => "text-green-600",
+                    Presence::Available | Presence::Invisible => "text-green-600",
+                    Presence::Away => "text-yellow-500",
+                    Presence::Dnd => "text-red-600",
+                };
 
                 html! {
-                    i #(os.attr()) .(i_class) ."fa-circle text-green-600 mr-2 text-sm" {}
+                    i #(os.attr()) .(i_class) .(color) ."fa-circle mr-2 text-sm" title=(presence_text(os)) {}
                 }
             }
         }
     }
 }
+
+fn last_seen_id(id: &user::Id) -> String {
+    format!("ls-{id}")
+}
+
+fn presence_text(os: &OnlineStatus) -> String {
+    if os.online() {
+        return match os.presence() {
+            Presence::Available | Presence::Invisible => "online",
+            Presence::Away => "away",
+            Presence::Dnd => "do not disturb",
+        }
+        .to_string();
+    }
+
+    os.last_seen().map_or_else(
+        || "offline".to_string(),
+        |ls| {
+            let ago = Utc::now().signed_duration_since(ls);
+            match ago.num_minutes() {
+                m if m < 1 => "last seen just now".to_string(),
+                m if m < 60 => format!("last seen {m} min ago"),
+                m if m < 24 * 60 => format!("last seen {} h ago", m / 60),
+                _ => format!("last seen {}", ls.format("%d %b")),
+            }
+        },
+    )
+}
+
+/// "last seen 5 min ago" next to a chat recipient, refreshed on every status change.
+pub struct LastSeen<'a>(pub &'a OnlineStatus);
+
+impl Render for LastSeen<'_> {
+    fn render(&self) -> Markup {
+        let id = self.0.id();
+
+        html! {
+            span #(last_seen_id(id)) ."text-sm text-gray-500 ml-2 self-center"
+                hx-get={"/templates/users/" (id) "/presence"}
+                hx-trigger={"sse:onlineStatusChange:" (id)}
+                hx-swap="outerHTML"
+            {
+                (presence_text(self.0))
+            }
+        }
+    }
+}
+
+/// Placeholder fetching the `LastSeen` of the user once rendered.
+pub struct LastSeenLoader<'a>(pub &'a user::Id);
+
+impl Render for LastSeenLoader<'_> {
+    fn render(&self) -> Markup {
+        html! {
+            span #(last_seen_id(self.0))
+                hx-get={"/templates/users/" (self.0) "/presence"}
+                hx-trigger="load"
+                hx-swap="outerHTML" {}
+        }
+    }
+}
+
+/// Manual status picker shown in settings.
+pub struct PresenceSelect(pub Presence);
+
+impl Render for PresenceSelect {
+    fn render(&self) -> Markup {
+        let options = [
+            (Presence::Available, "Available"),
+            (Presence::Away, "Away"),
+            (Presence::Dnd, "Do not disturb"),
+            (Presence::Invisible, "Invisible"),
+        ];
+
+        html! {
+            select #presence-select ."px-2 py-1 rounded-md bg-white border border-gray-300"
+                name="presence"
+                hx-put="/api/users/presence"
+                hx-trigger="change"
+                hx-swap="outerHTML"
+            {
+                @for (p, label) in options {
+                    option value=(p.as_str()) selected[p.eq(&self.0)] { (label) }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use maud::Render;
+    use uuid::Uuid;
+
+    use crate::auth::UserInfo;
+    use crate::user::model::{Privacy, UserDto};
+    use crate::user::{Audience, Id};
+
+    use super::{PrivacyForm, ProfileForm};
+
+    #[test]
+    fn should_render_profile_form() {
+        let ui: UserInfo = serde_json::from_value(serde_json::json!({
+            "sub": "idp|ion",
+            "nickname": "ion",
+            "name": "Ion Popescu",
+            "picture": "https://idp.example/ion.png",
+            "email": "ion@mail.md",
+        }))
+        .expect("user info should deserialize");
+        let user = UserDto::new(Id::from(Uuid::new_v4()), &ui);
+        let expected = concat!(
+            r#"<form class="flex flex-col space-y-2 p-3" id="profile-form" hx-put="/api/users/profile" hx-target="this" hx-swap="outerHTML">"#,
+            r#"<label class="text-sm text-gray-500" for="profile-name">Display name</label>"#,
+            r#"<input class="w-full px-3 py-2 border border-gray-300 rounded-md focus:outline-none" id="profile-name" type="text" name="name" required maxlength="64" value="Ion Popescu"></input>"#,
+            r#"<label class="text-sm text-gray-500" for="profile-nickname">Nickname</label>"#,
+            r#"<input class="w-full px-3 py-2 border border-gray-300 rounded-md focus:outline-none" id="profile-nickname" type="text" name="nickname" required minlength="3" maxlength="32" pattern="[A-Za-z0-9_.\-]+" value="ion"></input>"#,
+            r#"<label class="text-sm text-gray-500" for="profile-bio">Bio</label>"#,
+            r#"<textarea class="w-full px-3 py-2 border border-gray-300 rounded-md focus:outline-none" id="profile-bio" name="bio" rows="2" maxlength="160"></textarea>"#,
+            r#"<div class="flex items-center space-x-2">"#,
+            r#"<input class="px-4 py-2 text-white bg-green-700 hover:bg-green-800 rounded-md cursor-pointer" type="submit" value="Save"></input>"#,
+            "</div>",
+            "</form>",
+        );
+
+        let actual = ProfileForm {
+            user: &user,
+            saved: false,
+        }
+        .render()
+        .into_string();
+
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn should_render_privacy_form() {
+        let privacy = Privacy::new(
+            Id::from(Uuid::new_v4()),
+            Audience::ContactsOfContacts,
+            Audience::Everyone,
+            Audience::Nobody,
+            false,
+            true,
+        );
+        let expected = concat!(
+            r#"<form class="flex flex-col space-y-2 p-3" id="privacy-form" hx-put="/api/users/privacy" hx-target="this" hx-swap="outerHTML">"#,
+            r#"<label class="text-sm text-gray-500" for="discoverable_by">Who can find me</label>"#,
+            r#"<select class="px-2 py-1 rounded-md bg-white border border-gray-300" id="discoverable_by" name="discoverable_by">"#,
+            r#"<option value="everyone">Everyone</option>"#,
+            r#"<option value="contacts_of_contacts" selected>Contacts of contacts</option>"#,
+            r#"<option value="contacts">Contacts</option>"#,
+            r#"<option value="nobody">Nobody</option>"#,
+            "</select>",
+            r#"<label class="text-sm text-gray-500" for="contact_requests_from">Who can send me contact requests</label>"#,
+            r#"<select class="px-2 py-1 rounded-md bg-white border border-gray-300" id="contact_requests_from" name="contact_requests_from">"#,
+            r#"<option value="everyone" selected>Everyone</option>"#,
+            r#"<option value="contacts_of_contacts">Contacts of contacts</option>"#,
+            r#"<option value="contacts">Contacts</option>"#,
+            r#"<option value="nobody">Nobody</option>"#,
+            "</select>",
+            r#"<label class="text-sm text-gray-500" for="group_adds_from">Who can add me to groups</label>"#,
+            r#"<select class="px-2 py-1 rounded-md bg-white border border-gray-300" id="group_adds_from" name="group_adds_from">"#,
+            r#"<option value="everyone">Everyone</option>"#,
+            r#"<option value="contacts_of_contacts">Contacts of contacts</option>"#,
+            r#"<option value="contacts">Contacts</option>"#,
+            r#"<option value="nobody" selected>Nobody</option>"#,
+            "</select>",
+            r#"<label class="flex items-center space-x-2">"#,
+            r#"<input type="checkbox" name="share_presence" value="true"></input>"#,
+            "<span>Share my online status</span>",
+            "</label>",
+            r#"<label class="flex items-center space-x-2">"#,
+            r#"<input type="checkbox" name="share_read_receipts" value="true" checked></input>"#,
+            "<span>Send read receipts</span>",
+            "</label>",
+            r#"<div class="flex items-center space-x-2">"#,
+            r#"<input class="px-4 py-2 text-white bg-green-700 hover:bg-green-800 rounded-md cursor-pointer" type="submit" value="Save"></input>"#,
+            r#"<span class="text-green-700">Saved</span>"#,
+            "</div>",
+            "</form>",
+        );
+
+        let actual = PrivacyForm {
+            privacy: &privacy,
+            saved: true,
+        }
+        .render()
+        .into_string();
+
+        assert_eq!(expected, actual);
+    }
+}
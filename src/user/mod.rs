@@ -1,6 +1,9 @@
 use std::{fmt::Display, str::FromStr, sync::Arc};
 
-use axum::{Router, routing::post};
+use axum::{
+    Router,
+    routing::{delete, get, post, put},
+};
 use diesel::{deserialize::FromSqlRow, expression::AsExpression, sql_types};
 use log::error;
 use messenger_service::AsStr;
@@ -9,8 +12,9 @@ use serde::{Deserialize, Serialize};
 use service::UserService;
 use uuid::Uuid;
 
-use crate::state::AppServices;
+use crate::{contact, state::AppServices};
 
+mod archive;
 mod handler;
 pub mod markup;
 pub mod model;
@@ -18,12 +22,36 @@ pub mod repository;
 pub mod service;
 
 type Result<T> = std::result::Result<T, Error>;
+
+/// Maximum length of a display name, in characters.
+pub const NAME_MAX_LEN: usize = 64;
+
+/// Maximum length of a profile bio, in characters.
+pub const BIO_MAX_LEN: usize = 160;
+
+/// Users returned by a single search request.
+pub const SEARCH_PAGE_SIZE: i64 = 20;
+
 pub type Repository = Arc<dyn UserRepository + Send + Sync>;
 pub type Service = Arc<dyn UserService + Send + Sync>;
 
 pub fn api<S>(s: AppServices) -> Router<S> {
     Router::new()
         .route("/users/search", post(handler::api::search))
+        .route("/users/profile", put(handler::api::update_profile))
+        .route("/users/presence", put(handler::api::set_presence))
+        .route("/users/activity", post(handler::api::touch))
+        .route("/users/privacy", put(handler::api::update_privacy))
+        .route("/users/export", get(handler::api::export))
+        .route("/users/account", delete(handler::api::delete_account))
+        .with_state(s)
+}
+
+pub fn templates<S>(s: AppServices) -> Router<S> {
+    Router::new()
+        .route("/users/profile", get(handler::templates::profile))
+        .route("/users/privacy", get(handler::templates::privacy))
+        .route("/users/{id}/presence", get(handler::templates::presence))
         .with_state(s)
 }
 
@@ -32,6 +60,9 @@ pub fn api<S>(s: AppServices) -> Router<S> {
 pub struct Id(Uuid);
 
 impl Id {
+    /// Placeholder that takes over what deleted accounts leave behind in talks.
+    pub const DELETED: Self = Self(Uuid::nil());
+
     pub const fn get(&self) -> &Uuid {
         &self.0
     }
@@ -106,9 +137,27 @@ impl AsStr for Nickname {
     }
 }
 impl Nickname {
+    const MIN_LEN: usize = 3;
+    const MAX_LEN: usize = 32;
+
     pub const fn is_empty(&self) -> bool {
         self.0.is_empty()
     }
+
+    /// Nicknames are 3 to 32 latin letters, digits, `_`, `.` or `-`.
+    pub fn validate(&self) -> Result<()> {
+        let valid = (Self::MIN_LEN..=Self::MAX_LEN).contains(&self.0.len())
+            && self
+                .0
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'));
+
+        if valid {
+            Ok(())
+        } else {
+            Err(Error::InvalidNickname(self.0.clone()))
+        }
+    }
 }
 
 impl From<String> for Nickname {
@@ -163,6 +212,95 @@ impl TryFrom<&str> for Email {
     }
 }
 
+/// Status a user picks for themselves, `Invisible` shows them offline.
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq, AsExpression, FromSqlRow,
+)]
+#[diesel(sql_type = crate::schema::sql_types::UserPresence)]
+#[serde(rename_all = "snake_case")]
+pub enum Presence {
+    #[default]
+    Available,
+    Away,
+    Dnd,
+    Invisible,
+}
+
+impl AsStr for Presence {
+    fn as_str(&self) -> &str {
+        match self {
+            Self::Available => "available",
+            Self::Away => "away",
+            Self::Dnd => "dnd",
+            Self::Invisible => "invisible",
+        }
+    }
+}
+
+impl TryFrom<String> for Presence {
+    type Error = Error;
+
+    fn try_from(s: String) -> std::result::Result<Self, Self::Error> {
+        match s.as_str() {
+            "available" => Ok(Self::Available),
+            "away" => Ok(Self::Away),
+            "dnd" => Ok(Self::Dnd),
+            "invisible" => Ok(Self::Invisible),
+            _ => Err(Error::UnsupportedPresence(s)),
+        }
+    }
+}
+
+/// Who may reach a user in some way, by how close they are to them.
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq, AsExpression, FromSqlRow,
+)]
+#[diesel(sql_type = crate::schema::sql_types::Audience)]
+#[serde(rename_all = "snake_case")]
+pub enum Audience {
+    #[default]
+    Everyone,
+    ContactsOfContacts,
+    Contacts,
+    Nobody,
+}
+
+impl Audience {
+    pub const fn admits(self, r: contact::Relation) -> bool {
+        match self {
+            Self::Everyone => true,
+            Self::ContactsOfContacts => !matches!(r, contact::Relation::Stranger),
+            Self::Contacts => matches!(r, contact::Relation::Contact),
+            Self::Nobody => false,
+        }
+    }
+}
+
+impl AsStr for Audience {
+    fn as_str(&self) -> &str {
+        match self {
+            Self::Everyone => "everyone",
+            Self::ContactsOfContacts => "contacts_of_contacts",
+            Self::Contacts => "contacts",
+            Self::Nobody => "nobody",
+        }
+    }
+}
+
+impl TryFrom<String> for Audience {
+    type Error = Error;
+
+    fn try_from(s: String) -> std::result::Result<Self, Self::Error> {
+        match s.as_str() {
+            "everyone" => Ok(Self::Everyone),
+            "contacts_of_contacts" => Ok(Self::ContactsOfContacts),
+            "contacts" => Ok(Self::Contacts),
+            "nobody" => Ok(Self::Nobody),
+            _ => Err(Error::UnsupportedAudience(s)),
+        }
+    }
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error("invalid picture format: {0:?}")]
@@ -171,9 +309,56 @@ pub enum Error {
     MalformedEmail(String),
     #[error("authenticated user is not a member")]
     NotMember,
+    #[error("invalid nickname: {0:?}")]
+    InvalidNickname(String),
+    #[error("nickname is already taken: {0:?}")]
+    NicknameTaken(String),
+    #[error("name must be 1 to {NAME_MAX_LEN} characters")]
+    InvalidName,
+    #[error("bio is too long: {0} characters")]
+    BioTooLong(usize),
+    #[error("unsupported presence: {0:?}")]
+    UnsupportedPresence(String),
+    #[error("unsupported audience: {0:?}")]
+    UnsupportedAudience(String),
 
+    #[error(transparent)]
+    _Contact(#[from] contact::Error),
     #[error(transparent)]
     _R2d2(#[from] r2d2::Error),
     #[error(transparent)]
     _Diesel(#[from] diesel::result::Error),
+    #[error(transparent)]
+    _Io(#[from] std::io::Error),
+    #[error(transparent)]
+    _SerdeJson(#[from] serde_json::Error),
+    #[error(transparent)]
+    _Zip(#[from] zip::result::ZipError),
+}
+
+#[cfg(test)]
+mod test {
+    use super::Audience;
+    use crate::contact::Relation;
+
+    #[test]
+    fn should_admit_relations_by_audience() {
+        // (audience, contact, contact of contact, stranger)
+        let table = [
+            (Audience::Everyone, true, true, true),
+            (Audience::ContactsOfContacts, true, true, false),
+            (Audience::Contacts, true, false, false),
+            (Audience::Nobody, false, false, false),
+        ];
+
+        for (a, contact, contact_of_contact, stranger) in table {
+            assert_eq!(a.admits(Relation::Contact), contact, "{a:?}");
+            assert_eq!(
+                a.admits(Relation::ContactOfContact),
+                contact_of_contact,
+                "{a:?}"
+            );
+            assert_eq!(a.admits(Relation::Stranger), stranger, "{a:?}");
+        }
+    }
 }
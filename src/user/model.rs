@@ -1,12 +1,15 @@
-use diesel::prelude::{Insertable, Queryable, Selectable};
+use chrono::{DateTime, Utc};
+use diesel::prelude::{AsChangeset, Insertable, Queryable, QueryableByName, Selectable};
 use messenger_service::AsStr;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 use crate::auth::{self, UserInfo};
+use crate::{contact, talk};
 
-use super::{Email, Id, Nickname, Picture, Sub};
+use super::{Audience, Email, Id, Nickname, Picture, Presence, Sub};
 
-#[derive(Queryable, Selectable)]
+#[derive(Queryable, QueryableByName, Selectable)]
 #[diesel(table_name = crate::schema::users)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct User {
@@ -16,6 +19,40 @@ pub struct User {
     name: String,
     picture: String,
     email: String,
+    bio: Option<String>,
+    name_overridden: bool,
+    nickname_overridden: bool,
+    presence: Presence,
+}
+
+impl User {
+    pub const fn id(&self) -> &Id {
+        &self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn nickname(&self) -> &str {
+        &self.nickname
+    }
+
+    pub fn picture(&self) -> &str {
+        &self.picture
+    }
+
+    pub fn email(&self) -> &str {
+        &self.email
+    }
+
+    pub const fn name_overridden(&self) -> bool {
+        self.name_overridden
+    }
+
+    pub const fn nickname_overridden(&self) -> bool {
+        self.nickname_overridden
+    }
 }
 
 #[derive(Insertable)]
@@ -40,10 +77,198 @@ impl<'a> From<&'a auth::UserInfo> for NewUser<'a> {
     }
 }
 
+impl<'a> NewUser<'a> {
+    pub const fn nickname(&self) -> &str {
+        self.nickname
+    }
+
+    pub const fn with_nickname(self, nickname: &'a str) -> Self {
+        Self { nickname, ..self }
+    }
+}
+
+/// Fields a user edits on their own profile.
+/// Name and nickname changes are marked as overridden, so they survive an IdP sync.
+#[derive(AsChangeset)]
+#[diesel(table_name = crate::schema::users)]
+pub struct ProfileUpdate<'a> {
+    name: &'a str,
+    nickname: &'a str,
+    #[diesel(treat_none_as_null = true)]
+    bio: Option<&'a str>,
+    name_overridden: Option<bool>,
+    nickname_overridden: Option<bool>,
+}
+
+impl<'a> ProfileUpdate<'a> {
+    pub fn new(
+        current: &UserDto,
+        name: &'a str,
+        nickname: &'a Nickname,
+        bio: Option<&'a str>,
+    ) -> Self {
+        Self {
+            name,
+            nickname: nickname.0.as_str(),
+            bio,
+            name_overridden: current.name().ne(name).then_some(true),
+            nickname_overridden: current.nickname().ne(nickname).then_some(true),
+        }
+    }
+
+    pub const fn nickname(&self) -> &str {
+        self.nickname
+    }
+}
+
+/// Fields refreshed from the IdP on login, `None` when unchanged or overridden.
+#[derive(AsChangeset, Default)]
+#[diesel(table_name = crate::schema::users)]
+pub struct IdpSync<'a> {
+    name: Option<&'a str>,
+    nickname: Option<&'a str>,
+    picture: Option<&'a str>,
+    email: Option<&'a str>,
+}
+
+impl<'a> IdpSync<'a> {
+    pub fn new(stored: &User, ui: &'a UserInfo) -> Self {
+        let changed = |stored: &str, fresh: &'a str| stored.ne(fresh).then_some(fresh);
+
+        Self {
+            name: changed(stored.name(), ui.name()).filter(|_| !stored.name_overridden()),
+            nickname: changed(stored.nickname(), ui.nickname().as_str())
+                .filter(|_| !stored.nickname_overridden()),
+            picture: changed(stored.picture(), ui.picture().as_str()),
+            email: changed(stored.email(), ui.email().as_str()),
+        }
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.name.is_none()
+            && self.nickname.is_none()
+            && self.picture.is_none()
+            && self.email.is_none()
+    }
+
+    pub const fn renames(&self) -> bool {
+        self.name.is_some()
+    }
+
+    pub const fn nickname(&self) -> Option<&str> {
+        self.nickname
+    }
+
+    /// Keeps the stored nickname, e.g. when the one from the IdP is taken.
+    pub const fn without_nickname(self) -> Self {
+        Self {
+            nickname: None,
+            ..self
+        }
+    }
+}
+
+/// Who may find and reach the user, and what they share with their contacts.
+/// Users without a stored row get the defaults, which hide nothing.
+#[derive(Queryable, Selectable, Insertable, AsChangeset, Serialize, Deserialize, Clone)]
+#[diesel(table_name = crate::schema::privacy_settings)]
+#[diesel(primary_key(user_id))]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Privacy {
+    user_id: Id,
+    discoverable_by: Audience,
+    contact_requests_from: Audience,
+    group_adds_from: Audience,
+    share_presence: bool,
+    share_read_receipts: bool,
+}
+
+impl Privacy {
+    pub const fn new(
+        user_id: Id,
+        discoverable_by: Audience,
+        contact_requests_from: Audience,
+        group_adds_from: Audience,
+        share_presence: bool,
+        share_read_receipts: bool,
+    ) -> Self {
+        Self {
+            user_id,
+            discoverable_by,
+            contact_requests_from,
+            group_adds_from,
+            share_presence,
+            share_read_receipts,
+        }
+    }
+
+    pub fn default_for(user_id: &Id) -> Self {
+        Self::new(
+            user_id.clone(),
+            Audience::default(),
+            Audience::default(),
+            Audience::default(),
+            true,
+            true,
+        )
+    }
+
+    pub const fn discoverable_by(&self) -> Audience {
+        self.discoverable_by
+    }
+
+    pub const fn contact_requests_from(&self) -> Audience {
+        self.contact_requests_from
+    }
+
+    pub const fn group_adds_from(&self) -> Audience {
+        self.group_adds_from
+    }
+
+    pub const fn share_presence(&self) -> bool {
+        self.share_presence
+    }
+
+    pub const fn share_read_receipts(&self) -> bool {
+        self.share_read_receipts
+    }
+}
+
+/// What a deleted account leaves behind in the talks of others.
+pub struct Erasure {
+    /// Chats taken over by the placeholder user, with the remaining member of each.
+    pub chats: Vec<(talk::Id, Id)>,
+    /// Groups and channels the user was a member of that live on.
+    pub groups: Vec<talk::Id>,
+    /// Groups and channels the user owned, with the member who took over each.
+    pub owners: Vec<(talk::Id, Id)>,
+    /// Contacts and requests of the user, with the other side of each.
+    pub contacts: Vec<(contact::Id, Id)>,
+}
+
+/// Identifies one event stream of a user, they may have several tabs or devices open.
+#[derive(Clone)]
+pub struct ConnectionId(Uuid);
+
+impl ConnectionId {
+    pub fn random() -> Self {
+        Self(Uuid::new_v4())
+    }
+
+    pub const fn get(&self) -> &Uuid {
+        &self.0
+    }
+}
+
+/// Presence of a user as seen by others, invisible users appear offline.
 #[derive(Clone, Serialize, Deserialize)]
 pub struct OnlineStatus {
     id: Id,
     online: bool,
+    #[serde(default)]
+    presence: Presence,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_seen: Option<DateTime<Utc>>,
 }
 
 impl OnlineStatus {
@@ -51,9 +276,37 @@ impl OnlineStatus {
         Self {
             id: id.clone(),
             online,
+            presence: Presence::default(),
+            last_seen: None,
+        }
+    }
+
+    pub fn new(
+        id: &Id,
+        online: bool,
+        presence: Presence,
+        last_seen: Option<DateTime<Utc>>,
+    ) -> Self {
+        if presence.eq(&Presence::Invisible) {
+            return Self::from_ref(id, false);
+        }
+
+        Self {
+            id: id.clone(),
+            online,
+            presence,
+            last_seen,
         }
     }
 
+    pub const fn presence(&self) -> Presence {
+        self.presence
+    }
+
+    pub const fn last_seen(&self) -> Option<&DateTime<Utc>> {
+        self.last_seen.as_ref()
+    }
+
     pub const fn id(&self) -> &Id {
         &self.id
     }
@@ -71,6 +324,10 @@ pub struct UserDto {
     name: String,
     picture: Picture,
     email: Email,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    bio: Option<String>,
+    #[serde(default)]
+    presence: Presence,
 }
 
 impl UserDto {
@@ -82,6 +339,8 @@ impl UserDto {
             name: ui.name().to_string(),
             picture: ui.picture().clone(),
             email: ui.email().clone(),
+            bio: None,
+            presence: Presence::default(),
         }
     }
 
@@ -108,6 +367,14 @@ impl UserDto {
     pub const fn picture(&self) -> &Picture {
         &self.picture
     }
+
+    pub fn bio(&self) -> Option<&str> {
+        self.bio.as_deref()
+    }
+
+    pub const fn presence(&self) -> Presence {
+        self.presence
+    }
 }
 
 impl From<User> for UserDto {
@@ -119,6 +386,93 @@ impl From<User> for UserDto {
             name: user.name,
             picture: Picture(user.picture),
             email: Email(user.email),
+            bio: user.bio,
+            presence: user.presence,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use uuid::Uuid;
+
+    use crate::auth::UserInfo;
+    use crate::user::{Id, Presence};
+
+    use super::{IdpSync, User};
+
+    fn stored(name_overridden: bool, nickname_overridden: bool) -> User {
+        User {
+            id: Id::from(Uuid::new_v4()),
+            sub: String::from("idp|ion"),
+            nickname: String::from("ion"),
+            name: String::from("Ion"),
+            picture: String::from("https://idp.example/ion.png"),
+            email: String::from("ion@mail.md"),
+            bio: None,
+            name_overridden,
+            nickname_overridden,
+            presence: Presence::Available,
         }
     }
+
+    fn user_info() -> UserInfo {
+        serde_json::from_value(serde_json::json!({
+            "sub": "idp|ion",
+            "nickname": "ion.popescu",
+            "name": "Ion Popescu",
+            "picture": "https://idp.example/ion-2.png",
+            "email": "ion.popescu@mail.md",
+        }))
+        .expect("user info should deserialize")
+    }
+
+    #[test]
+    fn should_sync_all_fields_without_overrides() {
+        let ui = user_info();
+        let sync = IdpSync::new(&stored(false, false), &ui);
+
+        assert_eq!(sync.name, Some("Ion Popescu"));
+        assert_eq!(sync.nickname, Some("ion.popescu"));
+        assert_eq!(sync.picture, Some("https://idp.example/ion-2.png"));
+        assert_eq!(sync.email, Some("ion.popescu@mail.md"));
+        assert!(sync.renames());
+    }
+
+    #[test]
+    fn should_keep_overridden_name_and_nickname() {
+        let ui = user_info();
+        let sync = IdpSync::new(&stored(true, true), &ui);
+
+        assert_eq!(sync.name, None);
+        assert_eq!(sync.nickname, None);
+        assert_eq!(sync.picture, Some("https://idp.example/ion-2.png"));
+        assert_eq!(sync.email, Some("ion.popescu@mail.md"));
+        assert!(!sync.renames());
+    }
+
+    #[test]
+    fn should_keep_only_the_overridden_field() {
+        let ui = user_info();
+
+        let sync = IdpSync::new(&stored(true, false), &ui);
+        assert_eq!(sync.name, None);
+        assert_eq!(sync.nickname, Some("ion.popescu"));
+
+        let sync = IdpSync::new(&stored(false, true), &ui);
+        assert_eq!(sync.name, Some("Ion Popescu"));
+        assert_eq!(sync.nickname, None);
+    }
+
+    #[test]
+    fn should_be_empty_when_nothing_changed() {
+        let ui = user_info();
+        let mut u = stored(false, false);
+        u.nickname = String::from("ion.popescu");
+        u.name = String::from("Ion Popescu");
+        u.picture = String::from("https://idp.example/ion-2.png");
+        u.email = String::from("ion.popescu@mail.md");
+
+        assert!(IdpSync::new(&u, &ui).is_empty());
+    }
 }
@@ -1,22 +1,37 @@
+use chrono::{DateTime, Utc};
 use diesel::BoolExpressionMethods;
 use diesel::CombineDsl;
+use diesel::Connection;
 use diesel::ExpressionMethods;
 use diesel::OptionalExtension;
 use diesel::PgConnection;
 use diesel::QueryDsl;
+use diesel::QueryResult;
 use diesel::RunQueryDsl;
 use diesel::SelectableHelper;
-use diesel::TextExpressionMethods;
+use diesel::delete;
 use diesel::insert_into;
 use diesel::r2d2::ConnectionManager;
+use diesel::result::DatabaseErrorKind;
+use diesel::sql_query;
+use diesel::sql_types;
+use diesel::update;
 use messenger_service::AsStr;
 use uuid::Uuid;
 
-use crate::talk;
+use crate::contact;
+use crate::integration::db::pg;
+use crate::talk::{self, Role};
 
+use super::Email;
 use super::Nickname;
+use super::Presence;
 use super::Sub;
+use super::model::Erasure;
+use super::model::IdpSync;
 use super::model::NewUser;
+use super::model::Privacy;
+use super::model::ProfileUpdate;
 use super::model::User;
 use crate::user;
 
@@ -31,11 +46,94 @@ pub trait UserRepository {
 
     fn exists(&self, id: &user::Id) -> super::Result<bool>;
 
-    fn find_by_nickname_like_and_excluding(
+    /// Users matching the query by nickname, name or exact email, other than
+    /// the viewer, the excluded ones and those hiding from the viewer. Exact
+    /// matches come first, then prefix and fuzzy ones, contacts of the viewer
+    /// first within each.
+    fn search(
         &self,
-        n: &Nickname,
-        exclude: &Nickname,
+        query: &str,
+        viewer: &user::Id,
+        exclude: &[user::Id],
+        limit: i64,
+        offset: i64,
     ) -> super::Result<Vec<User>>;
+
+    /// Matches email addresses case-insensitively,
+    /// leaving out users who are not discoverable by the viewer.
+    fn find_by_emails(&self, emails: &[Email], viewer: &user::Id) -> super::Result<Vec<User>>;
+
+    fn update_profile(&self, id: &user::Id, p: &ProfileUpdate) -> super::Result<User>;
+
+    fn sync(&self, id: &user::Id, s: &IdpSync) -> super::Result<User>;
+
+    fn set_presence(&self, id: &user::Id, p: Presence) -> super::Result<()>;
+
+    fn set_last_seen(&self, id: &user::Id, at: &DateTime<Utc>) -> super::Result<()>;
+
+    fn find_last_seen(&self, id: &user::Id) -> super::Result<Option<DateTime<Utc>>>;
+
+    /// Whether another user than the given one goes by the nickname, in any case.
+    fn nickname_taken(&self, n: &Nickname, exclude: &user::Id) -> super::Result<bool>;
+
+    /// Chats of the user along with the other member of each.
+    fn find_chat_partners(&self, id: &user::Id) -> super::Result<Vec<(talk::Id, user::Id)>>;
+
+    fn find_privacy(&self, id: &user::Id) -> super::Result<Option<Privacy>>;
+
+    fn save_privacy(&self, p: &Privacy) -> super::Result<()>;
+
+    /// Whether a member of the talk other than the given one withholds read receipts.
+    fn receipts_withheld(&self, talk_id: &talk::Id, except: &user::Id) -> super::Result<bool>;
+
+    /// Whether the users are both members of at least one talk.
+    fn shares_talk(&self, me: &user::Id, you: &user::Id) -> super::Result<bool>;
+
+    /// Deletes the user, handing their messages and chats over to the placeholder
+    /// user and their groups over to the next admin or member. Talks nobody else
+    /// is left in are deleted along.
+    fn delete_account(&self, id: &user::Id) -> super::Result<Erasure>;
+}
+
+diesel::define_sql_function!(fn lower(x: diesel::sql_types::Text) -> diesel::sql_types::Text);
+
+/// Keeps nicknames unique regardless of case.
+const NICKNAME_INDEX: &str = "users_nickname_lower_idx";
+
+/// Relation of each user `u` to the viewer bound as `$2`, needed by [`DISCOVERABLE_BY_VIEWER`].
+const DISCOVERABLE_JOINS: &str = r"
+            LEFT JOIN contacts c
+                ON (c.user_id_1 = $2 AND c.user_id_2 = u.id)
+                OR (c.user_id_2 = $2 AND c.user_id_1 = u.id)
+            LEFT JOIN privacy_settings ps ON ps.user_id = u.id";
+
+/// Whether `u` lets the viewer `$2` find them, per their `discoverable_by` setting.
+const DISCOVERABLE_BY_VIEWER: &str = r"
+                CASE COALESCE(ps.discoverable_by, 'everyone')
+                    WHEN 'everyone' THEN true
+                    WHEN 'nobody' THEN false
+                    WHEN 'contacts' THEN COALESCE(c.status = 'accepted', false)
+                    ELSE COALESCE(c.status = 'accepted', false) OR EXISTS (
+                        SELECT 1
+                        FROM contacts a
+                        JOIN contacts b
+                            ON (CASE WHEN a.user_id_1 = $2 THEN a.user_id_2 ELSE a.user_id_1 END)
+                            = (CASE WHEN b.user_id_1 = u.id THEN b.user_id_2 ELSE b.user_id_1 END)
+                        WHERE (a.user_id_1 = $2 OR a.user_id_2 = $2) AND a.status = 'accepted'
+                        AND (b.user_id_1 = u.id OR b.user_id_2 = u.id) AND b.status = 'accepted'
+                    )
+                END";
+
+/// Tells a lost race for a nickname apart from other failures.
+fn nickname_conflict(e: diesel::result::Error, nickname: &str) -> super::Error {
+    match e {
+        diesel::result::Error::DatabaseError(DatabaseErrorKind::UniqueViolation, ref info)
+            if info.constraint_name() == Some(NICKNAME_INDEX) =>
+        {
+            super::Error::NicknameTaken(nickname.to_string())
+        }
+        e => super::Error::from(e),
+    }
 }
 
 pub struct PgUserRepository {
@@ -59,7 +157,7 @@ impl UserRepository for PgUserRepository {
             .returning(id)
             .get_result::<Uuid>(&mut conn)
             .map(user::Id)
-            .map_err(super::Error::from)
+            .map_err(|e| nickname_conflict(e, u.nickname()))
     }
 
     fn find_by_id(&self, id: &user::Id) -> super::Result<User> {
@@ -114,23 +212,360 @@ impl UserRepository for PgUserRepository {
         Ok(count > 0)
     }
 
-    fn find_by_nickname_like_and_excluding(
+    fn search(
         &self,
-        n: &Nickname,
-        exclude: &Nickname,
+        query: &str,
+        viewer: &user::Id,
+        exclude: &[user::Id],
+        limit: i64,
+        offset: i64,
     ) -> super::Result<Vec<User>> {
+        let mut conn = self.pool.get()?;
+
+        sql_query(format!(
+            r"
+            SELECT u.*
+            FROM users u
+            {DISCOVERABLE_JOINS}
+            WHERE u.id <> $2
+                AND u.id <> ALL($7)
+                AND {DISCOVERABLE_BY_VIEWER}
+                AND (
+                    u.nickname ILIKE $3 ESCAPE '\'
+                    OR u.name ILIKE $3 ESCAPE '\'
+                    OR lower(u.email) = lower($1)
+                    OR u.nickname % $1
+                    OR u.name % $1
+                )
+            ORDER BY
+                CASE
+                    WHEN lower(u.nickname) = lower($1) OR lower(u.email) = lower($1) THEN 0
+                    WHEN u.nickname ILIKE $4 ESCAPE '\' OR u.name ILIKE $4 ESCAPE '\' THEN 1
+                    ELSE 2
+                END,
+                c.status = 'accepted' DESC NULLS LAST,
+                greatest(similarity(u.nickname, $1), similarity(u.name, $1)) DESC,
+                u.name
+            LIMIT $5 OFFSET $6
+            ",
+        ))
+        .bind::<sql_types::Text, _>(query)
+        .bind::<sql_types::Uuid, _>(viewer)
+        .bind::<sql_types::Text, _>(format!("%{}%", pg::escape_like(query)))
+        .bind::<sql_types::Text, _>(format!("{}%", pg::escape_like(query)))
+        .bind::<sql_types::BigInt, _>(limit)
+        .bind::<sql_types::BigInt, _>(offset)
+        .bind::<sql_types::Array<sql_types::Uuid>, _>(exclude)
+        .load(&mut conn)
+        .map_err(super::Error::from)
+    }
+
+    fn find_by_emails(&self, emails: &[Email], viewer: &user::Id) -> super::Result<Vec<User>> {
+        let mut conn = self.pool.get()?;
+
+        let emails = emails
+            .iter()
+            .map(|e| e.as_str().to_lowercase())
+            .collect::<Vec<_>>();
+
+        sql_query(format!(
+            r"
+            SELECT u.*
+            FROM users u
+            {DISCOVERABLE_JOINS}
+            WHERE lower(u.email) = ANY($1)
+                AND {DISCOVERABLE_BY_VIEWER}
+            ",
+        ))
+        .bind::<sql_types::Array<sql_types::Text>, _>(emails)
+        .bind::<sql_types::Uuid, _>(viewer)
+        .load(&mut conn)
+        .map_err(super::Error::from)
+    }
+
+    fn update_profile(&self, id: &user::Id, p: &ProfileUpdate) -> super::Result<User> {
+        use crate::schema::users::dsl::users;
+
+        let mut conn = self.pool.get()?;
+
+        update(users.find(id))
+            .set(p)
+            .returning(User::as_returning())
+            .get_result(&mut conn)
+            .map_err(|e| nickname_conflict(e, p.nickname()))
+    }
+
+    fn sync(&self, id: &user::Id, s: &IdpSync) -> super::Result<User> {
+        use crate::schema::users::dsl::users;
+
+        let mut conn = self.pool.get()?;
+
+        update(users.find(id))
+            .set(s)
+            .returning(User::as_returning())
+            .get_result(&mut conn)
+            .map_err(|e| nickname_conflict(e, s.nickname().unwrap_or_default()))
+    }
+
+    fn set_presence(&self, id: &user::Id, p: Presence) -> super::Result<()> {
+        use crate::schema::users::dsl as u;
+
+        let mut conn = self.pool.get()?;
+
+        update(u::users.find(id))
+            .set(u::presence.eq(p))
+            .execute(&mut conn)?;
+
+        Ok(())
+    }
+
+    fn set_last_seen(&self, id: &user::Id, at: &DateTime<Utc>) -> super::Result<()> {
+        use crate::schema::users::dsl as u;
+
+        let mut conn = self.pool.get()?;
+
+        update(u::users.find(id))
+            .set(u::last_seen.eq(at))
+            .execute(&mut conn)?;
+
+        Ok(())
+    }
+
+    fn find_last_seen(&self, id: &user::Id) -> super::Result<Option<DateTime<Utc>>> {
         use crate::schema::users::dsl as u;
 
         let mut conn = self.pool.get()?;
 
         u::users
+            .find(id)
+            .select(u::last_seen)
+            .first(&mut conn)
+            .map_err(super::Error::from)
+    }
+
+    fn nickname_taken(&self, n: &Nickname, exclude: &user::Id) -> super::Result<bool> {
+        use crate::schema::users::dsl as u;
+
+        let mut conn = self.pool.get()?;
+
+        let count = u::users
             .filter(
-                u::nickname
-                    .like(format!("%{}%", n.as_str()))
-                    .and(u::nickname.ne(exclude.as_str())),
+                lower(u::nickname)
+                    .eq(lower(n.as_str()))
+                    .and(u::id.ne(exclude)),
             )
-            .select(User::as_select())
-            .get_results(&mut conn)
+            .count()
+            .get_result::<i64>(&mut conn)?;
+
+        Ok(count > 0)
+    }
+
+    fn find_chat_partners(&self, id: &user::Id) -> super::Result<Vec<(talk::Id, user::Id)>> {
+        use crate::schema::chats_users::dsl as cu;
+
+        let mut conn = self.pool.get()?;
+
+        let chat_ids = cu::chats_users
+            .filter(cu::user_id.eq(id))
+            .select(cu::chat_id)
+            .load::<talk::Id>(&mut conn)?;
+
+        cu::chats_users
+            .filter(cu::chat_id.eq_any(chat_ids).and(cu::user_id.ne(id)))
+            .select((cu::chat_id, cu::user_id))
+            .load(&mut conn)
+            .map_err(super::Error::from)
+    }
+
+    fn find_privacy(&self, id: &user::Id) -> super::Result<Option<Privacy>> {
+        use crate::schema::privacy_settings::dsl::privacy_settings;
+
+        let mut conn = self.pool.get()?;
+
+        privacy_settings
+            .find(id)
+            .select(Privacy::as_select())
+            .first(&mut conn)
+            .optional()
             .map_err(super::Error::from)
     }
+
+    fn save_privacy(&self, p: &Privacy) -> super::Result<()> {
+        use crate::schema::privacy_settings::dsl::{privacy_settings, user_id};
+
+        let mut conn = self.pool.get()?;
+
+        insert_into(privacy_settings)
+            .values(p)
+            .on_conflict(user_id)
+            .do_update()
+            .set(p)
+            .execute(&mut conn)?;
+
+        Ok(())
+    }
+
+    fn receipts_withheld(&self, t_id: &talk::Id, except: &user::Id) -> super::Result<bool> {
+        use crate::schema::chats_users::dsl as cu;
+        use crate::schema::groups_users::dsl as gu;
+        use crate::schema::privacy_settings::dsl as p;
+
+        let mut conn = self.pool.get()?;
+
+        let members = gu::groups_users
+            .filter(gu::group_id.eq(t_id))
+            .select(gu::user_id)
+            .union(
+                cu::chats_users
+                    .filter(cu::chat_id.eq(t_id))
+                    .select(cu::user_id),
+            )
+            .load::<user::Id>(&mut conn)?;
+
+        let count = p::privacy_settings
+            .filter(
+                p::user_id
+                    .eq_any(members)
+                    .and(p::user_id.ne(except))
+                    .and(p::share_read_receipts.eq(false)),
+            )
+            .count()
+            .get_result::<i64>(&mut conn)?;
+
+        Ok(count > 0)
+    }
+
+    fn shares_talk(&self, me: &user::Id, you: &user::Id) -> super::Result<bool> {
+        use crate::schema::chats_users::dsl as cu;
+        use crate::schema::groups_users::dsl as gu;
+
+        let mut conn = self.pool.get()?;
+
+        let talks = gu::groups_users
+            .filter(gu::user_id.eq(me))
+            .select(gu::group_id)
+            .union(
+                cu::chats_users
+                    .filter(cu::user_id.eq(me))
+                    .select(cu::chat_id),
+            )
+            .load::<talk::Id>(&mut conn)?;
+
+        let shared = gu::groups_users
+            .filter(gu::user_id.eq(you).and(gu::group_id.eq_any(&talks)))
+            .select(gu::group_id)
+            .union(
+                cu::chats_users
+                    .filter(cu::user_id.eq(you).and(cu::chat_id.eq_any(&talks)))
+                    .select(cu::chat_id),
+            )
+            .load::<talk::Id>(&mut conn)?;
+
+        Ok(!shared.is_empty())
+    }
+
+    fn delete_account(&self, id: &user::Id) -> super::Result<Erasure> {
+        use crate::schema::chats::dsl as c;
+        use crate::schema::chats_users::dsl as cu;
+        use crate::schema::contacts::dsl as ct;
+        use crate::schema::groups::dsl as g;
+        use crate::schema::groups_users::dsl as gu;
+        use crate::schema::messages::dsl as m;
+        use crate::schema::talks::dsl as t;
+        use crate::schema::users::dsl as u;
+
+        let mut conn = self.pool.get()?;
+
+        let tx_res: QueryResult<Erasure> = conn.transaction(|conn| {
+            let chat_ids = cu::chats_users
+                .filter(cu::user_id.eq(id))
+                .select(cu::chat_id)
+                .load::<talk::Id>(conn)?;
+
+            let chats = cu::chats_users
+                .filter(
+                    cu::chat_id
+                        .eq_any(&chat_ids)
+                        .and(cu::user_id.ne(id))
+                        .and(cu::user_id.ne(user::Id::DELETED)),
+                )
+                .select((cu::chat_id, cu::user_id))
+                .load::<(talk::Id, user::Id)>(conn)?;
+
+            // saved messages and chats with deleted users have no reader left
+            let orphaned = chat_ids
+                .into_iter()
+                .filter(|chat_id| !chats.iter().any(|(c, _)| c.eq(chat_id)))
+                .collect::<Vec<_>>();
+            delete(c::chats.filter(c::id.eq_any(&orphaned))).execute(conn)?;
+            delete(t::talks.filter(t::id.eq_any(&orphaned))).execute(conn)?;
+
+            let contacts = ct::contacts
+                .filter(ct::user_id_1.eq(id).or(ct::user_id_2.eq(id)))
+                .select((ct::id, ct::user_id_1, ct::user_id_2))
+                .load::<(contact::Id, user::Id, user::Id)>(conn)?
+                .into_iter()
+                .map(|(c, u1, u2)| if u1.eq(id) { (c, u2) } else { (c, u1) })
+                .collect::<Vec<_>>();
+
+            let owned = g::groups
+                .filter(g::owner.eq(id))
+                .select(g::id)
+                .load::<talk::Id>(conn)?;
+
+            let mut owners = Vec::with_capacity(owned.len());
+            for group_id in &owned {
+                // roles are ordered from owner to member, so admins take over first
+                let successor = gu::groups_users
+                    .filter(gu::group_id.eq(group_id).and(gu::user_id.ne(id)))
+                    .order((gu::role.asc(), gu::user_id))
+                    .select(gu::user_id)
+                    .first::<user::Id>(conn)
+                    .optional()?;
+
+                if let Some(s) = successor {
+                    update(g::groups.find(group_id))
+                        .set(g::owner.eq(&s))
+                        .execute(conn)?;
+
+                    update(gu::groups_users)
+                        .filter(gu::group_id.eq(group_id).and(gu::user_id.eq(&s)))
+                        .set(gu::role.eq(Role::Owner))
+                        .execute(conn)?;
+
+                    owners.push((group_id.clone(), s));
+                } else {
+                    delete(g::groups.find(group_id)).execute(conn)?;
+                    delete(t::talks.find(group_id)).execute(conn)?;
+                }
+            }
+
+            let groups = gu::groups_users
+                .filter(gu::user_id.eq(id))
+                .select(gu::group_id)
+                .load::<talk::Id>(conn)?;
+
+            update(m::messages)
+                .filter(m::owner.eq(id))
+                .set(m::owner.eq(user::Id::DELETED))
+                .execute(conn)?;
+
+            update(cu::chats_users)
+                .filter(cu::user_id.eq(id))
+                .set(cu::user_id.eq(user::Id::DELETED))
+                .execute(conn)?;
+
+            // memberships, contacts and everything else personal cascade
+            delete(u::users.find(id)).execute(conn)?;
+
+            Ok(Erasure {
+                chats,
+                groups,
+                owners,
+                contacts,
+            })
+        });
+
+        tx_res.map_err(super::Error::from)
+    }
 }
@@ -1,19 +1,28 @@
-use std::collections::HashSet;
+use std::{collections::HashSet, time::Duration};
 
 use async_trait::async_trait;
-use log::error;
+use chrono::{DateTime, Utc};
+use log::{debug, error};
+use uuid::Uuid;
 
 use crate::integration::cache;
 use crate::user::model::UserDto;
 use crate::{auth, contact, event, talk, user};
 
-use super::model::{NewUser, OnlineStatus};
-use super::{Nickname, Picture, Repository, Sub};
+use super::model::{ConnectionId, Erasure, IdpSync, NewUser, OnlineStatus, Privacy, ProfileUpdate};
+use super::{
+    BIO_MAX_LEN, Email, NAME_MAX_LEN, Nickname, Picture, Presence, Repository, SEARCH_PAGE_SIZE,
+    Sub,
+};
 
 #[async_trait]
 pub trait UserService {
     fn project(&self, user_info: &auth::UserInfo) -> super::Result<user::Id>;
 
+    /// Brings the stored user up to date with the IdP, projecting it when missing.
+    /// Name and nickname edited in the profile are kept.
+    async fn sync(&self, user_info: &auth::UserInfo) -> super::Result<user::Id>;
+
     async fn find_one(&self, id: &user::Id) -> super::Result<UserDto>;
 
     async fn find_by_sub(&self, sub: &Sub) -> super::Result<Option<UserDto>>;
@@ -28,11 +37,63 @@ pub trait UserService {
 
     fn exists(&self, id: &user::Id) -> super::Result<bool>;
 
-    fn search(&self, nickname: &Nickname, auth_user: &auth::User) -> super::Result<Vec<UserDto>>;
-
-    async fn notify_online(&self, id: &user::Id);
-
-    async fn notify_offline(&self, id: &user::Id);
+    /// Finds users by nickname, name or email, a page of `SEARCH_PAGE_SIZE` at a time,
+    /// leaving out those blocked in either direction or hiding from the user.
+    async fn search(
+        &self,
+        query: &str,
+        auth_user: &auth::User,
+        page: u32,
+    ) -> super::Result<Vec<UserDto>>;
+
+    /// Finds users by email address, those hiding from the viewer are left out.
+    fn find_by_emails(&self, emails: &[Email], viewer: &user::Id) -> super::Result<Vec<UserDto>>;
+
+    /// Saves the name, nickname and bio edited by the user.
+    /// Chat partners see a new name right away unless they gave the user an alias.
+    async fn update_profile(
+        &self,
+        auth_user: &auth::User,
+        name: &str,
+        nickname: &Nickname,
+        bio: Option<&str>,
+    ) -> super::Result<UserDto>;
+
+    /// Current presence of the user as their contacts see it.
+    async fn find_online_status(&self, id: &user::Id) -> super::Result<OnlineStatus>;
+
+    /// Status of the user as the viewer may see it. Only contacts and members
+    /// of a talk shared with the user see more than offline.
+    async fn find_online_status_for(
+        &self,
+        viewer: &user::Id,
+        id: &user::Id,
+    ) -> super::Result<OnlineStatus>;
+
+    async fn set_presence(&self, auth_user: &auth::User, p: Presence) -> super::Result<()>;
+
+    /// Records user interaction, postponing auto-away.
+    async fn touch(&self, id: &user::Id);
+
+    /// Keeps the connection alive and tells contacts the user is online.
+    async fn heartbeat(&self, id: &user::Id, conn: &ConnectionId);
+
+    /// Tells contacts the user went offline once their last connection is gone.
+    async fn disconnect(&self, id: &user::Id, conn: &ConnectionId);
+
+    async fn find_privacy(&self, id: &user::Id) -> super::Result<Privacy>;
+
+    /// Contacts are told about the change right away when the status gets hidden or shown.
+    async fn update_privacy(&self, auth_user: &auth::User, p: &Privacy) -> super::Result<()>;
+
+    /// Whether messages of the user in the talk must not show as seen,
+    /// because another member withholds read receipts.
+    fn receipts_withheld(&self, talk_id: &talk::Id, owner: &user::Id) -> super::Result<bool>;
+
+    /// Deletes the account for good. Contacts are removed and chat partners see
+    /// a deleted user in place of the former one, the groups left behind are
+    /// for the talks to announce.
+    async fn delete_account(&self, auth_user: &auth::User) -> super::Result<Erasure>;
 }
 
 #[derive(Clone)]
@@ -57,12 +118,67 @@ impl UserServiceImpl {
             redis,
         }
     }
+
+    /// Starts taking users whose connections expired offline in the background.
+    pub fn spawn_sweeper(&self) {
+        let s = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+            loop {
+                interval.tick().await;
+                s.sweep_expired().await;
+            }
+        });
+    }
 }
 
 #[async_trait]
 impl UserService for UserServiceImpl {
     fn project(&self, u: &auth::UserInfo) -> super::Result<user::Id> {
-        self.repo.create(&NewUser::from(u))
+        let new_user = NewUser::from(u);
+        match self.repo.create(&new_user) {
+            Err(super::Error::NicknameTaken(n)) => {
+                // the IdP does not know about nicknames chosen here
+                let nickname = format!("{n}-{}", &Uuid::new_v4().simple().to_string()[..6]);
+                debug!(
+                    "Nickname {n:?} is taken, projecting {:?} as {nickname:?}",
+                    u.sub()
+                );
+                self.repo.create(&new_user.with_nickname(&nickname))
+            }
+            r => r,
+        }
+    }
+
+    async fn sync(&self, ui: &auth::UserInfo) -> super::Result<user::Id> {
+        let Some(stored) = self.repo.find_by_sub(ui.sub())? else {
+            return self.project(ui);
+        };
+
+        let mut s = IdpSync::new(&stored, ui);
+        if let Some(n) = s.nickname()
+            && self.repo.nickname_taken(&Nickname::from(n), stored.id())?
+        {
+            debug!(
+                "IdP nickname of {:?} is taken, keeping the stored one",
+                ui.sub()
+            );
+            s = s.without_nickname();
+        }
+
+        if s.is_empty() {
+            return Ok(stored.id().clone());
+        }
+
+        debug!("Syncing {:?} with IdP", ui.sub());
+        let u = self.repo.sync(stored.id(), &s).map(UserDto::from)?;
+        self.invalidate(&u).await;
+
+        if s.renames() {
+            self.notify_renamed(&u).await;
+        }
+
+        Ok(u.id().clone())
     }
 
     async fn find_one(&self, id: &user::Id) -> super::Result<UserDto> {
@@ -142,26 +258,323 @@ impl UserService for UserServiceImpl {
         self.repo.exists(id)
     }
 
-    fn search(&self, nickname: &Nickname, auth_user: &auth::User) -> super::Result<Vec<UserDto>> {
-        let users = self
-            .repo
-            .find_by_nickname_like_and_excluding(nickname, auth_user.nickname())?;
+    async fn search(
+        &self,
+        query: &str,
+        auth_user: &auth::User,
+        page: u32,
+    ) -> super::Result<Vec<UserDto>> {
+        let mut exclude: Vec<user::Id> = self
+            .contact_service
+            .find_by_user_id(auth_user.id())
+            .await?
+            .into_iter()
+            .filter(|c| c.status().is_blocked())
+            .map(|c| c.recipient().clone())
+            .collect();
+        exclude.extend(self.contact_service.find_block_relations(auth_user.id())?);
+        exclude.push(user::Id::DELETED);
+
+        let users = self.repo.search(
+            query.trim(),
+            auth_user.id(),
+            &exclude,
+            SEARCH_PAGE_SIZE,
+            i64::from(page) * SEARCH_PAGE_SIZE,
+        )?;
 
         Ok(users.into_iter().map(UserDto::from).collect())
     }
 
-    async fn notify_online(&self, id: &user::Id) {
-        self.notify_online_status_change(id, true).await;
+    fn find_by_emails(&self, emails: &[Email], viewer: &user::Id) -> super::Result<Vec<UserDto>> {
+        if emails.is_empty() {
+            return Ok(Vec::with_capacity(0));
+        }
+
+        let users = self.repo.find_by_emails(emails, viewer)?;
+
+        Ok(users.into_iter().map(UserDto::from).collect())
+    }
+
+    async fn update_profile(
+        &self,
+        auth_user: &auth::User,
+        name: &str,
+        nickname: &Nickname,
+        bio: Option<&str>,
+    ) -> super::Result<UserDto> {
+        let name = name.trim();
+        if name.is_empty() || name.chars().count() > NAME_MAX_LEN {
+            return Err(super::Error::InvalidName);
+        }
+
+        let current = self.find_one(auth_user.id()).await?;
+
+        // nicknames from the IdP may not follow the rules, they stay as long as kept
+        if current.nickname().ne(nickname) {
+            nickname.validate()?;
+            if self.repo.nickname_taken(nickname, auth_user.id())? {
+                return Err(super::Error::NicknameTaken(nickname.0.clone()));
+            }
+        }
+
+        let bio = bio.map(str::trim).filter(|b| !b.is_empty());
+        if let Some(b) = bio
+            && b.chars().count() > BIO_MAX_LEN
+        {
+            return Err(super::Error::BioTooLong(b.chars().count()));
+        }
+        let u = self
+            .repo
+            .update_profile(
+                auth_user.id(),
+                &ProfileUpdate::new(&current, name, nickname, bio),
+            )
+            .map(UserDto::from)?;
+
+        self.invalidate(&u).await;
+
+        if u.name().ne(auth_user.name()) {
+            self.notify_renamed(&u).await;
+        }
+
+        Ok(u)
+    }
+
+    async fn find_online_status(&self, id: &user::Id) -> super::Result<OnlineStatus> {
+        let online = self.has_connections(id, Utc::now().timestamp()).await;
+        let last_seen = if online {
+            None
+        } else {
+            self.repo.find_last_seen(id)?
+        };
+
+        self.online_status(id, online, last_seen).await
+    }
+
+    async fn find_online_status_for(
+        &self,
+        viewer: &user::Id,
+        id: &user::Id,
+    ) -> super::Result<OnlineStatus> {
+        let visible = viewer.eq(id)
+            || matches!(
+                self.contact_service.find_relation(viewer, id)?,
+                contact::Relation::Contact
+            )
+            || self.repo.shares_talk(viewer, id)?;
+
+        if visible {
+            self.find_online_status(id).await
+        } else {
+            Ok(OnlineStatus::from_ref(id, false))
+        }
+    }
+
+    async fn set_presence(&self, auth_user: &auth::User, p: Presence) -> super::Result<()> {
+        self.repo.set_presence(auth_user.id(), p)?;
+        self.redis.json_del(cache::Key::User(auth_user.id())).await;
+
+        let status = self.find_online_status(auth_user.id()).await?;
+        self.notify_online_status_change(status).await;
+
+        Ok(())
+    }
+
+    async fn touch(&self, id: &user::Id) {
+        self.redis.set_ex(cache::Key::Activity(id), true).await;
+    }
+
+    async fn heartbeat(&self, id: &user::Id, conn: &ConnectionId) {
+        let expires_at = Utc::now().timestamp() + CONNECTION_TTL;
+
+        let key = cache::Key::Connections(id);
+        self.redis.zadd(key.clone(), conn, expires_at).await;
+        self.redis.expire(key).await;
+        self.redis
+            .zadd(cache::Key::OnlineUsers, id, expires_at)
+            .await;
+
+        match self.online_status(id, true, None).await {
+            Ok(status) => self.notify_online_status_change(status).await,
+            Err(e) => error!("failed to resolve online status of {id:?}: {e:?}"),
+        }
+    }
+
+    async fn disconnect(&self, id: &user::Id, conn: &ConnectionId) {
+        self.redis.zrem(cache::Key::Connections(id), conn).await;
+
+        if !self.has_connections(id, Utc::now().timestamp()).await
+            && self.redis.zrem(cache::Key::OnlineUsers, id).await
+        {
+            self.notify_offline(id).await;
+        }
+    }
+
+    async fn find_privacy(&self, id: &user::Id) -> super::Result<Privacy> {
+        let key = cache::Key::Privacy(id);
+        if let Some(p) = self.redis.json_get::<Privacy>(key.clone(), None).await {
+            return Ok(p);
+        }
+
+        let p = self
+            .repo
+            .find_privacy(id)?
+            .unwrap_or_else(|| Privacy::default_for(id));
+        self.redis.json_set_ex(key, &p).await;
+
+        Ok(p)
+    }
+
+    async fn update_privacy(&self, auth_user: &auth::User, p: &Privacy) -> super::Result<()> {
+        self.repo.save_privacy(p)?;
+        self.redis
+            .json_del(cache::Key::Privacy(auth_user.id()))
+            .await;
+
+        let status = self.find_online_status(auth_user.id()).await?;
+        self.notify_online_status_change(status).await;
+
+        Ok(())
+    }
+
+    fn receipts_withheld(&self, talk_id: &talk::Id, owner: &user::Id) -> super::Result<bool> {
+        self.repo.receipts_withheld(talk_id, owner)
+    }
+
+    async fn delete_account(&self, auth_user: &auth::User) -> super::Result<Erasure> {
+        let id = auth_user.id();
+        let u = self.find_one(id).await?;
+
+        let erasure = self.repo.delete_account(id)?;
+        debug!("Deleted account of {:?}", auth_user.sub());
+
+        self.invalidate(&u).await;
+        tokio::join!(
+            self.redis.del(cache::Key::Connections(id)),
+            self.redis.del(cache::Key::Activity(id)),
+            self.redis.json_del(cache::Key::Privacy(id)),
+            self.redis.json_del(cache::Key::Settings(id)),
+            self.redis.json_del(cache::Key::Contacts(id)),
+            self.redis.zrem(cache::Key::OnlineUsers, id),
+        );
+
+        for (contact_id, contact) in &erasure.contacts {
+            self.redis.json_del(cache::Key::Contacts(contact)).await;
+            self.event_service
+                .publish(
+                    &event::Subject::Notifications(contact),
+                    event::Notification::ContactRemoved {
+                        contact_id: contact_id.clone(),
+                    }
+                    .into(),
+                )
+                .await;
+        }
+
+        for talk_id in erasure
+            .groups
+            .iter()
+            .chain(erasure.chats.iter().map(|(talk_id, _)| talk_id))
+        {
+            self.redis.del(cache::Key::Members(talk_id)).await;
+        }
+
+        let deleted = self.find_one(&user::Id::DELETED).await?;
+        for (talk_id, partner) in &erasure.chats {
+            self.event_service
+                .publish(
+                    &event::Subject::Notifications(partner),
+                    event::Notification::TalkUpdated {
+                        talk_id: talk_id.clone(),
+                        name: deleted.name().to_string(),
+                        picture: talk::Picture::from(deleted.picture().clone()),
+                    }
+                    .into(),
+                )
+                .await;
+        }
+
+        Ok(erasure)
+    }
+}
+
+// connections
+impl UserServiceImpl {
+    /// Drops expired connections and tells whether any is left.
+    async fn has_connections(&self, id: &user::Id, now: i64) -> bool {
+        let key = cache::Key::Connections(id);
+        self.redis.zrembyscore(key.clone(), i64::MIN, now).await;
+        self.redis.zcount(key, now, i64::MAX).await > 0
+    }
+
+    /// Users whose connections all expired without a disconnect, e.g. when the
+    /// serving instance died. Removal from the online set is atomic, so only
+    /// one instance notifies.
+    async fn sweep_expired(&self) {
+        let now = Utc::now().timestamp();
+        let expired = self
+            .redis
+            .zrangebyscore::<user::Id, _>(cache::Key::OnlineUsers, i64::MIN, now)
+            .await;
+
+        for id in expired {
+            if self.has_connections(&id, now).await {
+                continue;
+            }
+
+            if self.redis.zrem(cache::Key::OnlineUsers, &id).await {
+                debug!("Connections of {id:?} expired");
+                self.notify_offline(&id).await;
+            }
+        }
     }
 
     async fn notify_offline(&self, id: &user::Id) {
-        self.notify_online_status_change(id, false).await;
+        let last_seen = Utc::now();
+
+        let (repo, user_id) = (self.repo.clone(), id.clone());
+        match tokio::task::spawn_blocking(move || repo.set_last_seen(&user_id, &last_seen)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => error!("failed to persist last seen of {id:?}: {e:?}"),
+            Err(e) => error!("failed to run persisting last seen of {id:?}: {e:?}"),
+        }
+
+        match self.online_status(id, false, Some(last_seen)).await {
+            Ok(status) => self.notify_online_status_change(status).await,
+            Err(e) => error!("failed to resolve online status of {id:?}: {e:?}"),
+        }
+    }
+}
+
+impl UserServiceImpl {
+    /// Available users without recent interaction are shown as away,
+    /// those not sharing their status as offline.
+    async fn online_status(
+        &self,
+        id: &user::Id,
+        online: bool,
+        last_seen: Option<DateTime<Utc>>,
+    ) -> super::Result<OnlineStatus> {
+        if !self.find_privacy(id).await?.share_presence() {
+            return Ok(OnlineStatus::from_ref(id, false));
+        }
+
+        let presence = match self.find_one(id).await?.presence() {
+            Presence::Available if online && !self.redis.exists(cache::Key::Activity(id)).await => {
+                Presence::Away
+            }
+            p => p,
+        };
+
+        Ok(OnlineStatus::new(id, online, presence, last_seen))
     }
 }
 
 // notifications
 impl UserServiceImpl {
-    async fn notify_online_status_change(&self, id: &user::Id, online: bool) {
+    async fn notify_online_status_change(&self, status: OnlineStatus) {
+        let id = status.id();
         match self
             .contact_service
             .find_by_user_id_and_status(id, &contact::Status::Accepted)
@@ -173,7 +586,6 @@ impl UserServiceImpl {
                     .map(|c| event::Subject::Notifications(c.recipient()))
                     .collect::<Vec<_>>();
 
-                let status = OnlineStatus::from_ref(id, online);
                 self.event_service
                     .broadcast(
                         &subjects,
@@ -186,10 +598,52 @@ impl UserServiceImpl {
             }
         }
     }
+
+    /// Renames the chats with the user in the talk lists of their partners,
+    /// those who know the user by an alias are left alone.
+    async fn notify_renamed(&self, u: &UserDto) {
+        let partners = match self.repo.find_chat_partners(u.id()) {
+            Ok(p) => p,
+            Err(e) => {
+                error!("failed to find chat partners of {:?}: {e:?}", u.id());
+                return;
+            }
+        };
+
+        for (talk_id, partner) in partners {
+            match self.contact_service.find_alias(&partner, u.id()) {
+                Ok(Some(_)) => continue,
+                Ok(None) => {}
+                Err(e) => {
+                    error!("failed to find alias for {partner:?}: {e:?}");
+                    continue;
+                }
+            }
+
+            self.event_service
+                .publish(
+                    &event::Subject::Notifications(&partner),
+                    event::Notification::TalkUpdated {
+                        talk_id,
+                        name: u.name().to_string(),
+                        picture: talk::Picture::from(u.picture().clone()),
+                    }
+                    .into(),
+                )
+                .await;
+        }
+    }
 }
 
 // cache operations
 impl UserServiceImpl {
+    async fn invalidate(&self, u: &UserDto) {
+        tokio::join!(
+            self.redis.json_del(cache::Key::User(u.id())),
+            self.redis.del(cache::Key::Sub(u.sub())),
+        );
+    }
+
     async fn cache(&self, u: &UserDto) {
         tokio::join!(
             self.redis.json_set_ex(cache::Key::User(u.id()), u),
@@ -226,3 +680,7 @@ impl UserServiceImpl {
             .map(|r| r.replace('\"', "")) // normalize json string
     }
 }
+
+/// Seconds a connection stays alive without a heartbeat, three missed online ticks.
+const CONNECTION_TTL: i64 = 45;
+const SWEEP_INTERVAL: Duration = Duration::from_secs(15);
@@ -8,7 +8,89 @@ function askNotificationPermission() {
   });
 }
 
+// Moves a talk to the top of its section once it receives a message,
+// keeping "Saved messages" first and pinned talks above the rest.
+function bumpTalk(id) {
+  var list = document.getElementById("talk-list");
+  var talk = document.getElementById("t-" + id);
+  if (!list || !talk || talk.parentElement !== list || talk.dataset.saved !== undefined) {
+    return;
+  }
+
+  var anchor = talk.dataset.pinned !== undefined
+    ? list.querySelector(".talk-item:not([data-saved])")
+    : list.querySelector(".talk-item:not([data-saved]):not([data-pinned])");
+  if (anchor && anchor !== talk) {
+    list.insertBefore(talk, anchor);
+  }
+}
+
+// Reports user interaction at most once a minute, idle users are shown as away.
+var lastActivity = 0;
+function reportActivity() {
+  var now = Date.now();
+  if (now - lastActivity < 60 * 1000) {
+    return;
+  }
+  lastActivity = now;
+  fetch("/api/users/activity", { method: "POST" });
+}
+
+// Settings of the user the browser applies itself, see the #preferences element.
+function preferences() {
+  return document.getElementById("preferences")?.dataset ?? {};
+}
+
+function applyPreferences() {
+  var p = preferences();
+  document.documentElement.dataset.theme = p.theme ?? "system";
+  document.documentElement.lang = p.locale ?? "en";
+  localizeTimes(document.body);
+}
+
+// Shows message times in the time zone and locale of the user.
+function localizeTimes(root) {
+  var p = preferences();
+  var format;
+  try {
+    format = new Intl.DateTimeFormat(p.locale, {
+      hour: "2-digit",
+      minute: "2-digit",
+      timeZone: p.timeZone,
+    });
+  } catch (e) {
+    // unknown zone, the UTC time stays
+    return;
+  }
+
+  root.querySelectorAll("time[datetime]").forEach(function (t) {
+    t.textContent = format.format(new Date(t.dateTime));
+  });
+}
+
 window.addEventListener("load", function () {
+  applyPreferences();
+
+  document.body.addEventListener("htmx:load", function (evt) {
+    localizeTimes(evt.detail.elt);
+  });
+
+  document.body.addEventListener("htmx:oobAfterSwap", function (evt) {
+    if (evt.detail.target.id === "preferences") {
+      applyPreferences();
+    }
+  });
+  ["keydown", "mousemove", "touchstart"].forEach(function (type) {
+    document.addEventListener(type, reportActivity, { passive: true });
+  });
+
+  document.body.addEventListener("htmx:sseMessage", function (evt) {
+    var [type, id] = evt.detail.type.split(":");
+    if (type === "newMessage" || type === "mutedMessage") {
+      bumpTalk(id);
+    }
+  });
+
   document.body.addEventListener("htmx:sseMessage", function (evt) {
     if (document.hasFocus()) {
       // don't push notifications if current tab is active
@@ -20,13 +102,24 @@ window.addEventListener("load", function () {
     }
 
     var notiType = evt.detail.type.split(":")[0];
+    var p = preferences();
 
+    // "mutedMessage" events of muted talks are intentionally not notified
     switch (notiType) {
       case "newMessage":
-        new Notification("You've got new message");
+        if (p.notifyMessages !== "false") {
+          new Notification("You've got new message");
+        }
         break;
       case "newTalk":
-        new Notification("You're invited to talk");
+        if (p.notifyTalks !== "false") {
+          new Notification("You're invited to talk");
+        }
+        break;
+      case "newContactRequest":
+        if (p.notifyContactRequests !== "false") {
+          new Notification("You've got a contact request");
+        }
         break;
     }
   });